@@ -69,6 +69,20 @@ pub enum Commands {
         /// optional - The path to run the command in, default will be where the command is run.
         #[clap(long, short)]
         path: Option<String>,
+
+        /// optional - Record every rpc request and response of the run into this directory.
+        #[clap(long)]
+        record: Option<String>,
+
+        /// optional - Answer every rpc request from a directory recorded with `--record`
+        /// instead of the rpc endpoints.
+        #[clap(long, conflicts_with = "record")]
+        replay: Option<String>,
+
+        /// optional - Hand the batches handlers skipped with `on_error: skip` back to them before
+        /// indexing starts.
+        #[clap(long)]
+        replay_failed_batches: bool,
     },
 
     /// Add elements such as contracts to the rindexer.yaml file.
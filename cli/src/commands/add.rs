@@ -179,6 +179,11 @@ pub async fn handle_add_contract_command(
             index_event_in_order: None,
             dependency_events: None,
             reorg_safe_distance: None,
+            include_block_timestamp: None,
+            include_tx_context: None,
+            logs_fetch_mode: None,
+            on_error: None,
+            functions: None,
             generate_csv: None,
             streams: None,
             chat: None,
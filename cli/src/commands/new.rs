@@ -193,16 +193,26 @@ pub fn handle_new_command(
         description: project_description,
         repository,
         project_type,
-        config: Config { buffer: None, callback_concurrency: None },
+        config: Config { buffer: None, callback_concurrency: None, backfill_segments: None },
         networks: vec![Network {
             name: "ethereum".to_string(),
             chain_id: 1,
-            rpc: "https://mainnet.gateway.tenderly.co".to_string(),
+            rpc: "https://mainnet.gateway.tenderly.co".into(),
+            chain_profile: None,
+            headers: None,
+            auth: None,
             block_poll_frequency: None,
+            live_subscription: None,
             compute_units_per_second: None,
+            compute_unit_costs: None,
             max_block_range: None,
             disable_logs_bloom_checks: None,
+            include_block_timestamp: None,
+            store_block_headers: None,
             get_logs_settings: None,
+            logs_source: None,
+            logs_archive: None,
+            reorg_safe_distance: None,
             reth: final_reth_config,
         }],
         contracts: vec![Contract {
@@ -223,11 +233,17 @@ pub fn handle_new_command(
             index_event_in_order: None,
             dependency_events: None,
             reorg_safe_distance: None,
+            include_block_timestamp: None,
+            include_tx_context: None,
+            logs_fetch_mode: None,
+            on_error: None,
+            functions: None,
             generate_csv: None,
             streams: None,
             chat: None,
         }],
         native_transfers: NativeTransfers::default(),
+        contract_deployments: Default::default(),
         phantom: None,
         global: None,
         storage: Storage {
@@ -238,6 +254,7 @@ pub fn handle_new_command(
                     relationships: None,
                     indexes: None,
                     disable_create_tables: None,
+                    atomic_checkpoint: None,
                 })
             } else {
                 None
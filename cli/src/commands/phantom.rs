@@ -536,21 +536,31 @@ async fn handle_phantom_deploy(
 
                 if let Some(index) = network_index {
                     let net = &mut manifest.networks[index];
-                    net.rpc = rpc_url.to_string();
+                    net.rpc = rpc_url.to_string().into();
                 } else {
                     manifest.networks.push(Network {
                         name: name.to_string(),
                         chain_id: network.unwrap().chain_id,
-                        rpc: rpc_url.to_string(),
+                        rpc: rpc_url.to_string().into(),
+                        chain_profile: None,
+                        headers: None,
+                        auth: None,
                         compute_units_per_second: None,
+                        compute_unit_costs: None,
                         block_poll_frequency: None,
+                        live_subscription: None,
                         max_block_range: if phantom.dyrpc_enabled() {
                             Some(U64::from(20_000))
                         } else {
                             Some(U64::from(2_000))
                         },
                         disable_logs_bloom_checks: None,
+                        include_block_timestamp: None,
+                        store_block_headers: None,
                         get_logs_settings: None,
+                        logs_source: None,
+                        logs_archive: None,
+                        reorg_safe_distance: None,
                         reth: None,
                     });
                 }
@@ -7,6 +7,7 @@ use rindexer::{
     },
     rindexer_error, rindexer_info, setup_info_logger, start_rindexer_no_code,
     GraphqlOverrideSettings, IndexerNoCodeDetails, PostgresClient, StartNoCodeDetails,
+    RPC_RECORD_ENV, RPC_REPLAY_ENV,
 };
 
 use crate::{
@@ -111,12 +112,29 @@ fn start_docker_compose(project_path: &PathBuf) -> Result<(), String> {
     check_docker_compose_status(project_path, 200)
 }
 
+/// Hands the cassette directory to the providers through the environment, so a rust project run
+/// with `cargo run` picks it up as well.
+fn use_rpc_cassette(record: Option<&str>, replay: Option<&str>) -> Result<(), std::io::Error> {
+    for (env_name, dir) in [(RPC_RECORD_ENV, record), (RPC_REPLAY_ENV, replay)] {
+        if let Some(dir) = dir {
+            env::set_var(env_name, env::current_dir()?.join(dir));
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn start(
     project_path: PathBuf,
     command: &StartSubcommands,
+    record: Option<&str>,
+    replay: Option<&str>,
+    replay_failed_batches: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     setup_info_logger();
 
+    use_rpc_cassette(record, replay)?;
+
     validate_rindexer_yaml_exist(&project_path);
 
     let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
@@ -151,21 +169,29 @@ pub async fn start(
     match manifest.project_type {
         ProjectType::Rust => {
             let project_cargo_manifest_path = project_path.join("Cargo.toml");
+            let mut run_args = match command {
+                StartSubcommands::Indexer => "-- --indexer".to_string(),
+                StartSubcommands::Graphql { port } => match port {
+                    Some(port) => format!("-- --graphql --port={port}"),
+                    None => "-- --graphql".to_string(),
+                },
+                StartSubcommands::All { port } => match port {
+                    Some(port) => format!("-- --port={port}"),
+                    None => "".to_string(),
+                },
+            };
+            if replay_failed_batches {
+                if run_args.is_empty() {
+                    run_args.push_str("--");
+                }
+                run_args.push_str(" --replay-failed-batches");
+            }
+
             let status = Command::new("cargo")
                 .arg("run")
                 .arg("--manifest-path")
                 .arg(project_cargo_manifest_path)
-                .arg(match command {
-                    StartSubcommands::Indexer => "-- --indexer".to_string(),
-                    StartSubcommands::Graphql { port } => match port {
-                        Some(port) => format!("-- --graphql --port={port}"),
-                        None => "-- --graphql".to_string(),
-                    },
-                    StartSubcommands::All { port } => match port {
-                        Some(port) => format!("-- --port={port}"),
-                        None => "".to_string(),
-                    },
-                })
+                .arg(run_args)
                 .status()
                 .expect("Failed to execute cargo run.");
 
@@ -177,7 +203,7 @@ pub async fn start(
             StartSubcommands::Indexer => {
                 let details = StartNoCodeDetails {
                     manifest_path: &project_path.join(YAML_CONFIG_NAME),
-                    indexing_details: IndexerNoCodeDetails { enabled: true },
+                    indexing_details: IndexerNoCodeDetails { enabled: true, replay_failed_batches },
                     graphql_details: GraphqlOverrideSettings {
                         enabled: false,
                         override_port: None,
@@ -192,7 +218,10 @@ pub async fn start(
             StartSubcommands::Graphql { port } => {
                 let details = StartNoCodeDetails {
                     manifest_path: &project_path.join(YAML_CONFIG_NAME),
-                    indexing_details: IndexerNoCodeDetails { enabled: false },
+                    indexing_details: IndexerNoCodeDetails {
+                        enabled: false,
+                        replay_failed_batches: false,
+                    },
                     graphql_details: GraphqlOverrideSettings {
                         enabled: true,
                         override_port: port.as_ref().and_then(|port| port.parse().ok()),
@@ -207,7 +236,7 @@ pub async fn start(
             StartSubcommands::All { port } => {
                 let details = StartNoCodeDetails {
                     manifest_path: &project_path.join(YAML_CONFIG_NAME),
-                    indexing_details: IndexerNoCodeDetails { enabled: true },
+                    indexing_details: IndexerNoCodeDetails { enabled: true, replay_failed_batches },
                     graphql_details: GraphqlOverrideSettings {
                         enabled: true,
                         override_port: port.as_ref().and_then(|port| port.parse().ok()),
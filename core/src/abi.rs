@@ -232,7 +232,11 @@ impl ABIItem {
         let filtered_abi_items = match &contract.include_events {
             Some(events) => abi_items
                 .into_iter()
-                .filter(|item| item.type_ != "event" || events.contains(&item.name))
+                .filter(|item| {
+                    item.type_ != "event"
+                        || events.contains(&item.name)
+                        || contract.function_for_event(&item.name).is_some()
+                })
                 .collect(),
             None => abi_items,
         };
@@ -262,7 +266,11 @@ impl ABIItem {
 
             abi_items = abi_items
                 .iter()
-                .filter(|item| item.type_ == "event" && filter_event_names.contains(&item.name))
+                .filter(|item| {
+                    item.type_ == "event"
+                        && (filter_event_names.contains(&item.name)
+                            || contract.function_for_event(&item.name).is_some())
+                })
                 .cloned()
                 .collect();
         }
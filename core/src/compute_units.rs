@@ -0,0 +1,217 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportFut},
+};
+use tower::{Layer, Service};
+
+/// What each method costs when the network does not set `compute_unit_costs`, roughly following
+/// the compute unit pricing of the larger rpc providers.
+const DEFAULT_COMPUTE_UNIT_COSTS: &[(&str, u64)] = &[
+    ("eth_getLogs", 75),
+    ("eth_getBlockByNumber", 16),
+    ("eth_getBlockByHash", 16),
+    ("eth_getBlockReceipts", 500),
+    ("eth_getTransactionReceipt", 15),
+    ("eth_call", 26),
+    ("eth_blockNumber", 10),
+    ("eth_chainId", 0),
+    ("trace_block", 24),
+    ("debug_traceBlockByNumber", 309),
+];
+
+/// What a method missing from the cost table costs.
+const DEFAULT_METHOD_COST: u64 = 10;
+
+#[derive(Debug)]
+struct Bucket {
+    // can go negative, requests which took more than was left wait for the bucket to refill
+    units: f64,
+    refilled_at: Instant,
+}
+
+/// A token bucket of compute units shared by every request of a network, so indexing stays
+/// inside the rpc provider's plan instead of reacting to rate limit errors.
+#[derive(Debug)]
+pub struct ComputeUnitLimiter {
+    units_per_second: f64,
+    costs: HashMap<String, u64>,
+    bucket: Mutex<Bucket>,
+}
+
+impl ComputeUnitLimiter {
+    pub fn new(
+        compute_units_per_second: u64,
+        cost_overrides: Option<&BTreeMap<String, u64>>,
+    ) -> Self {
+        let mut costs = DEFAULT_COMPUTE_UNIT_COSTS
+            .iter()
+            .map(|(method, cost)| (method.to_string(), *cost))
+            .collect::<HashMap<_, _>>();
+        if let Some(cost_overrides) = cost_overrides {
+            costs.extend(cost_overrides.iter().map(|(method, cost)| (method.clone(), *cost)));
+        }
+
+        let units_per_second = compute_units_per_second as f64;
+        Self {
+            units_per_second,
+            costs,
+            bucket: Mutex::new(Bucket { units: units_per_second, refilled_at: Instant::now() }),
+        }
+    }
+
+    /// The compute units of a request, a batch costs the sum of its requests. Capped at a
+    /// second of budget so a request costing more than that is still sent.
+    pub fn cost(&self, request: &RequestPacket) -> u64 {
+        let cost = request
+            .method_names()
+            .map(|method| self.costs.get(method).copied().unwrap_or(DEFAULT_METHOD_COST))
+            .sum::<u64>();
+
+        cost.min(self.units_per_second as u64)
+    }
+
+    /// Takes `cost` units from the bucket, returning how long the request has to wait before it
+    /// can be sent.
+    fn reserve(&self, cost: u64, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.units = (bucket.units + elapsed * self.units_per_second).min(self.units_per_second);
+        bucket.refilled_at = now;
+        bucket.units -= cost as f64;
+
+        if bucket.units >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.units / self.units_per_second)
+        }
+    }
+
+    /// Waits until the network has the compute units for a request.
+    pub async fn acquire(&self, cost: u64) {
+        let wait = self.reserve(cost, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Holds every request back until the network's [`ComputeUnitLimiter`] has the units for it,
+/// without a limiter requests are sent straight away.
+#[derive(Debug, Clone)]
+pub struct ComputeUnitLayer {
+    limiter: Option<Arc<ComputeUnitLimiter>>,
+}
+
+impl ComputeUnitLayer {
+    pub fn new(limiter: Option<Arc<ComputeUnitLimiter>>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for ComputeUnitLayer {
+    type Service = ComputeUnitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ComputeUnitService { limiter: self.limiter.clone(), inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ComputeUnitService<S> {
+    limiter: Option<Arc<ComputeUnitLimiter>>,
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for ComputeUnitService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let Some(limiter) = self.limiter.clone() else {
+            return self.inner.call(req);
+        };
+
+        // the inner service was polled ready, keep that one and leave a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            limiter.acquire(limiter.cost(&req)).await;
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::json_rpc::{Id, Request};
+
+    use super::*;
+
+    fn request(method: &'static str) -> RequestPacket {
+        let request: Request<()> = Request::new(method, Id::Number(1), ());
+        RequestPacket::Single(request.serialize().unwrap())
+    }
+
+    #[test]
+    fn test_method_costs() {
+        let overrides = BTreeMap::from([("eth_getLogs".to_string(), 100)]);
+        let limiter = ComputeUnitLimiter::new(1000, Some(&overrides));
+
+        assert_eq!(limiter.cost(&request("eth_getLogs")), 100);
+        assert_eq!(limiter.cost(&request("eth_call")), 26);
+        assert_eq!(limiter.cost(&request("eth_unknownMethod")), DEFAULT_METHOD_COST);
+        assert_eq!(
+            limiter.cost(&RequestPacket::Batch(vec![
+                Request::<()>::new("eth_call", Id::Number(1), ()).serialize().unwrap(),
+                Request::<()>::new("eth_call", Id::Number(2), ()).serialize().unwrap(),
+            ])),
+            52
+        );
+    }
+
+    #[test]
+    fn test_cost_is_capped_at_one_second() {
+        let limiter = ComputeUnitLimiter::new(50, None);
+
+        assert_eq!(limiter.cost(&request("eth_getLogs")), 50);
+    }
+
+    #[test]
+    fn test_bucket_waits_for_refill() {
+        let limiter = ComputeUnitLimiter::new(100, None);
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(75, now), Duration::ZERO);
+        assert_eq!(limiter.reserve(75, now), Duration::from_millis(500));
+        // the second request already took the units of the next half second
+        assert_eq!(
+            limiter.reserve(50, now + Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(limiter.reserve(100, now + Duration::from_secs(3)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use alloy::primitives::{B256, U256, U64};
+use chrono::DateTime;
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle};
+use tracing::{debug, error};
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError, ToSql},
+        sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    provider::BlockHeader,
+};
+
+/// Postgres caps a statement at 65535 parameters, each header takes 6.
+const MAX_HEADERS_PER_UPSERT: usize = 1000;
+
+pub fn generate_blocks_table_sql() -> String {
+    r#"CREATE TABLE IF NOT EXISTS rindexer_internal.blocks ("network" TEXT NOT NULL, "number" NUMERIC NOT NULL, "hash" CHAR(66) NOT NULL, "parent_hash" CHAR(66) NOT NULL, "timestamp" TIMESTAMPTZ NOT NULL, "base_fee" NUMERIC, PRIMARY KEY ("network", "number"));
+CREATE INDEX IF NOT EXISTS blocks_hash_idx ON rindexer_internal.blocks ("hash");"#
+        .to_string()
+}
+
+/// Upserts the headers into `rindexer_internal.blocks`. A header for a height which is already
+/// stored replaces it, so the table follows the canonical chain through reorgs.
+pub async fn upsert_block_headers(
+    postgres: &PostgresClient,
+    network: &str,
+    headers: Vec<BlockHeader>,
+) -> Result<u64, PostgresError> {
+    // a reorg can hand over two headers for the same height in one batch, postgres refuses to
+    // update the same row twice in one statement so only the latest one is kept
+    let headers =
+        headers.into_iter().map(|header| (header.number, header)).collect::<BTreeMap<_, _>>();
+
+    let mut query = r#"INSERT INTO rindexer_internal.blocks ("network", "number", "hash", "parent_hash", "timestamp", "base_fee") VALUES "#.to_string();
+    let mut values: Vec<EthereumSqlTypeWrapper> = Vec::with_capacity(headers.len() * 6);
+
+    for (i, header) in headers.into_values().enumerate() {
+        if i > 0 {
+            query.push(',');
+        }
+        let placeholders = (1..=6).map(|j| format!("${}", i * 6 + j)).collect::<Vec<_>>();
+        query.push_str(&format!("({})", placeholders.join(",")));
+
+        values.extend([
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            EthereumSqlTypeWrapper::U64(header.number),
+            EthereumSqlTypeWrapper::B256(header.hash),
+            EthereumSqlTypeWrapper::B256(header.parent_hash),
+            EthereumSqlTypeWrapper::DateTime(
+                DateTime::from_timestamp(header.timestamp as i64, 0).unwrap_or_default(),
+            ),
+            EthereumSqlTypeWrapper::U256NumericNullable(header.base_fee.map(U256::from)),
+        ]);
+    }
+
+    query.push_str(
+        r#" ON CONFLICT ("network", "number") DO UPDATE SET "hash" = EXCLUDED."hash", "parent_hash" = EXCLUDED."parent_hash", "timestamp" = EXCLUDED."timestamp", "base_fee" = EXCLUDED."base_fee""#,
+    );
+
+    let params: Vec<&(dyn ToSql + Sync)> =
+        values.iter().map(|value| value as &(dyn ToSql + Sync)).collect();
+
+    postgres.execute(&query, &params).await
+}
+
+/// The hash and parent hash of the last `limit` blocks stored for the network up to `to_block`,
+/// lowest first. Live indexing seeds its reorg detection with them so a reorg which happened
+/// while rindexer was down is still noticed.
+pub async fn load_block_hashes(
+    postgres: &PostgresClient,
+    network: &str,
+    to_block: U64,
+    limit: u64,
+) -> Result<Vec<(u64, B256, B256)>, PostgresError> {
+    let rows = postgres
+        .query(
+            r#"SELECT "number"::BIGINT AS "number", "hash", "parent_hash" FROM rindexer_internal.blocks WHERE "network" = $1 AND "number" <= $2 ORDER BY "number" DESC LIMIT $3"#,
+            &[&network, &EthereumSqlTypeWrapper::U64(to_block), &(limit as i64)],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .rev()
+        .filter_map(|row| {
+            let number: i64 = row.get("number");
+            let hash: String = row.get("hash");
+            let parent_hash: String = row.get("parent_hash");
+            Some((number as u64, hash.parse().ok()?, parent_hash.parse().ok()?))
+        })
+        .collect())
+}
+
+/// Removes the headers above `fork_block`, they belong to a branch which was reorged out and the
+/// canonical headers are stored again as they are fetched.
+pub async fn delete_block_headers_above(
+    postgres: &PostgresClient,
+    network: &str,
+    fork_block: U64,
+) -> Result<u64, PostgresError> {
+    postgres
+        .execute(
+            r#"DELETE FROM rindexer_internal.blocks WHERE "network" = $1 AND "number" > $2"#,
+            &[&network, &EthereumSqlTypeWrapper::U64(fork_block)],
+        )
+        .await
+}
+
+/// Drains the headers a network provider publishes into `rindexer_internal.blocks`, batching
+/// whatever has queued up since the last write.
+pub fn spawn_block_headers_writer(
+    postgres: Arc<PostgresClient>,
+    network: String,
+    mut headers_rx: UnboundedReceiver<BlockHeader>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(header) = headers_rx.recv().await {
+            let mut headers = vec![header];
+            while headers.len() < MAX_HEADERS_PER_UPSERT {
+                match headers_rx.try_recv() {
+                    Ok(header) => headers.push(header),
+                    Err(_) => break,
+                }
+            }
+
+            let count = headers.len();
+            match upsert_block_headers(&postgres, &network, headers).await {
+                Ok(_) => debug!("{} - Stored {} block headers", network, count),
+                Err(e) => error!("{} - Error storing {} block headers: {}", network, count, e),
+            }
+        }
+    })
+}
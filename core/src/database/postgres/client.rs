@@ -1,10 +1,10 @@
-use std::{env, future::Future, time::Duration};
+use std::{env, time::Duration};
 
 use bb8::{Pool, RunError};
 use bb8_postgres::PostgresConnectionManager;
 use bytes::Buf;
 use dotenv::dotenv;
-use futures::pin_mut;
+use futures::{future::BoxFuture, pin_mut};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
 use tokio::{task, time::timeout};
@@ -55,21 +55,106 @@ pub enum PostgresError {
     ConnectionPoolError(#[from] RunError<tokio_postgres::Error>),
 }
 
-#[allow(unused)]
 pub struct PostgresTransaction<'a> {
     pub transaction: PgTransaction<'a>,
 }
 
 impl PostgresTransaction<'_> {
-    #[allow(unused)]
     pub async fn execute(
-        &mut self,
+        &self,
         query: &str,
         params: &[&(dyn ToSql + Sync)],
     ) -> Result<u64, PostgresError> {
         self.transaction.execute(query, params).await.map_err(PostgresError::PgError)
     }
 
+    /// Inserts the rows with a single `INSERT`, rows which already exist are skipped.
+    pub async fn bulk_insert(
+        &self,
+        table_name: &str,
+        column_names: &[String],
+        bulk_data: &[Vec<EthereumSqlTypeWrapper>],
+    ) -> Result<u64, PostgresError> {
+        let (query, params) = build_bulk_insert_query(table_name, column_names, bulk_data);
+        self.transaction.execute(&query, &params).await.map_err(PostgresError::PgError)
+    }
+
+    /// `COPY` can not skip conflicting rows so the data is copied into a temp staging table
+    /// which is dropped on commit and then moved over with `ON CONFLICT DO NOTHING`.
+    pub async fn bulk_insert_via_copy(
+        &self,
+        table_name: &str,
+        column_names: &[String],
+        column_types: &[PgType],
+        data: &[Vec<EthereumSqlTypeWrapper>],
+    ) -> Result<(), BulkInsertPostgresError> {
+        let columns = generate_event_table_columns_names_sql(column_names);
+        let staging_table_name = format!("rindexer_staging_{}", table_name.replace('.', "_"));
+
+        self.transaction
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE {staging_table_name} ON COMMIT DROP AS SELECT {columns} FROM {table_name} WITH NO DATA;"
+            ))
+            .await?;
+
+        let stmt = format!("COPY {staging_table_name} ({columns}) FROM STDIN WITH (FORMAT binary)");
+
+        let prepared_data: Vec<Vec<&(dyn ToSql + Sync)>> = data
+            .iter()
+            .map(|row| row.iter().map(|param| param as &(dyn ToSql + Sync)).collect())
+            .collect();
+
+        let sink: CopyInSink<bytes::Bytes> = self.transaction.copy_in(&stmt).await?;
+
+        let writer = BinaryCopyInWriter::new(sink, column_types);
+        pin_mut!(writer);
+
+        // This can cause issues with Binary Copy command not completing and leaving hanging
+        // processes. See similar: https://github.com/sfackler/rust-postgres/issues/1109
+        //
+        // We have to call `finish` manually on any write error.
+        for row in prepared_data.iter() {
+            if let Err(e) = writer.as_mut().write(row).await {
+                error!("Error writing binary data, aborting early: {}", e);
+                writer.finish().await?;
+                return Err(e)?;
+            };
+        }
+
+        writer.finish().await?;
+
+        self.transaction
+            .batch_execute(&format!(
+                "INSERT INTO {table_name} ({columns}) SELECT {columns} FROM {staging_table_name} ON CONFLICT DO NOTHING;
+                 DROP TABLE {staging_table_name};"
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Same as [`PostgresClient::insert_bulk`] but runs inside this transaction.
+    pub async fn insert_bulk(
+        &self,
+        table_name: &str,
+        columns: &[String],
+        postgres_bulk_data: &[Vec<EthereumSqlTypeWrapper>],
+    ) -> Result<(), BulkInsertPostgresError> {
+        if postgres_bulk_data.is_empty() {
+            return Ok(());
+        }
+
+        if postgres_bulk_data.len() > 100 {
+            let column_types: Vec<PgType> =
+                postgres_bulk_data[0].iter().map(|param| param.to_type()).collect();
+
+            self.bulk_insert_via_copy(table_name, columns, &column_types, postgres_bulk_data).await
+        } else {
+            self.bulk_insert(table_name, columns, postgres_bulk_data).await?;
+            Ok(())
+        }
+    }
+
     #[allow(unused)]
     pub async fn commit(self) -> Result<(), PostgresError> {
         self.transaction.commit().await.map_err(PostgresError::PgError)
@@ -81,6 +166,41 @@ impl PostgresTransaction<'_> {
     }
 }
 
+/// Builds a multi row `INSERT` which skips rows that conflict with an existing unique key.
+fn build_bulk_insert_query<'a>(
+    table_name: &str,
+    column_names: &[String],
+    bulk_data: &'a [Vec<EthereumSqlTypeWrapper>],
+) -> (String, Vec<&'a (dyn ToSql + Sync)>) {
+    let total_columns = column_names.len();
+
+    let mut query = format!(
+        "INSERT INTO {} ({}) VALUES ",
+        table_name,
+        generate_event_table_columns_names_sql(column_names),
+    );
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+
+    for (i, row) in bulk_data.iter().enumerate() {
+        if i > 0 {
+            query.push(',');
+        }
+        let mut placeholders = vec![];
+        for j in 0..total_columns {
+            placeholders.push(format!("${}", i * total_columns + j + 1));
+        }
+        query.push_str(&format!("({})", placeholders.join(",")));
+
+        for param in row {
+            params.push(param as &(dyn ToSql + Sync));
+        }
+    }
+
+    query.push_str(" ON CONFLICT DO NOTHING");
+
+    (query, params)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum BulkInsertPostgresError {
     #[error("{0}")]
@@ -195,25 +315,21 @@ impl PostgresClient {
         conn.prepare_typed(query, parameter_types).await.map_err(PostgresError::PgError)
     }
 
-    pub async fn with_transaction<F, Fut, T, Q>(
-        &self,
-        query: &Q,
-        params: &[&(dyn ToSql + Sync)],
-        f: F,
-    ) -> Result<T, PostgresError>
+    /// Runs `f` inside a single transaction which is only committed if `f` succeeds, so
+    /// everything written through the [`PostgresTransaction`] lands together or not at all.
+    pub async fn with_transaction<F, T, E>(&self, f: F) -> Result<T, E>
     where
-        F: FnOnce(u64) -> Fut + Send,
-        Fut: Future<Output = Result<T, PostgresError>> + Send,
-        Q: ?Sized + ToStatement,
+        F: for<'a> FnOnce(&'a PostgresTransaction<'a>) -> BoxFuture<'a, Result<T, E>>,
+        E: From<PostgresError>,
     {
         let mut conn = self.pool.get().await.map_err(PostgresError::ConnectionPoolError)?;
-        let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
+        let transaction = PostgresTransaction {
+            transaction: conn.transaction().await.map_err(PostgresError::PgError)?,
+        };
 
-        let count = transaction.execute(query, params).await.map_err(PostgresError::PgError)?;
+        let result = f(&transaction).await?;
 
-        let result = f(count).await?;
-
-        transaction.commit().await.map_err(PostgresError::PgError)?;
+        transaction.commit().await?;
 
         Ok(result)
     }
@@ -295,39 +411,13 @@ impl PostgresClient {
         column_types: &[PgType],
         data: &[Vec<EthereumSqlTypeWrapper>],
     ) -> Result<(), BulkInsertPostgresError> {
-        let stmt = format!(
-            "COPY {} ({}) FROM STDIN WITH (FORMAT binary)",
-            table_name,
-            generate_event_table_columns_names_sql(column_names),
-        );
-
-        // info!("Bulk insert statement: {}", stmt);
-
-        let prepared_data: Vec<Vec<&(dyn ToSql + Sync)>> = data
-            .iter()
-            .map(|row| row.iter().map(|param| param as &(dyn ToSql + Sync)).collect())
-            .collect();
-
-        // info!("Prepared data: {:?}", prepared_data);
-
-        let sink = self.copy_in(&stmt).await?;
-
-        let writer = BinaryCopyInWriter::new(sink, column_types);
-        pin_mut!(writer);
-
-        // This can cause issues with Binary Copy command not completing and leaving hanging
-        // processes. See similar: https://github.com/sfackler/rust-postgres/issues/1109
-        //
-        // We have to call `finish` manually on any write error.
-        for row in prepared_data.iter() {
-            if let Err(e) = writer.as_mut().write(row).await {
-                error!("Error writing binary data, aborting early: {}", e);
-                writer.finish().await?;
-                return Err(e)?;
-            };
-        }
+        let mut conn = self.pool.get().await.map_err(PostgresError::ConnectionPoolError)?;
+        let transaction = PostgresTransaction {
+            transaction: conn.transaction().await.map_err(PostgresError::PgError)?,
+        };
 
-        writer.finish().await?;
+        transaction.bulk_insert_via_copy(table_name, column_names, column_types, data).await?;
+        transaction.commit().await?;
 
         Ok(())
     }
@@ -338,8 +428,6 @@ impl PostgresClient {
         column_names: &[String],
         bulk_data: &[Vec<EthereumSqlTypeWrapper>],
     ) -> Result<u64, PostgresError> {
-        let total_columns = column_names.len();
-
         // good for debugging
         // for (i, row) in bulk_data.iter().enumerate() {
         //     for (j, param) in row.iter().enumerate() {
@@ -354,27 +442,7 @@ impl PostgresClient {
         //     }
         // }
 
-        let mut query = format!(
-            "INSERT INTO {} ({}) VALUES ",
-            table_name,
-            generate_event_table_columns_names_sql(column_names),
-        );
-        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
-
-        for (i, row) in bulk_data.iter().enumerate() {
-            if i > 0 {
-                query.push(',');
-            }
-            let mut placeholders = vec![];
-            for j in 0..total_columns {
-                placeholders.push(format!("${}", i * total_columns + j + 1));
-            }
-            query.push_str(&format!("({})", placeholders.join(",")));
-
-            for param in row {
-                params.push(param as &(dyn ToSql + Sync));
-            }
-        }
+        let (query, params) = build_bulk_insert_query(table_name, column_names, bulk_data);
 
         // Good for debugging
         // tracing::info!("query: {:?}", query);
@@ -7,6 +7,7 @@ use crate::{
     abi::{ABIInput, ABIItem, EventInfo, GenerateAbiPropertiesType, ParamTypeError, ReadAbiError},
     helpers::camel_to_snake,
     indexer::{
+        contract_deployment::CONTRACT_CREATED_ABI,
         native_transfer::{NATIVE_TRANSFER_ABI, NATIVE_TRANSFER_CONTRACT_NAME},
         Indexer,
     },
@@ -60,11 +61,42 @@ pub fn generate_column_names_only_with_base_properties(inputs: &[ABIInput]) -> V
     column_names
 }
 
+/// The columns holding the transaction of an event, only added to the tables of contracts with
+/// `include_tx_context` enabled. `tx_to` is `NULL` for contract creations.
+pub const TX_CONTEXT_COLUMNS: [(&str, &str); 6] = [
+    ("tx_from", "CHAR(42)"),
+    ("tx_to", "CHAR(42)"),
+    ("tx_value", "NUMERIC"),
+    ("gas_used", "NUMERIC"),
+    ("effective_gas_price", "NUMERIC"),
+    ("tx_status", "BOOLEAN"),
+];
+
+/// The unique key on `network`, `tx_hash` and `log_index` which lets inserts skip rows that are
+/// already stored. Tables created before the key existed may hold duplicates from re-indexing a
+/// range, they are removed once before the key is added.
+fn generate_unique_log_key_sql(schema_name: &str, table_name: &str, event_name: &str) -> String {
+    let index_name =
+        compact_table_name_if_needed(format!("{}_unique_log_key", camel_to_snake(event_name)));
+
+    format!(
+        "DO $$ BEGIN \
+            IF NOT EXISTS (SELECT 1 FROM pg_indexes WHERE schemaname = '{schema_name}' AND indexname = '{index_name}') THEN \
+                DELETE FROM {table_name} a USING {table_name} b \
+                WHERE a.rindexer_id > b.rindexer_id AND a.network = b.network AND a.tx_hash = b.tx_hash AND a.log_index = b.log_index; \
+                CREATE UNIQUE INDEX {index_name} ON {table_name} (network, tx_hash, log_index); \
+            END IF; \
+        END $$;"
+    )
+}
+
 fn generate_event_table_sql_with_comments(
     abi_inputs: &[EventInfo],
     contract_name: &str,
     schema_name: &str,
     apply_full_name_comment_for_events: Vec<String>,
+    include_tx_context: bool,
+    unique_log_key: bool,
 ) -> String {
     abi_inputs
         .iter()
@@ -87,10 +119,36 @@ fn generate_event_table_sql_with_comments(
                 block_hash CHAR(66) NOT NULL, \
                 network VARCHAR(50) NOT NULL, \
                 tx_index NUMERIC NOT NULL, \
-                log_index VARCHAR(78) NOT NULL\
-            );"
+                log_index VARCHAR(78) NOT NULL, \
+                block_timestamp TIMESTAMPTZ\
+            );\
+            ALTER TABLE {table_name} ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMPTZ;"
             );
 
+            let create_table_sql = if unique_log_key {
+                format!(
+                    "{create_table_sql}{}",
+                    generate_unique_log_key_sql(schema_name, &table_name, &event_info.name)
+                )
+            } else {
+                create_table_sql
+            };
+
+            // added afterwards so enabling `include_tx_context` later on migrates the table
+            let create_table_sql = if include_tx_context {
+                let tx_context_columns = TX_CONTEXT_COLUMNS
+                    .iter()
+                    .map(|(name, data_type)| {
+                        format!(
+                            "ALTER TABLE {table_name} ADD COLUMN IF NOT EXISTS {name} {data_type};"
+                        )
+                    })
+                    .collect::<String>();
+                format!("{create_table_sql}{tx_context_columns}")
+            } else {
+                create_table_sql
+            };
+
             if !apply_full_name_comment_for_events.contains(&event_info.name) {
                 return create_table_sql;
             }
@@ -111,7 +169,7 @@ fn generate_internal_factory_event_table_sql(
     indexer_name: &str,
     factories: &[FactoryDetailsYaml],
 ) -> String {
-    factories.iter().map(|factory| {
+    factories.iter().flat_map(|factory| factory.levels()).map(|factory| {
         let params = GenerateInternalFactoryEventTableNameParams {
             indexer_name: indexer_name.to_string(),
             contract_name: factory.name.to_string(),
@@ -137,7 +195,8 @@ fn generate_internal_event_table_sql(
         let table_name = generate_internal_event_table_name(schema_name, &event_info.name);
 
         let create_table_query = format!(
-            r#"CREATE TABLE IF NOT EXISTS rindexer_internal.{table_name} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);"#
+            r#"CREATE TABLE IF NOT EXISTS rindexer_internal.{table_name} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC, "synced_segments" TEXT);
+            ALTER TABLE rindexer_internal.{table_name} ADD COLUMN IF NOT EXISTS "synced_segments" TEXT;"#
         );
 
         let insert_queries = networks.iter().map(|network| {
@@ -198,6 +257,48 @@ fn find_clashing_event_names(
     Ok(clashing_events)
 }
 
+/// The tables of the events of the imaginary `EvmTraces` contract, which only exist in the `abi_str`
+/// rindexer invented for them.
+fn generate_trace_tables_sql(
+    project_path: &Path,
+    indexer: &Indexer,
+    abi_str: &str,
+    networks: Vec<&str>,
+    disable_event_tables: bool,
+) -> Result<String, GenerateTablesForIndexerSqlError> {
+    let mut sql = String::new();
+    let contract_name = NATIVE_TRANSFER_CONTRACT_NAME.to_string();
+    let abi_items: Vec<ABIItem> =
+        serde_json::from_str(abi_str).expect("JSON was not well-formatted");
+    let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+    let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
+
+    if !disable_event_tables {
+        sql.push_str(format!("CREATE SCHEMA IF NOT EXISTS {schema_name};").as_str());
+        info!("Creating schema if not exists: {}", schema_name);
+
+        let event_matching_name_on_other = find_clashing_event_names(
+            project_path,
+            &contract_name,
+            &indexer.contracts,
+            &event_names,
+        )?;
+
+        sql.push_str(&generate_event_table_sql_with_comments(
+            &event_names,
+            &contract_name,
+            &schema_name,
+            event_matching_name_on_other,
+            false,
+            // the internal native transfers of a transaction share its tx hash and log index
+            false,
+        ));
+    }
+    sql.push_str(&generate_internal_event_table_sql(&event_names, &schema_name, networks));
+
+    Ok(sql)
+}
+
 pub fn generate_tables_for_indexer_sql(
     project_path: &Path,
     indexer: &Indexer,
@@ -229,6 +330,8 @@ pub fn generate_tables_for_indexer_sql(
                 &contract.name,
                 &schema_name,
                 event_matching_name_on_other,
+                contract.include_tx_context.unwrap_or_default(),
+                true,
             ));
         }
 
@@ -240,34 +343,27 @@ pub fn generate_tables_for_indexer_sql(
     }
 
     if indexer.native_transfers.enabled {
-        let contract_name = NATIVE_TRANSFER_CONTRACT_NAME.to_string();
-        let abi_str = NATIVE_TRANSFER_ABI;
-        let abi_items: Vec<ABIItem> =
-            serde_json::from_str(abi_str).expect("JSON was not well-formatted");
-        let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
-        let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
-        let networks = indexer.clone().native_transfers.networks.unwrap();
+        let networks = indexer.native_transfers.networks.clone().unwrap_or_default();
         let networks: Vec<&str> = networks.iter().map(|d| d.network.as_str()).collect();
+        sql.push_str(&generate_trace_tables_sql(
+            project_path,
+            indexer,
+            NATIVE_TRANSFER_ABI,
+            networks,
+            disable_event_tables,
+        )?);
+    }
 
-        if !disable_event_tables {
-            sql.push_str(format!("CREATE SCHEMA IF NOT EXISTS {schema_name};").as_str());
-            info!("Creating schema if not exists: {}", schema_name);
-
-            let event_matching_name_on_other = find_clashing_event_names(
-                project_path,
-                &contract_name,
-                &indexer.contracts,
-                &event_names,
-            )?;
-
-            sql.push_str(&generate_event_table_sql_with_comments(
-                &event_names,
-                &contract_name,
-                &schema_name,
-                event_matching_name_on_other,
-            ));
-        }
-        sql.push_str(&generate_internal_event_table_sql(&event_names, &schema_name, networks));
+    if indexer.contract_deployments.enabled {
+        let networks = indexer.contract_deployments.networks.clone().unwrap_or_default();
+        let networks: Vec<&str> = networks.iter().map(|d| d.network.as_str()).collect();
+        sql.push_str(&generate_trace_tables_sql(
+            project_path,
+            indexer,
+            CONTRACT_CREATED_ABI,
+            networks,
+            disable_event_tables,
+        )?);
     }
 
     sql.push_str(&format!(
@@ -323,6 +419,11 @@ pub struct GenerateInternalFactoryEventTableNameParams {
     pub input_name: String,
 }
 
+/// The factory input name made safe for table and file names, `pools[].addr` is `pools_addr`.
+pub fn factory_input_name_identifier(input_name: &str) -> String {
+    input_name.replace("[]", "").replace('.', "_")
+}
+
 pub fn generate_internal_factory_event_table_name(
     params: &GenerateInternalFactoryEventTableNameParams,
 ) -> String {
@@ -332,7 +433,7 @@ pub fn generate_internal_factory_event_table_name(
         "{}_{}_{}",
         schema_name,
         camel_to_snake(&params.event_name),
-        camel_to_snake(&params.input_name)
+        camel_to_snake(&factory_input_name_identifier(&params.input_name))
     );
 
     compact_table_name_if_needed(table_name)
@@ -368,7 +469,9 @@ pub fn drop_tables_for_indexer_sql(project_path: &Path, indexer: &Indexer) -> Co
         }
 
         // drop factory indexing tables
-        for factory in contract.details.iter().flat_map(|d| d.factory.as_ref()) {
+        for factory in
+            contract.details.iter().flat_map(|d| d.factory.as_ref()).flat_map(|f| f.levels())
+        {
             let params = GenerateInternalFactoryEventTableNameParams {
                 indexer_name: indexer.name.clone(),
                 contract_name: factory.name.clone(),
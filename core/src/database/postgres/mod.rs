@@ -1,3 +1,4 @@
+pub mod blocks;
 pub mod client;
 pub mod generate;
 pub mod indexes;
@@ -4,6 +4,7 @@ use tracing::{debug, info};
 
 use crate::{
     database::postgres::{
+        blocks::generate_blocks_table_sql,
         client::{PostgresClient, PostgresConnectionError, PostgresError},
         generate::{generate_tables_for_indexer_sql, GenerateTablesForIndexerSqlError},
     },
@@ -54,6 +55,9 @@ pub async fn setup_postgres(
     )?;
     debug!("{}", sql);
     client.batch_execute(sql.as_str()).await?;
+    if manifest.networks.iter().any(|network| network.store_block_headers.unwrap_or_default()) {
+        client.batch_execute(&generate_blocks_table_sql()).await?;
+    }
     if !disable_event_tables {
         info!("Created tables for {}", manifest.name);
     } else {
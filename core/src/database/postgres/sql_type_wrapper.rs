@@ -18,6 +18,7 @@ use crate::{abi::ABIInput, event::callback_registry::TxInformation, types::core:
 pub enum EthereumSqlTypeWrapper {
     // Boolean
     Bool(bool),
+    BoolNullable(Option<bool>),
     VecBool(Vec<bool>),
 
     // 8-bit integers
@@ -123,6 +124,7 @@ impl EthereumSqlTypeWrapper {
         match self {
             // Boolean
             EthereumSqlTypeWrapper::Bool(_) => "Bool",
+            EthereumSqlTypeWrapper::BoolNullable(_) => "BoolNullable",
             EthereumSqlTypeWrapper::VecBool(_) => "VecBool",
 
             // 8-bit integers
@@ -225,7 +227,9 @@ impl EthereumSqlTypeWrapper {
     pub fn to_type(&self) -> PgType {
         match self {
             // Boolean
-            EthereumSqlTypeWrapper::Bool(_) => PgType::BOOL,
+            EthereumSqlTypeWrapper::Bool(_) | EthereumSqlTypeWrapper::BoolNullable(_) => {
+                PgType::BOOL
+            }
             EthereumSqlTypeWrapper::VecBool(_) => PgType::BOOL_ARRAY,
 
             // 8-bit integers
@@ -805,6 +809,10 @@ impl ToSql for EthereumSqlTypeWrapper {
                 }
             }
             EthereumSqlTypeWrapper::Bool(value) => bool::to_sql(value, ty, out),
+            EthereumSqlTypeWrapper::BoolNullable(value) => match value {
+                Some(value) => bool::to_sql(value, ty, out),
+                None => Ok(IsNull::Yes),
+            },
             EthereumSqlTypeWrapper::VecBool(values) => {
                 if values.is_empty() {
                     return Ok(IsNull::Yes);
@@ -1581,6 +1589,42 @@ fn count_components(components: &[ABIInput]) -> usize {
         .sum()
 }
 
+/// The `block_timestamp` column of an event, `NULL` when the node did not return it.
+pub fn map_block_timestamp_to_ethereum_wrapper(
+    transaction_information: &TxInformation,
+) -> EthereumSqlTypeWrapper {
+    EthereumSqlTypeWrapper::DateTimeNullable(
+        transaction_information
+            .block_timestamp
+            .and_then(|timestamp| i64::try_from(timestamp).ok())
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+    )
+}
+
+/// The values of the `TX_CONTEXT_COLUMNS` of an event, all `NULL` when the transaction could not
+/// be fetched.
+pub fn map_tx_context_to_ethereum_wrapper(
+    transaction_information: &TxInformation,
+) -> Vec<EthereumSqlTypeWrapper> {
+    let tx_context = transaction_information.tx_context.as_ref();
+    vec![
+        EthereumSqlTypeWrapper::AddressNullable(
+            tx_context.map(|context| context.from).unwrap_or_default(),
+        ),
+        EthereumSqlTypeWrapper::AddressNullable(
+            tx_context.and_then(|context| context.to).unwrap_or_default(),
+        ),
+        EthereumSqlTypeWrapper::U256NumericNullable(tx_context.and_then(|context| context.value)),
+        EthereumSqlTypeWrapper::U256NumericNullable(
+            tx_context.map(|context| U256::from(context.gas_used)),
+        ),
+        EthereumSqlTypeWrapper::U256NumericNullable(
+            tx_context.map(|context| context.effective_gas_price),
+        ),
+        EthereumSqlTypeWrapper::BoolNullable(tx_context.map(|context| context.status)),
+    ]
+}
+
 pub fn map_ethereum_wrapper_to_json(
     abi_inputs: &[ABIInput],
     wrappers: &[EthereumSqlTypeWrapper],
@@ -1680,6 +1724,7 @@ pub fn map_ethereum_wrapper_to_json(
                     EthereumSqlTypeWrapper::VecAddress(addresses)
                     | EthereumSqlTypeWrapper::VecAddressBytes(addresses) => json!(addresses),
                     EthereumSqlTypeWrapper::Bool(b) => json!(b),
+                    EthereumSqlTypeWrapper::BoolNullable(b) => json!(b),
                     EthereumSqlTypeWrapper::VecBool(bools) => json!(bools),
                     EthereumSqlTypeWrapper::U32(u) => json!(u),
                     EthereumSqlTypeWrapper::VecU32(u32s) => json!(u32s),
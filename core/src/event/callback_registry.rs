@@ -1,23 +1,30 @@
-use std::{any::Any, sync::Arc, time::Duration};
+use std::{any::Any, path::Path, sync::Arc, time::Duration};
 
 use alloy::consensus::Transaction;
-use alloy::network::{AnyRpcTransaction, TransactionResponse};
+use alloy::network::{
+    AnyRpcTransaction, AnyTransactionReceipt, ReceiptResponse, TransactionResponse,
+};
 use alloy::{
-    primitives::{Address, BlockHash, Bytes, TxHash, B256, U256, U64},
+    primitives::{keccak256, Address, BlockHash, Bytes, TxHash, B256, U256, U64},
     rpc::types::{
-        trace::parity::{CallAction, LocalizedTransactionTrace},
+        trace::parity::{CallAction, CreateAction, CreateOutput, LocalizedTransactionTrace},
         Log,
     },
 };
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    event::contract_setup::{ContractInformation, NetworkContract, TraceInformation},
+    event::{
+        contract_setup::{ContractInformation, NetworkContract, TraceInformation},
+        failed_batches::{record_failed_batch, FailedBatch},
+    },
     indexer::start::ProcessedNetworkContract,
     is_running,
+    manifest::contract::{OnError, OnErrorPolicy},
+    system_state::halt,
 };
 
 pub type Decoder = Arc<dyn Fn(Vec<TxHash>, Bytes) -> Arc<dyn Any + Send + Sync> + Send + Sync>;
@@ -50,6 +57,40 @@ pub struct TxInformation {
     pub transaction_hash: TxHash,
     pub log_index: U256,
     pub transaction_index: U64,
+    /// Only set for contracts with `include_tx_context`, its fields are flattened into the
+    /// transaction information.
+    #[serde(default, flatten)]
+    pub tx_context: Option<TxContext>,
+    /// Set when the log was reorged out of the chain, handlers should undo what they did for it
+    /// instead of indexing it.
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// The transaction an event was emitted in, beyond what the log itself carries.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TxContext {
+    pub from: Address,
+    /// `None` for contract creations
+    pub to: Option<Address>,
+    /// `None` when the node did not return the transaction
+    pub value: Option<U256>,
+    pub gas_used: U64,
+    pub effective_gas_price: U256,
+    pub status: bool,
+}
+
+impl TxContext {
+    pub fn new(receipt: &AnyTransactionReceipt, value: Option<U256>) -> Self {
+        Self {
+            from: receipt.from(),
+            to: receipt.to(),
+            value,
+            gas_used: U64::from(receipt.gas_used()),
+            effective_gas_price: U256::from(receipt.effective_gas_price()),
+            status: receipt.status(),
+        }
+    }
 }
 
 /// Define a trait over any entity that has attached transaction information. This is very useful
@@ -97,6 +138,8 @@ impl EventResult {
                     log.transaction_index.expect("log should contain transaction_index"),
                 ),
                 log_index: U256::from(log.log_index.expect("log should contain log_index")),
+                tx_context: None,
+                removed: log.removed,
             },
             found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
         }
@@ -105,6 +148,16 @@ impl EventResult {
 
 pub type EventCallbackResult<T> = Result<T, String>;
 
+/// What became of a batch handed to a callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallbackOutcome {
+    Processed,
+    /// The callback kept failing with the `skip` policy, the batch was recorded as a failed batch.
+    Skipped,
+    /// Indexing is stopping before the batch was processed, it must not be marked as synced.
+    Stopped,
+}
+
 pub type EventCallbackType =
     Arc<dyn Fn(Vec<EventResult>) -> BoxFuture<'static, EventCallbackResult<()>> + Send + Sync>;
 pub type TraceCallbackType =
@@ -116,6 +169,7 @@ pub struct EventCallbackRegistryInformation {
     pub topic_id: B256,
     pub event_name: String,
     pub index_event_in_order: bool,
+    pub on_error: OnError,
     pub contract: ContractInformation,
     pub callback: EventCallbackType,
 }
@@ -134,6 +188,7 @@ impl Clone for EventCallbackRegistryInformation {
             topic_id: self.topic_id,
             event_name: self.event_name.clone(),
             index_event_in_order: self.index_event_in_order,
+            on_error: self.on_error,
             contract: self.contract.clone(),
             callback: Arc::clone(&self.callback),
         }
@@ -164,22 +219,87 @@ impl EventCallbackRegistry {
         self.events.push(event);
     }
 
-    pub async fn trigger_event(&self, id: &String, data: Vec<EventResult>) {
-        if let Some(event_information) = self.find_event(id) {
-            trigger_event(
+    /// Hands the events to the callback of the event, a batch the callback gives up on with the
+    /// `skip` policy is recorded to the failed batches of the project.
+    pub async fn trigger_event(
+        &self,
+        project_path: &Path,
+        id: &String,
+        data: Vec<EventResult>,
+    ) -> CallbackOutcome {
+        let Some(event_information) = self.find_event(id) else {
+            error!(
+                "EventCallbackRegistry: No event found for id: {}. Data: {:?}",
                 id,
-                data,
-                |d| (event_information.callback)(d),
-                || event_information.info_log_name(),
-                &event_information.topic_id.to_string(),
-            )
-            .await;
-        } else {
+                data.first()
+            );
+            return CallbackOutcome::Processed;
+        };
+
+        let outcome = trigger_event(
+            id,
+            &data,
+            |d| (event_information.callback)(d),
+            || event_information.info_log_name(),
+            &event_information.topic_id.to_string(),
+            &event_information.on_error,
+        )
+        .await;
+
+        let error = match outcome {
+            Ok(outcome) => return outcome,
+            Err(error) => error,
+        };
+
+        let failed_batch = FailedBatch::new(event_information, &data, &error);
+        if let Err(e) = record_failed_batch(project_path, &failed_batch) {
+            // the batch would be lost if indexing carried on past it
+            error!(
+                "{} - Failed to record failed batch, halting indexing: {}",
+                event_information.info_log_name(),
+                e
+            );
+            halt();
+            return CallbackOutcome::Stopped;
+        }
+
+        CallbackOutcome::Skipped
+    }
+
+    /// Hands the removed events of a reorg to the callback of the event. A retraction the
+    /// callback gives up on with the `skip` policy is not recorded as a failed batch, replaying it
+    /// would index the removed events again.
+    pub async fn retract_event(&self, id: &String, data: Vec<EventResult>) -> CallbackOutcome {
+        let Some(event_information) = self.find_event(id) else {
             error!(
                 "EventCallbackRegistry: No event found for id: {}. Data: {:?}",
                 id,
                 data.first()
             );
+            return CallbackOutcome::Processed;
+        };
+
+        let outcome = trigger_event(
+            id,
+            &data,
+            |d| (event_information.callback)(d),
+            || event_information.info_log_name(),
+            &event_information.topic_id.to_string(),
+            &event_information.on_error,
+        )
+        .await;
+
+        match outcome {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                error!(
+                    "{} - Skipped retracting {} removed events, they have to be retracted by hand: {}",
+                    event_information.info_log_name(),
+                    data.len(),
+                    error
+                );
+                CallbackOutcome::Skipped
+            }
         }
     }
 
@@ -219,11 +339,15 @@ impl EventCallbackRegistry {
 // "Native" Trace Callback Registry
 // --------------------------------
 
+/// A native transfer, or for a `ContractCreated` trace the deployer in `from`, the deployed
+/// contract in `to` and the value it was endowed with.
 #[derive(Debug, Clone)]
 pub struct TraceResult {
     pub from: Address,
     pub to: Address,
     pub value: U256,
+    /// The keccak256 of the deployed code, only set for `ContractCreated` traces.
+    pub code_hash: Option<B256>,
     pub tx_information: TxInformation,
     pub found_in_request: LogFoundInRequest,
 }
@@ -248,6 +372,7 @@ impl TraceResult {
             from: action.from,
             to: action.to,
             value: action.value,
+            code_hash: None,
             tx_information: TxInformation {
                 network: network.to_string(),
                 address: Address::ZERO,
@@ -258,6 +383,41 @@ impl TraceResult {
                 block_hash: trace.block_hash.unwrap_or(BlockHash::ZERO),
                 transaction_index: U64::from(trace.transaction_position.unwrap_or(0)),
                 log_index: U256::from(0),
+                tx_context: None,
+                removed: false,
+            },
+            found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
+        }
+    }
+
+    /// Create a "ContractCreated" TraceResult from a `CREATE` or `CREATE2` trace, the log index is
+    /// the position of the deployment among the deployments of the block as a transaction can
+    /// deploy many contracts.
+    pub fn new_contract_created(
+        action: &CreateAction,
+        output: &CreateOutput,
+        trace: &LocalizedTransactionTrace,
+        log_index: u64,
+        network: &str,
+        start_block: U64,
+        end_block: U64,
+    ) -> Self {
+        Self {
+            from: action.from,
+            to: output.address,
+            value: action.value,
+            code_hash: Some(keccak256(&output.code)),
+            tx_information: TxInformation {
+                network: network.to_string(),
+                address: Address::ZERO,
+                block_number: trace.block_number.map(U64::from).unwrap_or_else(|| U64::ZERO),
+                block_timestamp: None,
+                transaction_hash: trace.transaction_hash.unwrap_or(TxHash::ZERO),
+                block_hash: trace.block_hash.unwrap_or(BlockHash::ZERO),
+                transaction_index: U64::from(trace.transaction_position.unwrap_or(0)),
+                log_index: U256::from(log_index),
+                tx_context: None,
+                removed: false,
             },
             found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
         }
@@ -276,6 +436,7 @@ impl TraceResult {
             to,
             from: tx.from(),
             value: tx.value(),
+            code_hash: None,
             tx_information: TxInformation {
                 network: network.to_string(),
                 address: Address::ZERO,
@@ -290,6 +451,8 @@ impl TraceResult {
                     tx.transaction_index.expect("transaction_index should be present"),
                 ),
                 log_index: U256::from(0),
+                tx_context: None,
+                removed: false,
             },
             found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
         }
@@ -334,12 +497,14 @@ impl TraceCallbackRegistry {
 
     pub async fn trigger_event(&self, id: &String, data: Vec<TraceResult>) {
         if let Some(event_information) = self.find_event(id) {
-            trigger_event(
+            // native transfers have no `on_error`, their callbacks are retried until they succeed
+            let _ = trigger_event(
                 id,
-                data,
+                &data,
                 |d| (event_information.callback)(d),
                 || event_information.info_log_name(),
                 &event_information.event_name,
+                &OnError::default(),
             )
             .await;
         } else {
@@ -352,13 +517,17 @@ impl TraceCallbackRegistry {
     }
 }
 
+/// Calls the callback until it succeeds or the `on_error` policy gives up on the batch, the error
+/// is returned when the batch should be skipped.
 async fn trigger_event<T>(
     id: &String,
-    data: Vec<T>,
+    data: &[T],
     callback: impl Fn(Vec<T>) -> BoxFuture<'static, EventCallbackResult<()>>,
     info_log_name: impl Fn() -> String,
     event_identifier: &str,
-) where
+    on_error: &OnError,
+) -> Result<CallbackOutcome, String>
+where
     T: Clone,
 {
     let mut attempts = 0;
@@ -370,23 +539,41 @@ async fn trigger_event<T>(
     loop {
         if !is_running() {
             info!("Detected shutdown, stopping event trigger");
-            break;
+            return Ok(CallbackOutcome::Stopped);
         }
 
-        match callback(data.clone()).await {
+        match callback(data.to_vec()).await {
             Ok(_) => {
                 debug!(
                     "Event processing succeeded for id: {} - topic_id: {}",
                     id, event_identifier
                 );
-                break;
+                return Ok(CallbackOutcome::Processed);
             }
             Err(e) => {
                 if !is_running() {
                     info!("Detected shutdown, stopping event trigger");
-                    break;
+                    return Ok(CallbackOutcome::Stopped);
                 }
                 attempts += 1;
+
+                if on_error.max_attempts().is_some_and(|max_attempts| attempts >= max_attempts) {
+                    if on_error.policy == OnErrorPolicy::Skip {
+                        warn!(
+                            "{} Event processing failed - id: {} - topic_id: {}. Skipping the batch after {} attempts. Error: {}",
+                            info_log_name(), id, event_identifier, attempts, e
+                        );
+                        return Err(e);
+                    }
+
+                    error!(
+                        "{} Event processing failed - id: {} - topic_id: {}. Halting indexing after {} attempts. Error: {}",
+                        info_log_name(), id, event_identifier, attempts, e
+                    );
+                    halt();
+                    return Ok(CallbackOutcome::Stopped);
+                }
+
                 error!(
                     "{} Event processing failed - id: {} - topic_id: {}. Retrying... (attempt {}). Error: {}",
                     info_log_name(), id, event_identifier, attempts, e
@@ -399,3 +586,44 @@ async fn trigger_event<T>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_tx_context_is_flattened_into_tx_information() {
+        let mut tx_information = TxInformation {
+            network: "ethereum".to_string(),
+            address: Address::ZERO,
+            block_hash: BlockHash::ZERO,
+            block_number: U64::from(1),
+            block_timestamp: None,
+            transaction_hash: TxHash::ZERO,
+            log_index: U256::ZERO,
+            transaction_index: U64::ZERO,
+            tx_context: None,
+            removed: false,
+        };
+        let json = serde_json::to_value(&tx_information).unwrap();
+        assert!(json.get("from").is_none());
+
+        tx_information.tx_context = Some(TxContext {
+            from: address!("0x00000000000000000000000000000000000000aa"),
+            to: None,
+            value: Some(U256::from(1)),
+            gas_used: U64::from(21_000),
+            effective_gas_price: U256::from(2),
+            status: true,
+        });
+        let json = serde_json::to_value(&tx_information).unwrap();
+        assert_eq!(json["from"], "0x00000000000000000000000000000000000000aa");
+        assert!(json["to"].is_null());
+        assert_eq!(json["status"], true);
+
+        let deserialized: TxInformation = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.tx_context, tx_information.tx_context);
+    }
+}
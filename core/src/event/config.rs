@@ -1,20 +1,20 @@
 use alloy::json_abi::Event;
 use alloy::primitives::{keccak256, Address, B256, U64};
-use alloy::rpc::types::ValueOrArray;
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
 use tokio::sync::Mutex;
 
-use crate::event::contract_setup::{AddressDetails, IndexingContractSetup};
+use crate::event::contract_setup::{AddressDetails, FactoryAddress, IndexingContractSetup};
 use crate::event::factory_event_filter_sync::update_known_factory_deployed_addresses;
+use crate::event::function_call::FunctionCallFilter;
 use crate::event::rindexer_event_filter::FactoryFilter;
 use crate::manifest::config::Config;
 use crate::manifest::contract::EventInputIndexedFilters;
 use crate::{
     event::{
         callback_registry::{
-            EventCallbackRegistry, EventResult, TraceCallbackRegistry, TraceResult,
+            CallbackOutcome, EventCallbackRegistry, EventResult, TraceCallbackRegistry, TraceResult,
         },
-        contract_setup::NetworkContract,
+        contract_setup::{NetworkContract, TraceEventKind},
         BuildRindexerFilterError, RindexerEventFilter,
     },
     indexer::IndexingEventsProgressState,
@@ -41,12 +41,15 @@ pub struct ContractEventProcessingConfig {
     pub stream_last_synced_block_file_path: Option<String>,
     pub index_event_in_order: bool,
     pub live_indexing: bool,
+    pub reorg_safe_distance: bool,
     pub indexing_distance_from_head: U64,
+    /// Set when the event is the calls of a contract function.
+    pub function_call: Option<Arc<FunctionCallFilter>>,
 }
 
 impl ContractEventProcessingConfig {
     pub fn to_event_filter(&self) -> Result<RindexerEventFilter, BuildRindexerFilterError> {
-        match &self.network_contract.indexing_contract_setup {
+        let filter = match &self.network_contract.indexing_contract_setup {
             IndexingContractSetup::Address(details) => RindexerEventFilter::new_address_filter(
                 &self.topic_id,
                 &self.event_name,
@@ -70,7 +73,7 @@ impl ContractEventProcessingConfig {
                     project_path: self.project_path.clone(),
                     indexer_name: self.indexer_name.clone(),
                     factory_contract_name: details.contract_name.clone(),
-                    factory_address: details.address.clone(),
+                    factory_address: details.root_address().clone(),
                     factory_event_name: details.event.name.clone(),
                     factory_input_name: details.input_name.clone(),
                     network: self.network_contract.network.clone(),
@@ -81,13 +84,20 @@ impl ContractEventProcessingConfig {
 
                     current_block: self.start_block,
                     next_block: self.end_block,
+                    function_call: None,
                 }))
             }
-        }
+        }?;
+
+        Ok(filter.with_function_call(self.function_call.clone()))
     }
 
-    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) {
-        self.registry.trigger_event(&self.id, fn_data).await;
+    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) -> CallbackOutcome {
+        self.registry.trigger_event(&self.project_path, &self.id, fn_data).await
+    }
+
+    pub async fn retract_event(&self, removed_data: Vec<EventResult>) -> CallbackOutcome {
+        self.registry.retract_event(&self.id, removed_data).await
     }
 }
 
@@ -95,7 +105,7 @@ pub struct FactoryEventProcessingConfig {
     pub project_path: PathBuf,
     pub indexer_name: String,
     pub contract_name: String,
-    pub address: ValueOrArray<Address>,
+    pub address: FactoryAddress,
     pub input_name: String,
     pub event: Event,
     pub config: Config,
@@ -108,6 +118,7 @@ pub struct FactoryEventProcessingConfig {
     pub stream_last_synced_block_file_path: Option<String>,
     pub index_event_in_order: bool,
     pub live_indexing: bool,
+    pub reorg_safe_distance: bool,
     pub indexing_distance_from_head: U64,
 }
 
@@ -116,8 +127,32 @@ impl FactoryEventProcessingConfig {
         let event_name = self.event.name.clone();
         let event_selector = self.event.selector();
 
+        let address = match &self.address {
+            FactoryAddress::Address(address) => address.clone(),
+            // a nested factory listens to the addresses its own factory deployed
+            FactoryAddress::Factory(factory) => {
+                return Ok(RindexerEventFilter::Factory(FactoryFilter {
+                    project_path: self.project_path.clone(),
+                    indexer_name: self.indexer_name.clone(),
+                    factory_contract_name: factory.contract_name.clone(),
+                    factory_address: factory.root_address().clone(),
+                    factory_event_name: factory.event.name.clone(),
+                    factory_input_name: factory.input_name.clone(),
+                    network: self.network_contract.network.clone(),
+                    topic_id: event_selector,
+                    topics: Default::default(),
+                    database: self.database.clone(),
+                    csv_details: self.csv_details.clone(),
+
+                    current_block: self.start_block,
+                    next_block: self.end_block,
+                    function_call: None,
+                }));
+            }
+        };
+
         let details = AddressDetails {
-            address: self.address.clone(),
+            address,
             indexed_filters: Some(vec![EventInputIndexedFilters {
                 event_name: event_name.clone(),
                 indexed_1: None,
@@ -135,10 +170,12 @@ impl FactoryEventProcessingConfig {
         )
     }
 
-    pub async fn trigger_event(&self, events: Vec<EventResult>) {
+    pub async fn trigger_event(&self, events: Vec<EventResult>) -> CallbackOutcome {
         update_known_factory_deployed_addresses(self, &events)
             .await
             .expect("Failed to update known factory deployed addresses");
+
+        CallbackOutcome::Processed
     }
 
     pub fn info_log_name(&self) -> String {
@@ -243,6 +280,13 @@ impl EventProcessingConfig {
         }
     }
 
+    pub fn reorg_safe_distance(&self) -> bool {
+        match self {
+            Self::ContractEventProcessing(config) => config.reorg_safe_distance,
+            Self::FactoryEventProcessing(config) => config.reorg_safe_distance,
+        }
+    }
+
     pub fn indexing_distance_from_head(&self) -> U64 {
         match self {
             Self::ContractEventProcessing(config) => config.indexing_distance_from_head,
@@ -296,12 +340,20 @@ impl EventProcessingConfig {
         }
     }
 
-    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) {
+    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) -> CallbackOutcome {
         match self {
             Self::ContractEventProcessing(config) => config.trigger_event(fn_data).await,
             Self::FactoryEventProcessing(config) => config.trigger_event(fn_data).await,
         }
     }
+
+    /// Factory events have nothing to retract, the addresses they found are kept.
+    pub async fn retract_event(&self, removed_data: Vec<EventResult>) -> CallbackOutcome {
+        match self {
+            Self::ContractEventProcessing(config) => config.retract_event(removed_data).await,
+            Self::FactoryEventProcessing(_) => CallbackOutcome::Processed,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -313,12 +365,15 @@ pub struct TraceProcessingConfig {
     pub indexer_name: String,
     pub contract_name: String,
     pub event_name: String,
+    pub kind: TraceEventKind,
     pub network: String,
     pub progress: Arc<Mutex<IndexingEventsProgressState>>,
     pub database: Option<Arc<PostgresClient>>,
     pub csv_details: Option<CsvDetails>,
     pub registry: Arc<TraceCallbackRegistry>,
     pub method: TraceProcessingMethod,
+    /// Only index the contracts deployed by these, every deployment when not set.
+    pub deployers: Option<HashSet<Address>>,
     pub stream_last_synced_block_file_path: Option<String>,
 }
 
@@ -3,9 +3,10 @@ use crate::notifications::ChainStateNotification;
 use crate::{
     event::callback_registry::Decoder,
     generate_random_id,
-    indexer::native_transfer::EVENT_NAME,
+    indexer::{contract_deployment::CONTRACT_CREATED_EVENT_NAME, native_transfer::EVENT_NAME},
     manifest::{
-        contract::{Contract, EventInputIndexedFilters},
+        contract::{Contract, EventInputIndexedFilters, LogsFetchMode},
+        contract_deployment::ContractDeployments,
         native_transfer::{NativeTransfers, TraceProcessingMethod},
     },
     provider::{get_network_provider, CreateNetworkProvider, JsonRpcCachedProvider},
@@ -31,6 +32,8 @@ pub struct NetworkContract {
     pub start_block: Option<U64>,
     pub end_block: Option<U64>,
     pub disable_logs_bloom_checks: bool,
+    pub include_block_timestamp: bool,
+    pub include_tx_context: bool,
 }
 
 impl NetworkContract {
@@ -53,6 +56,7 @@ pub struct ContractInformation {
     pub details: Vec<NetworkContract>,
     pub abi: StringOrArray,
     pub reorg_safe_distance: bool,
+    pub logs_fetch_mode: LogsFetchMode,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -88,6 +92,10 @@ impl ContractInformation {
                         start_block: c.start_block,
                         end_block: c.end_block,
                         disable_logs_bloom_checks: provider.disable_logs_bloom_checks,
+                        include_block_timestamp: contract
+                            .include_block_timestamp
+                            .unwrap_or(provider.include_block_timestamp),
+                        include_tx_context: contract.include_tx_context.unwrap_or_default(),
                     });
                 }
             }
@@ -98,6 +106,7 @@ impl ContractInformation {
             details,
             abi: contract.abi.clone(),
             reorg_safe_distance: contract.reorg_safe_distance.unwrap_or_default(),
+            logs_fetch_mode: contract.logs_fetch_mode.unwrap_or_default(),
         })
     }
 }
@@ -110,6 +119,8 @@ pub struct NetworkTrace {
     pub start_block: Option<U64>,
     pub end_block: Option<U64>,
     pub method: TraceProcessingMethod,
+    /// Only set for contract deployments limited to some deployers.
+    pub deployers: Option<Vec<Address>>,
 }
 
 impl NetworkTrace {
@@ -122,9 +133,17 @@ impl NetworkTrace {
     }
 }
 
+/// What is indexed from the traces of the blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEventKind {
+    NativeTransfer,
+    ContractDeployment,
+}
+
 #[derive(Clone)]
 pub struct TraceInformation {
     pub name: String,
+    pub kind: TraceEventKind,
     pub details: Vec<NetworkTrace>,
     pub reorg_safe_distance: bool,
 }
@@ -155,6 +174,7 @@ impl TraceInformation {
                         start_block: n.start_block,
                         end_block: n.end_block,
                         method: n.method,
+                        deployers: None,
                     });
                 }
             }
@@ -162,10 +182,47 @@ impl TraceInformation {
 
         Ok(TraceInformation {
             name: EVENT_NAME.to_string(),
+            kind: TraceEventKind::NativeTransfer,
             details,
             reorg_safe_distance: native_transfers.reorg_safe_distance.unwrap_or_default(),
         })
     }
+
+    pub fn create_contract_deployments(
+        contract_deployments: ContractDeployments,
+        network_providers: &[CreateNetworkProvider],
+    ) -> Result<TraceInformation, CreateContractInformationError> {
+        let details = contract_deployments
+            .networks
+            .unwrap_or_default()
+            .into_iter()
+            .map(|n| {
+                let provider =
+                    get_network_provider(&n.network, network_providers).ok_or_else(|| {
+                        CreateContractInformationError::CanNotFindNetworkFromProviders(
+                            n.network.clone(),
+                        )
+                    })?;
+
+                Ok(NetworkTrace {
+                    id: generate_random_id(10),
+                    network: n.network,
+                    cached_provider: Arc::clone(&provider.client),
+                    start_block: n.start_block,
+                    end_block: n.end_block,
+                    method: n.method,
+                    deployers: n.deployers,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TraceInformation {
+            name: CONTRACT_CREATED_EVENT_NAME.to_string(),
+            kind: TraceEventKind::ContractDeployment,
+            details,
+            reorg_safe_distance: contract_deployments.reorg_safe_distance.unwrap_or_default(),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -191,23 +248,81 @@ pub enum FactoryDetailsFromAbiError {
 
     #[error("Can not find event {0}")]
     EventNotFoundError(String),
+
+    #[error("Input {1} of event {0} is not an address or a path to addresses")]
+    InputNotAnAddress(String, String),
+
+    #[error("Factory {0} must have either an address or a factory")]
+    AddressOrFactoryRequired(String),
+}
+
+/// Where the addresses of the factory contracts come from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FactoryAddress {
+    Address(ValueOrArray<Address>),
+    /// The factory contracts were deployed by another factory.
+    Factory(Box<FactoryDetails>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FactoryDetails {
     pub contract_name: String,
-    pub address: ValueOrArray<Address>,
+    pub address: FactoryAddress,
     pub input_name: String,
     pub event: Event,
     pub indexed_filters: Option<Vec<EventInputIndexedFilters>>,
 }
 
+/// Whether `input_name` resolves to addresses in the event inputs, where `.` goes into a tuple and
+/// `[]` through an array.
+fn is_address_input(event: &Event, input_name: &str) -> bool {
+    let resolve = |ty: &str, segment: &str| -> Option<String> {
+        match segment.strip_suffix("[]") {
+            Some(_) => ty
+                .strip_suffix(']')
+                .and_then(|ty| ty.rsplit_once('['))
+                .map(|(ty, _)| ty.to_string()),
+            None => Some(ty.to_string()),
+        }
+    };
+
+    let mut segments = input_name.split('.');
+    let Some(first) = segments.next() else {
+        return false;
+    };
+    let Some(input) = event.inputs.iter().find(|input| input.name == first.trim_end_matches("[]"))
+    else {
+        return false;
+    };
+    let Some(mut ty) = resolve(&input.ty, first) else {
+        return false;
+    };
+    let mut components = &input.components;
+
+    for segment in segments {
+        let Some(component) = components
+            .iter()
+            .find(|component| component.name == segment.trim_end_matches("[]"))
+            .filter(|_| ty == "tuple")
+        else {
+            return false;
+        };
+        let Some(component_ty) = resolve(&component.ty, segment) else {
+            return false;
+        };
+        ty = component_ty;
+        components = &component.components;
+    }
+
+    ty == "address"
+}
+
 impl FactoryDetails {
     pub fn from_abi(
         project_path: &Path,
         abi: String,
         contract_name: String,
-        address: ValueOrArray<Address>,
+        address: FactoryAddress,
         event_name: String,
         input_name: String,
         indexed_filters: Option<Vec<EventInputIndexedFilters>>,
@@ -222,15 +337,38 @@ impl FactoryDetails {
             .ok_or(FactoryDetailsFromAbiError::EventNotFoundError(event_name.clone()))?
             .clone();
 
+        if !is_address_input(&event, &input_name) {
+            return Err(FactoryDetailsFromAbiError::InputNotAnAddress(event_name, input_name));
+        }
+
         Ok(FactoryDetails { contract_name, address, input_name, event, indexed_filters })
     }
+
+    /// Every factory of a nested factory, from the one with a fixed address to this one.
+    pub fn levels(&self) -> Vec<&FactoryDetails> {
+        let mut levels = match &self.address {
+            FactoryAddress::Factory(factory) => factory.levels(),
+            FactoryAddress::Address(_) => vec![],
+        };
+        levels.push(self);
+
+        levels
+    }
+
+    /// The addresses of the outermost factory of a nested factory.
+    pub fn root_address(&self) -> &ValueOrArray<Address> {
+        match &self.address {
+            FactoryAddress::Address(address) => address,
+            FactoryAddress::Factory(factory) => factory.root_address(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FilterDetails {
     pub events: ValueOrArray<String>,
 
-    pub indexed_filters: Option<EventInputIndexedFilters>,
+    pub indexed_filters: Option<Vec<EventInputIndexedFilters>>,
 }
 
 #[derive(Clone)]
@@ -1,6 +1,7 @@
 use crate::database::postgres::client::PostgresError;
 use crate::database::postgres::generate::{
-    generate_internal_factory_event_table_name, GenerateInternalFactoryEventTableNameParams,
+    factory_input_name_identifier, generate_internal_factory_event_table_name,
+    GenerateInternalFactoryEventTableNameParams,
 };
 use crate::event::callback_registry::EventResult;
 use crate::event::config::FactoryEventProcessingConfig;
@@ -66,7 +67,7 @@ fn build_known_factory_address_file(
         contract_name.to_lowercase(),
         network.to_lowercase(),
         event_name.to_lowercase(),
-        input_name.to_lowercase()
+        factory_input_name_identifier(input_name).to_lowercase()
     ));
 
     path.to_string_lossy().into_owned()
@@ -103,15 +104,24 @@ pub async fn update_known_factory_deployed_addresses(
         .iter()
         .map(|event| {
             parse_log(&config.event, &event.log)
-                .and_then(|log| log.get_param_value(&config.input_name))
-                .and_then(|value| value.as_address())
-                .map(|address| KnownFactoryDeployedAddress {
-                    factory_address: event.tx_information.address,
-                    address,
+                .and_then(|log| log.get_param_values(&config.input_name))
+                .and_then(|values| {
+                    values
+                        .iter()
+                        .map(|value| {
+                            value.as_address().map(|address| KnownFactoryDeployedAddress {
+                                factory_address: event.tx_information.address,
+                                address,
+                            })
+                        })
+                        .collect::<Option<Vec<_>>>()
                 })
         })
-        .collect::<Option<HashSet<_>>>()
-        .ok_or(UpdateKnownFactoryDeployedAddressesError::LogsParse)?;
+        .collect::<Option<Vec<_>>>()
+        .ok_or(UpdateKnownFactoryDeployedAddressesError::LogsParse)?
+        .into_iter()
+        .flatten()
+        .collect();
 
     // update in memory cache of factory addresses
     let key = KnownFactoryDeployedAddressesCacheKey {
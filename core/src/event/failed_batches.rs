@@ -0,0 +1,238 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use alloy::{primitives::U64, rpc::types::Log};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::event::callback_registry::{
+    CallbackOutcome, EventCallbackRegistry, EventCallbackRegistryInformation, EventResult,
+    TxContext,
+};
+
+const FAILED_BATCHES_PATH: &str = ".rindexer/failed_batches.jsonl";
+
+/// Handlers of every event append to the same file, a line must never be interleaved.
+static FAILED_BATCHES_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(thiserror::Error, Debug)]
+pub enum FailedBatchesError {
+    #[error("Could not access failed batches {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Could not parse failed batches {0}: {1}")]
+    Parse(String, serde_json::Error),
+}
+
+/// A batch of logs a handler gave up on with the `skip` policy, one per line of
+/// `.rindexer/failed_batches.jsonl`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedBatch {
+    pub indexer_name: String,
+    pub contract_name: String,
+    pub event_name: String,
+    pub network: String,
+    pub from_block: U64,
+    pub to_block: U64,
+    pub error: String,
+    pub logs: Vec<Log>,
+    /// The transaction context of each log, empty when the contract does not include it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tx_contexts: Vec<Option<TxContext>>,
+}
+
+impl FailedBatch {
+    pub fn new(
+        event_information: &EventCallbackRegistryInformation,
+        data: &[EventResult],
+        error: &str,
+    ) -> Self {
+        let first = data.first();
+        Self {
+            indexer_name: event_information.indexer_name.clone(),
+            contract_name: event_information.contract.name.clone(),
+            event_name: event_information.event_name.clone(),
+            network: first.map(|result| result.tx_information.network.clone()).unwrap_or_default(),
+            from_block: first.map(|result| result.found_in_request.from_block).unwrap_or_default(),
+            to_block: first.map(|result| result.found_in_request.to_block).unwrap_or_default(),
+            error: error.to_string(),
+            logs: data.iter().map(|result| result.log.clone()).collect(),
+            tx_contexts: if data.iter().any(|result| result.tx_information.tx_context.is_some()) {
+                data.iter().map(|result| result.tx_information.tx_context.clone()).collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+}
+
+pub fn failed_batches_path(project_path: &Path) -> PathBuf {
+    project_path.join(FAILED_BATCHES_PATH)
+}
+
+pub fn record_failed_batch(
+    project_path: &Path,
+    failed_batch: &FailedBatch,
+) -> Result<(), FailedBatchesError> {
+    record_failed_batches(project_path, std::slice::from_ref(failed_batch))
+}
+
+fn record_failed_batches(
+    project_path: &Path,
+    failed_batches: &[FailedBatch],
+) -> Result<(), FailedBatchesError> {
+    let path = failed_batches_path(project_path);
+    let display_path = path.display().to_string();
+
+    let mut lines = String::new();
+    for failed_batch in failed_batches {
+        let line = serde_json::to_string(failed_batch)
+            .map_err(|e| FailedBatchesError::Parse(display_path.clone(), e))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+
+    let _lock = FAILED_BATCHES_LOCK.lock().unwrap();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| FailedBatchesError::Io(display_path.clone(), e))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(|e| FailedBatchesError::Io(display_path, e))
+}
+
+/// Takes the failed batches of the project out of its file, the file is gone afterwards.
+fn take_failed_batches(project_path: &Path) -> Result<Vec<FailedBatch>, FailedBatchesError> {
+    let path = failed_batches_path(project_path);
+    let display_path = path.display().to_string();
+
+    let _lock = FAILED_BATCHES_LOCK.lock().unwrap();
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(FailedBatchesError::Io(display_path, e)),
+    };
+
+    let failed_batches = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| FailedBatchesError::Parse(display_path.clone(), e))
+        })
+        .collect::<Result<Vec<FailedBatch>, _>>()?;
+    fs::remove_file(&path).map_err(|e| FailedBatchesError::Io(display_path, e))?;
+
+    Ok(failed_batches)
+}
+
+/// Hands every failed batch of the project back to the handler of its event in the order they
+/// were recorded. Batches which fail again are recorded again, and the batches left when
+/// indexing stops are kept for the next replay.
+pub async fn replay_failed_batches(
+    project_path: &Path,
+    registry: &EventCallbackRegistry,
+) -> Result<(), FailedBatchesError> {
+    let failed_batches = take_failed_batches(project_path)?;
+    if failed_batches.is_empty() {
+        info!("No failed batches to replay");
+        return Ok(());
+    }
+    info!("Replaying {} failed batches", failed_batches.len());
+
+    let mut remaining = failed_batches.into_iter();
+    while let Some(failed_batch) = remaining.next() {
+        let event = registry.events.iter().find(|event| {
+            event.indexer_name == failed_batch.indexer_name
+                && event.contract.name == failed_batch.contract_name
+                && event.event_name == failed_batch.event_name
+        });
+        let network_contract = event.and_then(|event| {
+            event.contract.details.iter().find(|detail| detail.network == failed_batch.network)
+        });
+        let (Some(event), Some(network_contract)) = (event, network_contract) else {
+            warn!(
+                "{}::{} on {} is no longer indexed, keeping its failed batch of blocks {} - {}",
+                failed_batch.contract_name,
+                failed_batch.event_name,
+                failed_batch.network,
+                failed_batch.from_block,
+                failed_batch.to_block
+            );
+            record_failed_batch(project_path, &failed_batch)?;
+            continue;
+        };
+
+        let network_contract = Arc::new(network_contract.clone());
+        let data = failed_batch
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(index, log)| {
+                let mut result = EventResult::new(
+                    Arc::clone(&network_contract),
+                    log.clone(),
+                    failed_batch.from_block,
+                    failed_batch.to_block,
+                );
+                result.tx_information.tx_context =
+                    failed_batch.tx_contexts.get(index).cloned().flatten();
+                result
+            })
+            .collect();
+
+        if registry.trigger_event(project_path, &event.id, data).await == CallbackOutcome::Stopped {
+            let unprocessed: Vec<FailedBatch> =
+                std::iter::once(failed_batch).chain(remaining).collect();
+            return record_failed_batches(project_path, &unprocessed);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_batches_are_taken_once() {
+        let project_path =
+            std::env::temp_dir().join(format!("rindexer-failed-batches-{}", std::process::id()));
+        let failed_batch = |from_block: u64| FailedBatch {
+            indexer_name: "Indexer".to_string(),
+            contract_name: "Token".to_string(),
+            event_name: "Transfer".to_string(),
+            network: "ethereum".to_string(),
+            from_block: U64::from(from_block),
+            to_block: U64::from(from_block + 9),
+            error: "handler failed".to_string(),
+            logs: vec![Log::default()],
+            tx_contexts: vec![],
+        };
+
+        assert!(take_failed_batches(&project_path).unwrap().is_empty());
+
+        record_failed_batch(&project_path, &failed_batch(10)).unwrap();
+        record_failed_batches(&project_path, &[failed_batch(20), failed_batch(30)]).unwrap();
+
+        let taken = take_failed_batches(&project_path).unwrap();
+        assert_eq!(
+            taken.iter().map(|batch| batch.from_block.to::<u64>()).collect::<Vec<_>>(),
+            vec![10, 20, 30]
+        );
+        assert_eq!(taken[0].logs.len(), 1);
+        assert!(!failed_batches_path(&project_path).exists());
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+}
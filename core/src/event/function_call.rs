@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt},
+    json_abi::{Event, EventParam, Function},
+    primitives::{Address, LogData, TxHash, B256},
+    rpc::types::{
+        trace::parity::{Action, CallType, LocalizedTransactionTrace},
+        Log,
+    },
+};
+use tracing::warn;
+
+use crate::manifest::native_transfer::TraceProcessingMethod;
+
+/// The name of the event the calls of a function are indexed as, `transfer` becomes
+/// `TransferCall`.
+pub fn function_call_event_name(function_name: &str) -> String {
+    let mut chars = function_name.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}Call", first.to_uppercase(), chars.as_str()),
+        None => "Call".to_string(),
+    }
+}
+
+/// The event the calls of `function` are indexed as. Its inputs are the inputs of the function,
+/// followed by a `succeeded` flag when reverted calls are indexed too.
+pub fn function_call_event(function: &Function, include_reverted: bool) -> Event {
+    let mut inputs = function
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, param)| EventParam {
+            ty: param.ty.clone(),
+            name: if param.name.is_empty() { format!("arg{index}") } else { param.name.clone() },
+            indexed: false,
+            components: param.components.clone(),
+            internal_type: param.internal_type.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    if include_reverted {
+        inputs.push(EventParam {
+            ty: "bool".to_string(),
+            name: "succeeded".to_string(),
+            indexed: false,
+            components: vec![],
+            internal_type: None,
+        });
+    }
+
+    Event { name: function_call_event_name(&function.name), inputs, anonymous: false }
+}
+
+/// Whether each of the traces reverted, a trace is reverted when it or any call it was made from
+/// reverted. Parents always come before their subcalls in the traces.
+pub fn reverted_traces(traces: &[LocalizedTransactionTrace]) -> Vec<bool> {
+    let mut reverted_calls: Vec<(Option<TxHash>, Option<u64>, &[usize])> = Vec::new();
+
+    traces
+        .iter()
+        .map(|trace| {
+            let trace_address = trace.trace.trace_address.as_slice();
+            let reverted = trace.trace.error.is_some()
+                || reverted_calls.iter().any(|(hash, position, address)| {
+                    *hash == trace.transaction_hash
+                        && *position == trace.transaction_position
+                        && trace_address.starts_with(address)
+                });
+            if trace.trace.error.is_some() {
+                reverted_calls.push((
+                    trace.transaction_hash,
+                    trace.transaction_position,
+                    trace_address,
+                ));
+            }
+            reverted
+        })
+        .collect()
+}
+
+/// Turns the calls of a function found in the call traces of a block into logs of the event it is
+/// indexed as, so they flow through the same handlers as the logs of any other event.
+#[derive(Debug, Clone)]
+pub struct FunctionCallFilter {
+    pub function: Function,
+    pub include_reverted: bool,
+    pub method: TraceProcessingMethod,
+}
+
+impl FunctionCallFilter {
+    /// The calls of the function to `addresses` in the traces of one block. The log index is the
+    /// position of the call among the matching calls of the block.
+    pub fn call_logs(
+        &self,
+        event_signature: B256,
+        addresses: &Option<HashSet<Address>>,
+        traces: &[LocalizedTransactionTrace],
+    ) -> Vec<Log> {
+        let selector = self.function.selector();
+        let mut logs = Vec::new();
+
+        for (trace, reverted) in traces.iter().zip(reverted_traces(traces)) {
+            let Action::Call(call) = &trace.trace.action else {
+                continue;
+            };
+            if call.call_type != CallType::Call
+                || (reverted && !self.include_reverted)
+                || !call.input.starts_with(selector.as_slice())
+                || addresses.as_ref().is_some_and(|addresses| !addresses.contains(&call.to))
+            {
+                continue;
+            }
+
+            let mut values = match self.function.abi_decode_input(&call.input[4..]) {
+                Ok(values) => values,
+                Err(e) => {
+                    warn!(
+                        "Skipping call to {} with calldata {} can not decode - err: {}",
+                        self.function.name, call.input, e
+                    );
+                    continue;
+                }
+            };
+            if self.include_reverted {
+                values.push(DynSolValue::Bool(!reverted));
+            }
+
+            logs.push(Log {
+                inner: alloy::primitives::Log {
+                    address: call.to,
+                    data: LogData::new_unchecked(
+                        vec![event_signature],
+                        DynSolValue::Tuple(values).abi_encode_params().into(),
+                    ),
+                },
+                block_hash: trace.block_hash,
+                block_number: trace.block_number,
+                block_timestamp: None,
+                transaction_hash: trace.transaction_hash,
+                transaction_index: trace.transaction_position,
+                log_index: Some(logs.len() as u64),
+                removed: false,
+            });
+        }
+
+        logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        dyn_abi::EventExt,
+        primitives::{address, Bytes, U256},
+        rpc::types::trace::parity::{CallAction, TransactionTrace},
+    };
+
+    use super::*;
+
+    fn call_trace(
+        to: Address,
+        input: Bytes,
+        trace_address: Vec<usize>,
+        error: Option<&str>,
+    ) -> LocalizedTransactionTrace {
+        LocalizedTransactionTrace {
+            trace: TransactionTrace {
+                action: Action::Call(CallAction {
+                    from: Address::ZERO,
+                    to,
+                    value: U256::ZERO,
+                    gas: 0,
+                    input,
+                    call_type: CallType::Call,
+                }),
+                error: error.map(str::to_string),
+                result: None,
+                subtraces: 0,
+                trace_address,
+            },
+            block_hash: Some(B256::repeat_byte(1)),
+            block_number: Some(100),
+            transaction_hash: Some(B256::repeat_byte(2)),
+            transaction_position: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_calls_become_logs_of_the_call_event() {
+        let function = Function::parse("function transfer(address to, uint256)").unwrap();
+        let token = address!("0x00000000000000000000000000000000000000aa");
+        let input = |amount: u64| -> Bytes {
+            function
+                .abi_encode_input(&[
+                    DynSolValue::Address(token),
+                    DynSolValue::from(U256::from(amount)),
+                ])
+                .unwrap()
+                .into()
+        };
+
+        let traces = vec![
+            call_trace(token, input(1), vec![], None),
+            call_trace(Address::ZERO, input(2), vec![0], None),
+            // reverted with its parent
+            call_trace(token, input(3), vec![1], Some("Reverted")),
+            call_trace(token, input(4), vec![1, 0], None),
+            call_trace(token, Bytes::from_static(&[1, 2, 3, 4]), vec![2], None),
+        ];
+
+        let event = function_call_event(&function, true);
+        assert_eq!(event.name, "TransferCall");
+        assert_eq!(event.inputs[1].name, "arg1");
+
+        let filter = FunctionCallFilter {
+            function: function.clone(),
+            include_reverted: true,
+            method: TraceProcessingMethod::TraceBlock,
+        };
+        let addresses = Some(HashSet::from([token]));
+        let logs = filter.call_logs(event.selector(), &addresses, &traces);
+
+        let decoded = logs
+            .iter()
+            .map(|log| {
+                let decoded =
+                    event.decode_log_parts(log.topics().to_vec(), &log.data().data).unwrap();
+                (
+                    decoded.body[1].as_uint().unwrap().0.to::<u64>(),
+                    decoded.body[2].as_bool().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, vec![(1, true), (3, false), (4, false)]);
+        assert_eq!(
+            logs.iter().map(|log| log.log_index).collect::<Vec<_>>(),
+            vec![Some(0), Some(1), Some(2)]
+        );
+        assert_eq!(logs[0].transaction_index, Some(3));
+
+        let successful = FunctionCallFilter { include_reverted: false, ..filter };
+        assert_eq!(successful.call_logs(event.selector(), &addresses, &traces).len(), 1);
+    }
+}
@@ -2,10 +2,22 @@ use alloy::primitives::B256;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// Tells consumers whether a message carries newly indexed events or retracts events which were
+/// reorged out of the canonical chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EventMessageType {
+    #[default]
+    Event,
+    Removed,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EventMessage {
     pub event_name: String,
     pub event_data: Value,
     pub event_signature_hash: B256,
     pub network: String,
+    #[serde(default)]
+    pub message_type: EventMessageType,
 }
@@ -2,12 +2,14 @@ pub mod callback_registry;
 
 pub mod config;
 pub mod contract_setup;
+pub mod failed_batches;
+pub mod function_call;
 
 mod rindexer_event_filter;
 pub use rindexer_event_filter::{BuildRindexerFilterError, RindexerEventFilter};
 
 mod message;
-pub use message::EventMessage;
+pub use message::{EventMessage, EventMessageType};
 
 mod conditions;
 mod factory_event_filter_sync;
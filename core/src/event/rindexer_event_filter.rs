@@ -2,9 +2,10 @@ use crate::event::contract_setup::{AddressDetails, FilterDetails};
 use crate::event::factory_event_filter_sync::{
     get_known_factory_deployed_addresses, GetKnownFactoryDeployedAddressesParams,
 };
+use crate::event::function_call::FunctionCallFilter;
 use crate::manifest::storage::CsvDetails;
 use crate::PostgresClient;
-use alloy::rpc::types::Topic;
+use alloy::rpc::types::{Filter, Log, Topic};
 use alloy::{
     primitives::{Address, B256, U64},
     rpc::types::ValueOrArray,
@@ -26,6 +27,8 @@ pub struct SimpleEventFilter {
     pub topics: [Topic; 4],
     pub current_block: U64,
     pub next_block: U64,
+
+    pub function_call: Option<Arc<FunctionCallFilter>>,
 }
 
 impl SimpleEventFilter {
@@ -67,6 +70,8 @@ pub struct FactoryFilter {
 
     pub current_block: U64,
     pub next_block: U64,
+
+    pub function_call: Option<Arc<FunctionCallFilter>>,
 }
 
 impl std::fmt::Debug for FactoryFilter {
@@ -82,6 +87,7 @@ impl std::fmt::Debug for FactoryFilter {
             .field("topic_id", &self.topic_id)
             .field("current_block", &self.current_block)
             .field("next_block", &self.next_block)
+            .field("function_call", &self.function_call)
             .finish()
     }
 }
@@ -142,26 +148,30 @@ impl RindexerEventFilter {
                 .unwrap_or_default(),
             current_block,
             next_block,
+            function_call: None,
         }))
     }
 
     pub fn new_filter(
         topic_id: &B256,
-        _: &str,
+        event_name: &str,
         filter_details: &FilterDetails,
         current_block: U64,
         next_block: U64,
     ) -> Result<RindexerEventFilter, BuildRindexerFilterError> {
+        let index_filter = filter_details.indexed_filters.iter().find_map(|indexed_filters| {
+            indexed_filters.iter().find(|&n| n.event_name == event_name)
+        });
+
         Ok(RindexerEventFilter::Filter(SimpleEventFilter {
             address: None,
             topic_id: *topic_id,
-            topics: filter_details
-                .clone()
-                .indexed_filters
+            topics: index_filter
                 .map(|indexed_filter| indexed_filter.clone().into())
                 .unwrap_or_default(),
             current_block,
             next_block,
+            function_call: None,
         }))
     }
 
@@ -173,6 +183,71 @@ impl RindexerEventFilter {
         }
     }
 
+    /// Set for the events the calls of a contract function are indexed as, their logs are built
+    /// from the call traces of the blocks instead of being fetched.
+    pub fn function_call(&self) -> Option<&Arc<FunctionCallFilter>> {
+        match self {
+            RindexerEventFilter::Address(filter) => filter.function_call.as_ref(),
+            RindexerEventFilter::Filter(filter) => filter.function_call.as_ref(),
+            RindexerEventFilter::Factory(filter) => filter.function_call.as_ref(),
+        }
+    }
+
+    pub fn with_function_call(self, function_call: Option<Arc<FunctionCallFilter>>) -> Self {
+        match self {
+            Self::Address(filter) => Self::Address(SimpleEventFilter { function_call, ..filter }),
+            Self::Filter(filter) => Self::Filter(SimpleEventFilter { function_call, ..filter }),
+            Self::Factory(filter) => Self::Factory(FactoryFilter { function_call, ..filter }),
+        }
+    }
+
+    fn topics(&self) -> &[Topic; 4] {
+        match self {
+            RindexerEventFilter::Address(filter) => &filter.topics,
+            RindexerEventFilter::Filter(filter) => &filter.topics,
+            RindexerEventFilter::Factory(filter) => &filter.topics,
+        }
+    }
+
+    /// The topic0s the filter matches, only the event's own signature unless it was combined
+    /// with other events through [`RindexerEventFilter::with_event_signatures`].
+    pub fn event_signatures(&self) -> Vec<B256> {
+        let topic0 = &self.topics()[0];
+        if topic0.is_empty() {
+            vec![self.event_signature()]
+        } else {
+            topic0.iter().copied().collect()
+        }
+    }
+
+    /// Match the logs of every event in `topic_ids` instead of a single event. The indexed topic
+    /// filters are dropped as they belong to single events, the logs have to be checked against
+    /// them once they are routed back to their event.
+    pub fn with_event_signatures(self, topic_ids: Vec<B256>) -> Self {
+        let topics = [topic_ids.into(), Topic::default(), Topic::default(), Topic::default()];
+        match self {
+            Self::Address(filter) => Self::Address(SimpleEventFilter { topics, ..filter }),
+            Self::Filter(filter) => Self::Filter(SimpleEventFilter { topics, ..filter }),
+            Self::Factory(filter) => Self::Factory(FactoryFilter { topics, ..filter }),
+        }
+    }
+
+    /// Whether the log matches the indexed topic filters of the event.
+    pub fn matches_indexed_topics(&self, log: &Log) -> bool {
+        let log_topics = log.topics();
+        self.topics()[1..].iter().enumerate().all(|(index, topic)| {
+            topic.is_empty() || log_topics.get(index + 1).is_some_and(|value| topic.matches(value))
+        })
+    }
+
+    /// Whether the log is one `eth_getLogs` would return for the filter, used by log sources
+    /// which filter logs locally. `addresses` are the filter's [`RindexerEventFilter::contract_addresses`].
+    pub fn matches_log(&self, addresses: &Option<HashSet<Address>>, log: &Log) -> bool {
+        addresses.as_ref().is_none_or(|addresses| addresses.contains(&log.address()))
+            && log.topic0().is_some_and(|topic| self.event_signatures().contains(topic))
+            && self.matches_indexed_topics(log)
+    }
+
     pub fn topic1(&self) -> Topic {
         match self {
             RindexerEventFilter::Address(filter) => filter.topics[1].clone(),
@@ -235,6 +310,27 @@ impl RindexerEventFilter {
         }
     }
 
+    /// The filter for an `eth_subscribe("logs")` wake up. Factory children are still being found
+    /// while live indexing, so those only subscribe on the topics.
+    pub fn subscription_filter(&self) -> Filter {
+        let filter = Filter::new()
+            .event_signature(self.event_signatures())
+            .topic1(self.topic1())
+            .topic2(self.topic2())
+            .topic3(self.topic3());
+
+        match self {
+            RindexerEventFilter::Address(filter_details)
+            | RindexerEventFilter::Filter(filter_details) => {
+                match filter_details.contract_address() {
+                    Some(addresses) => filter.address(addresses.into_iter().collect::<Vec<_>>()),
+                    None => filter,
+                }
+            }
+            RindexerEventFilter::Factory(_) => filter,
+        }
+    }
+
     pub async fn contract_addresses(&self) -> Option<HashSet<Address>> {
         match self {
             RindexerEventFilter::Address(filter) => filter.contract_address(),
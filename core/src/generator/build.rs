@@ -517,6 +517,7 @@ serde = {{ version = "1.0", features = ["derive"] }}
 
                 let mut enable_graphql = false;
                 let mut enable_indexer = false;
+                let mut replay_failed_batches = false;
                 
                 let mut port: Option<u16> = None;
 
@@ -530,6 +531,7 @@ serde = {{ version = "1.0", features = ["derive"] }}
                     match arg.as_str() {
                         "--graphql" => enable_graphql = true,
                         "--indexer" => enable_indexer = true,
+                        "--replay-failed-batches" => replay_failed_batches = true,
                         _ if arg.starts_with("--port=") || arg.starts_with("--p") => {
                             if let Some(value) = arg.split('=').nth(1) {
                                 let overridden_port = value.parse::<u16>();
@@ -556,6 +558,7 @@ serde = {{ version = "1.0", features = ["derive"] }}
                                 Some(IndexingDetails {
                                     registry: register_all_handlers(&manifest_path).await,
                                     trace_registry: TraceCallbackRegistry { events: vec![] },
+                                    replay_failed_batches,
                                 })
                             } else {
                                 None
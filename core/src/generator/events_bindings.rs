@@ -10,6 +10,7 @@ use crate::{
     },
     database::postgres::generate::{
         generate_column_names_only_with_base_properties, generate_event_table_full_name,
+        TX_CONTEXT_COLUMNS,
     },
     helpers::camel_to_snake,
     manifest::{
@@ -597,6 +598,7 @@ fn generate_event_bindings_code(
                     .index_event_in_order
                     .as_ref()
                     .map_or(false, |vec| vec.contains(&event_name.to_string()));
+                let on_error = contract_details.on_error_for_event(event_name);
 
                 // Expect providers to have been initialized, but it's an async init so this should
                 // be fast but for correctness we must await each future.
@@ -627,10 +629,19 @@ fn generate_event_bindings_code(
                                                         .iter()
                                                         .find(|n| n.name == c.network)
                                                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                            include_block_timestamp: contract_details
+                                                        .include_block_timestamp
+                                                        .unwrap_or_else(|| rindexer_yaml
+                                                            .networks
+                                                            .iter()
+                                                            .find(|n| n.name == c.network)
+                                                            .map_or(false, |n| n.include_block_timestamp.unwrap_or_default())),
+                            include_tx_context: contract_details.include_tx_context.unwrap_or_default(),
                         }})
                         .collect(),
                     abi: contract_details.abi,
                     reorg_safe_distance: contract_details.reorg_safe_distance.unwrap_or_default(),
+                    logs_fetch_mode: contract_details.logs_fetch_mode.unwrap_or_default(),
                 }};
 
                 let callback: Arc<dyn Fn(Vec<EventResult>) -> BoxFuture<'static, EventCallbackResult<()>> + Send + Sync> = match self {{
@@ -642,6 +653,7 @@ fn generate_event_bindings_code(
                     indexer_name: "{indexer_name}".to_string(),
                     event_name: event_name.to_string(),
                     index_event_in_order,
+                    on_error,
                     topic_id: topic_id.parse::<B256>().unwrap(),
                     contract,
                     callback,
@@ -739,6 +751,14 @@ pub fn generate_event_handlers(
             };
         "#,
     );
+    let include_tx_context = contract.include_tx_context.unwrap_or_default();
+    if !storage.postgres_disable_create_tables() {
+        imports.push_str(if include_tx_context {
+            "use rindexer::{map_block_timestamp_to_ethereum_wrapper, map_tx_context_to_ethereum_wrapper};\n"
+        } else {
+            "use rindexer::map_block_timestamp_to_ethereum_wrapper;\n"
+        });
+    }
     imports.push_str("use std::sync::Arc;\n");
     imports.push_str(&format!(
         r#"use std::path::PathBuf;
@@ -807,7 +827,7 @@ pub fn generate_event_handlers(
                 csv_write = format!(
                     r#"
                       let mut csv_bulk_data: Vec<Vec<String>> = vec![];
-                      for result in &results {{
+                      for result in results.iter().filter(|result| !result.tx_information.removed) {{
                         {inner_csv_write}
                       }}
 
@@ -883,16 +903,28 @@ pub fn generate_event_handlers(
             data.push_str(
                 "\nEthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),",
             );
-            data.push_str("\nEthereumSqlTypeWrapper::U256(result.tx_information.log_index)");
+            data.push_str("\nEthereumSqlTypeWrapper::U256(result.tx_information.log_index),");
+            data.push_str("\nmap_block_timestamp_to_ethereum_wrapper(&result.tx_information)");
             data.push_str("\n]");
 
+            let mut columns_names = generate_column_names_only_with_base_properties(&event.inputs);
+            columns_names.push("block_timestamp".to_string());
+            let tx_context_write = if include_tx_context {
+                columns_names.extend(TX_CONTEXT_COLUMNS.iter().map(|(name, _)| name.to_string()));
+                "data.extend(map_tx_context_to_ethereum_wrapper(&result.tx_information));"
+            } else {
+                ""
+            };
+
             postgres_write = format!(
                 r#"
                     let mut postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = vec![];
                     {csv_bulk_data}
-                    for result in results.iter() {{
+                    // removed results retract rows written before, they must not be written again
+                    for result in results.iter().filter(|result| !result.tx_information.removed) {{
                         {csv_write}
-                        let data = {data};
+                        let {data_mut}data = {data};
+                        {tx_context_write}
                         postgres_bulk_data.push(data);
                     }}
 
@@ -944,12 +976,14 @@ pub fn generate_event_handlers(
                     generate_event_table_full_name(indexer_name, &contract.name, &event.name),
                 handler_name = event.name,
                 event_type_name = event_type_name,
-                columns_names = generate_column_names_only_with_base_properties(&event.inputs)
+                columns_names = columns_names
                     .iter()
                     .map(|item| format!("\"{item}\".to_string()"))
                     .collect::<Vec<String>>()
                     .join(", "),
                 data = data,
+                data_mut = if include_tx_context { "mut " } else { "" },
+                tx_context_write = tx_context_write,
                 csv_write = csv_write,
                 csv_bulk_data = if storage.csv_enabled() {
                     "let mut csv_bulk_data: Vec<Vec<String>> = vec![];"
@@ -1,4 +1,7 @@
-use crate::{manifest::network::Network, types::code::Code};
+use crate::{
+    manifest::network::{ChainProfile, Network, NetworkRpc, RpcAuth, RpcEndpoint},
+    types::code::Code,
+};
 
 fn network_provider_name(network: &Network) -> String {
     network_provider_name_from_name(&network.name)
@@ -32,13 +35,235 @@ fn generate_reth_init_fn(network: &Network) -> Code {
     }
 }
 
+fn generate_env_value_code(value: &str) -> String {
+    format!(r#"public_read_env_value({value:?}).unwrap_or({value:?}.to_string())"#)
+}
+
+/// Header and auth values keep their `${VAR}` placeholders, wherever they are in the value, and
+/// are substituted when the generated code runs.
+fn generate_env_template_code(value: &str) -> String {
+    format!("rindexer::public_substitute_env_variables({value:?})")
+}
+
+fn generate_rpc_endpoint_code(endpoint: &RpcEndpoint) -> String {
+    format!(
+        "rindexer::manifest::network::RpcEndpoint {{ url: {url}, weight: {weight:?}, priority: {priority:?}, max_block_range: {max_block_range} }}",
+        url = generate_env_value_code(&endpoint.url),
+        weight = endpoint.weight,
+        priority = endpoint.priority,
+        max_block_range = if let Some(max_block_range) = endpoint.max_block_range {
+            format!("Some(U64::from({max_block_range}))")
+        } else {
+            "None".to_string()
+        },
+    )
+}
+
+fn generate_chain_profile_code(chain_profile: Option<&ChainProfile>) -> String {
+    let Some(chain_profile) = chain_profile else {
+        return "None".to_string();
+    };
+
+    format!(
+        "Some(rindexer::manifest::network::ChainProfile {{ block_time_ms: {block_time_ms:?}, zk: {zk}, reorg_depth: {reorg_depth:?}, trace_methods: {trace_methods}, max_logs_range: {max_logs_range} }})",
+        block_time_ms = chain_profile.block_time_ms,
+        zk = if let Some(zk) = chain_profile.zk {
+            format!("Some(rindexer::manifest::chain_profile::ZkFlavour::{zk:?})")
+        } else {
+            "None".to_string()
+        },
+        reorg_depth = chain_profile.reorg_depth,
+        trace_methods = if let Some(trace_methods) = &chain_profile.trace_methods {
+            format!(
+                "Some(vec![{}])",
+                trace_methods
+                    .iter()
+                    .map(|method| format!(
+                        "rindexer::manifest::native_transfer::TraceProcessingMethod::{method:?}"
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        } else {
+            "None".to_string()
+        },
+        max_logs_range = if let Some(max_logs_range) = chain_profile.max_logs_range {
+            format!("Some(U64::from({max_logs_range}))")
+        } else {
+            "None".to_string()
+        },
+    )
+}
+
+fn generate_rpc_headers_code(network: &Network) -> String {
+    if network.headers.is_none() && network.auth.is_none() {
+        return "HeaderMap::new()".to_string();
+    }
+
+    let headers = network
+        .headers
+        .iter()
+        .flatten()
+        .map(|(name, value)| {
+            format!("({name:?}.to_string(), {})", generate_env_template_code(value))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let auth = match &network.auth {
+        Some(RpcAuth::Bearer(token)) => format!(
+            "Some(rindexer::manifest::network::RpcAuth::Bearer({}))",
+            generate_env_template_code(token)
+        ),
+        Some(RpcAuth::Basic { username, password }) => format!(
+            "Some(rindexer::manifest::network::RpcAuth::Basic {{ username: {}, password: {} }})",
+            generate_env_template_code(username),
+            generate_env_template_code(password)
+        ),
+        None => "None".to_string(),
+    };
+
+    format!(
+        r#"rindexer::manifest::network::rpc_headers(HeaderMap::new(), [{headers}], {auth}.as_ref()).expect({expect:?})"#,
+        expect = format!("Invalid rpc headers for {}", network.name)
+    )
+}
+
+fn generate_option_code(value: Option<String>) -> String {
+    value.map(|value| format!("Some({value})")).unwrap_or_else(|| "None".to_string())
+}
+
 fn generate_network_lazy_provider_code(network: &Network) -> Code {
+    let compute_units_per_second = generate_option_code(
+        network
+            .compute_units_per_second
+            .map(|compute_units_per_second| compute_units_per_second.to_string()),
+    );
+    let max_block_range = generate_option_code(
+        network.max_block_range.map(|max_block_range| format!("U64::from({max_block_range})")),
+    );
+    let block_poll_frq = generate_option_code(
+        network.block_poll_frequency.map(|block_frq| format!("BlockPollFrequency::{block_frq:?}")),
+    );
+    let get_logs_settings = generate_option_code(
+        network
+            .get_logs_settings
+            .as_ref()
+            .map(|settings| format!("AddressFiltering::{:?}", settings.address_filtering)),
+    );
+    let reorg_safe_distance = generate_option_code(
+        network
+            .reorg_safe_distance
+            .map(|reorg_safe_distance| format!("ReorgSafeDistance::{reorg_safe_distance:?}")),
+    );
+
+    // a list of endpoints, a live subscription, a logs source or a chain profile is handled by
+    // rindexer's router, reth always uses its own ipc endpoint
+    let endpoints = match &network.rpc {
+        _ if network.is_reth_enabled() => None,
+        NetworkRpc::Endpoints(_) => Some(network.rpc.endpoints()),
+        NetworkRpc::Url(_)
+            if network.live_subscription.is_some()
+                || network.compute_unit_costs.is_some()
+                || network.logs_source.is_some()
+                || network.chain_profile.is_some() =>
+        {
+            Some(network.rpc.endpoints())
+        }
+        _ => None,
+    };
+
+    let create_client = if let Some(endpoints) = &endpoints {
+        format!(
+            r#"rindexer::provider::create_client_with_endpoints(
+                &[{endpoints}],
+                {chain_id},
+                rindexer::provider::CreateClientOptions {{
+                    live_subscription: {live_subscription},
+                    logs_source: {logs_source},
+                    chain_profile: {chain_profile},
+                    compute_units_per_second: {compute_units_per_second},
+                    compute_unit_costs: {compute_unit_costs},
+                    max_block_range: {max_block_range},
+                    block_poll_frequency: {block_poll_frq},
+                    custom_headers: {custom_headers},
+                    address_filtering: {get_logs_settings},
+                    reorg_safe_distance: {reorg_safe_distance},
+                    chain_state_notification,
+                }},
+            )"#,
+            endpoints =
+                endpoints.iter().map(generate_rpc_endpoint_code).collect::<Vec<_>>().join(", "),
+            chain_id = network.chain_id,
+            live_subscription =
+                generate_option_code(network.live_subscription.map(|live_subscription| {
+                    format!("rindexer::manifest::network::LiveSubscription::{live_subscription:?}")
+                })),
+            logs_source = generate_option_code(network.logs_source.map(|logs_source| {
+                format!("rindexer::manifest::network::LogsSource::{logs_source:?}")
+            })),
+            chain_profile = generate_chain_profile_code(network.chain_profile.as_ref()),
+            compute_unit_costs =
+                generate_option_code(network.compute_unit_costs.as_ref().map(|costs| {
+                    format!(
+                        "std::collections::BTreeMap::from([{}])",
+                        costs
+                            .iter()
+                            .map(|(method, cost)| format!("({method:?}.to_string(), {cost})"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })),
+            custom_headers = generate_rpc_headers_code(network),
+        )
+    } else {
+        let url = if network.is_reth_enabled() {
+            network.get_reth_ipc_path().unwrap_or_else(|| network.rpc.primary_url().to_string())
+        } else {
+            network.rpc.primary_url().to_string()
+        };
+        let is_shadow = network.rpc.primary_url().contains("shadow");
+
+        let url = generate_env_value_code(&url);
+        let custom_headers = generate_rpc_headers_code(network);
+
+        if is_shadow {
+            format!(
+                "create_shadow_client(&{url}, {chain_id}, {compute_units_per_second}, {max_block_range}, {block_poll_frq}, {custom_headers}, {get_logs_settings}, {reorg_safe_distance}, chain_state_notification)",
+                chain_id = network.chain_id,
+            )
+        } else if network.reorg_safe_distance.is_some() {
+            // `create_client` has no reorg safe distance argument
+            format!(
+                r#"create_client_with_endpoints(
+                    &[RpcEndpoint::new({url})],
+                    {chain_id},
+                    CreateClientOptions {{
+                        compute_units_per_second: {compute_units_per_second},
+                        max_block_range: {max_block_range},
+                        block_poll_frequency: {block_poll_frq},
+                        custom_headers: {custom_headers},
+                        address_filtering: {get_logs_settings},
+                        reorg_safe_distance: {reorg_safe_distance},
+                        chain_state_notification,
+                        ..Default::default()
+                    }},
+                )"#,
+                chain_id = network.chain_id,
+            )
+        } else {
+            format!(
+                "create_client(&{url}, {chain_id}, {compute_units_per_second}, {max_block_range}, {block_poll_frq}, {custom_headers}, {get_logs_settings}, chain_state_notification)",
+                chain_id = network.chain_id,
+            )
+        }
+    };
+
     Code::new(format!(
         r#"
         {network_name}
             .get_or_init(|| async {{
                 {reth_init_fn}
-                {client_fn}(&public_read_env_value("{network_url}").unwrap_or("{network_url}".to_string()), {chain_id}, {compute_units_per_second}, {max_block_range}, {block_poll_frq} {placeholder_headers}, {get_logs_settings}, {chain_state_notification})
+                {create_client}
                 .await
                 .expect("Error creating provider")
             }})
@@ -46,42 +271,6 @@ fn generate_network_lazy_provider_code(network: &Network) -> Code {
             .clone()
         "#,
         network_name = network_provider_name(network),
-        network_url = if network.is_reth_enabled() {
-            if let Some(ipc_path) = network.get_reth_ipc_path() {
-                ipc_path
-            } else {
-                network.rpc.clone()
-            }
-        } else {
-            network.rpc.clone()
-        },
-        chain_id = network.chain_id,
-        compute_units_per_second =
-            if let Some(compute_units_per_second) = network.compute_units_per_second {
-                format!("Some({compute_units_per_second})")
-            } else {
-                "None".to_string()
-            },
-        max_block_range = if let Some(max_block_range) = network.max_block_range {
-            format!("Some(U64::from({max_block_range}))")
-        } else {
-            "None".to_string()
-        },
-        block_poll_frq = if let Some(block_frq) = network.block_poll_frequency {
-            format!("Some(BlockPollFrequency::{block_frq:?})")
-        } else {
-            "None".to_string()
-        },
-        get_logs_settings = if let Some(settings) = &network.get_logs_settings {
-            format!("Some(AddressFiltering::{:?})", settings.address_filtering)
-        } else {
-            "None".to_string()
-        },
-        client_fn =
-            if network.rpc.contains("shadow") { "create_shadow_client" } else { "create_client" },
-        placeholder_headers =
-            if network.rpc.contains("shadow") { "" } else { ", HeaderMap::new()" },
-        chain_state_notification = "chain_state_notification",
         reth_init_fn = generate_reth_init_fn(network),
     ))
 }
@@ -141,8 +330,8 @@ pub fn generate_networks_code(networks: &[Network]) -> Code {
     use alloy::{primitives::U64, transports::http::reqwest::header::HeaderMap};
     use rindexer::{
         lazy_static,
-        manifest::network::{AddressFiltering, BlockPollFrequency},
-        provider::{RindexerProvider, create_client, JsonRpcCachedProvider, RetryClientError},
+        manifest::network::{AddressFiltering, BlockPollFrequency, ReorgSafeDistance, RpcEndpoint},
+        provider::{RindexerProvider, create_client, create_client_with_endpoints, CreateClientOptions, JsonRpcCachedProvider, RetryClientError},
         notifications::ChainStateNotification,
         reth::node::start_reth_node_with_exex,
         public_read_env_value
@@ -156,17 +345,32 @@ pub fn generate_networks_code(networks: &[Network]) -> Code {
         rpc_url: &str,
         chain_id: u64,
         compute_units_per_second: Option<u64>,
-        block_poll_frequency: Option<BlockPollFrequency>,
         max_block_range: Option<U64>,
+        block_poll_frequency: Option<BlockPollFrequency>,
+        mut custom_headers: HeaderMap,
         address_filtering: Option<AddressFiltering>,
+        reorg_safe_distance: Option<ReorgSafeDistance>,
         chain_state_notification: Option<Sender<ChainStateNotification>>,
     ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
-        let mut header = HeaderMap::new();
-        header.insert(
+        custom_headers.insert(
             "X-SHADOW-API-KEY",
             public_read_env_value("RINDEXER_PHANTOM_API_KEY").unwrap().parse().unwrap(),
         );
-        create_client(rpc_url, chain_id, compute_units_per_second, max_block_range, block_poll_frequency, header, address_filtering, chain_state_notification).await
+        create_client_with_endpoints(
+            &[RpcEndpoint::new(rpc_url.to_string())],
+            chain_id,
+            CreateClientOptions {
+                compute_units_per_second,
+                max_block_range,
+                block_poll_frequency,
+                custom_headers,
+                address_filtering,
+                reorg_safe_distance,
+                chain_state_notification,
+                ..Default::default()
+            },
+        )
+        .await
     }
         "#
         .to_string(),
@@ -456,7 +456,7 @@ fn generate_trace_bindings_code(
                     TraceCallbackRegistry, TraceCallbackRegistryInformation, TraceCallbackResult,
                     TraceResult, TxInformation, HasTxInformation
                 }},
-                contract_setup::{{TraceInformation, NetworkTrace}},
+                contract_setup::{{TraceInformation, TraceEventKind, NetworkTrace}},
             }},
             manifest::{{
                 contract::{{Contract, ContractDetails}},
@@ -541,6 +541,7 @@ fn generate_trace_bindings_code(
 
                 let trace_information = TraceInformation {{
                     name: "{EVENT_NAME}".to_string(),
+                    kind: TraceEventKind::NativeTransfer,
                     details: contract_details
                         .iter()
                         .map(|c| NetworkTrace {{
@@ -553,6 +554,7 @@ fn generate_trace_bindings_code(
                             start_block: c.start_block,
                             end_block: c.end_block,
                             method: c.method,
+                            deployers: None,
                         }})
                         .collect(),
                     reorg_safe_distance: rindexer_yaml
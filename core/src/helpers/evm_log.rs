@@ -118,7 +118,7 @@ pub fn topic_in_bloom(topic_id: B256, logs_bloom: Bloom) -> bool {
 
 pub fn is_relevant_block(
     contract_address: &Option<HashSet<Address>>,
-    topic_id: &B256,
+    topic_ids: &[B256],
     latest_block: &AnyRpcBlock,
 ) -> bool {
     let logs_bloom = latest_block.header.logs_bloom;
@@ -129,7 +129,7 @@ pub fn is_relevant_block(
         }
     }
 
-    if !topic_in_bloom(*topic_id, logs_bloom) {
+    if topic_ids.iter().all(|topic_id| !topic_in_bloom(*topic_id, logs_bloom)) {
         return false;
     }
 
@@ -24,6 +24,8 @@ pub use file::{
     load_env_from_project_path, write_file, CreateModFileError, WriteFileError,
 };
 use rand::{distr::Alphanumeric, Rng};
+use regex::{Captures, Regex};
+use tracing::error;
 
 pub fn camel_to_snake(s: &str) -> String {
     camel_to_snake_advanced(s, false)
@@ -173,6 +175,28 @@ pub fn public_read_env_value(var_name: &str) -> Result<String, VarError> {
     env::var(var_name)
 }
 
+pub fn substitute_env_variables(contents: &str) -> Result<String, regex::Error> {
+    let re = Regex::new(r"\$\{([^}]+)\}")?;
+    let result = re.replace_all(contents, |caps: &Captures| {
+        let var_name = &caps[1];
+        match env::var(var_name) {
+            Ok(val) => val,
+            Err(_) => {
+                error!("Environment variable {} not found", var_name);
+                panic!("Environment variable {var_name} not found")
+            }
+        }
+    });
+    Ok(result.into_owned())
+}
+
+/// Replaces every `${VAR}` in the value with its environment variable the same way the manifest
+/// is read, generated rust projects resolve the values which embed env variables with it.
+pub fn public_substitute_env_variables(value: &str) -> String {
+    dotenv().ok();
+    substitute_env_variables(value).expect("Env variable pattern is a valid regex")
+}
+
 pub fn replace_env_variable_to_raw_name(rpc: &str) -> String {
     if rpc.starts_with("${") && rpc.ends_with('}') {
         rpc[2..rpc.len() - 1].to_string()
@@ -221,6 +245,20 @@ mod tests {
         assert_eq!(camel_to_snake_advanced("ERC20", true), "erc20");
     }
 
+    #[test]
+    fn test_substitute_env_variables_inside_values() {
+        env::set_var("RINDEXER_TEST_RPC_TOKEN", "secret");
+        assert_eq!(
+            public_substitute_env_variables("Bearer ${RINDEXER_TEST_RPC_TOKEN}"),
+            "Bearer secret"
+        );
+        assert_eq!(
+            public_substitute_env_variables("${RINDEXER_TEST_RPC_TOKEN}-\"quoted\""),
+            "secret-\"quoted\""
+        );
+        assert_eq!(public_substitute_env_variables("plain"), "plain");
+    }
+
     #[test]
     fn test_underscore_separated() {
         assert_eq!(to_pascal_case("user_profile_update"), "UserProfileUpdate");
@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use alloy::primitives::U64;
+use tokio::sync::Mutex;
+
+use crate::{
+    event::config::EventProcessingConfig,
+    indexer::last_synced::{update_progress_and_last_synced_task, update_synced_segments},
+};
+
+/// Splits `[from_block, to_block]` into `segments` ranges of about the same size which are
+/// fetched concurrently. Every segment holds the block ranges it still has to fetch, the parts
+/// already in `synced` (sorted and merged) from before a restart are left out.
+pub fn backfill_segments(
+    from_block: U64,
+    to_block: U64,
+    segments: usize,
+    synced: &[(U64, U64)],
+) -> Vec<Vec<(U64, U64)>> {
+    if from_block > to_block {
+        return vec![];
+    }
+
+    let blocks = to_block - from_block + U64::from(1);
+    let segments = U64::from(segments.max(1)).min(blocks);
+    let segment_size = blocks.div_ceil(segments);
+
+    let mut ranges = Vec::new();
+    let mut segment_from = from_block;
+    while segment_from <= to_block {
+        let segment_to = (segment_from + segment_size - U64::from(1)).min(to_block);
+        let unsynced = unsynced_ranges(segment_from, segment_to, synced);
+        if !unsynced.is_empty() {
+            ranges.push(unsynced);
+        }
+        segment_from = segment_to + U64::from(1);
+    }
+
+    ranges
+}
+
+fn unsynced_ranges(from_block: U64, to_block: U64, synced: &[(U64, U64)]) -> Vec<(U64, U64)> {
+    let mut ranges = Vec::new();
+    let mut next_block = from_block;
+
+    for (synced_from, synced_to) in synced {
+        if *synced_to < next_block {
+            continue;
+        }
+        if *synced_from > to_block {
+            break;
+        }
+        if *synced_from > next_block {
+            ranges.push((next_block, *synced_from - U64::from(1)));
+        }
+        next_block = *synced_to + U64::from(1);
+    }
+
+    if next_block <= to_block {
+        ranges.push((next_block, to_block));
+    }
+
+    ranges
+}
+
+#[derive(Debug)]
+struct BackfillState {
+    /// The first block which is not indexed yet, everything below it is covered by the last
+    /// synced block.
+    next_block: U64,
+    /// Indexed ranges above `next_block`, sorted and merged.
+    synced: Vec<(U64, U64)>,
+}
+
+impl BackfillState {
+    fn complete(&mut self, from_block: U64, to_block: U64) {
+        let position = self.synced.partition_point(|(synced_from, _)| *synced_from < from_block);
+        self.synced.insert(position, (from_block, to_block));
+
+        let mut merged: Vec<(U64, U64)> = Vec::with_capacity(self.synced.len());
+        for (synced_from, synced_to) in self.synced.drain(..) {
+            match merged.last_mut() {
+                Some((_, last_to)) if synced_from <= *last_to + U64::from(1) => {
+                    *last_to = (*last_to).max(synced_to);
+                }
+                _ => merged.push((synced_from, synced_to)),
+            }
+        }
+
+        // the ranges touching the last synced block now count as synced
+        while let Some((synced_from, synced_to)) = merged.first().copied() {
+            if synced_from > self.next_block {
+                break;
+            }
+            self.next_block = self.next_block.max(synced_to + U64::from(1));
+            merged.remove(0);
+        }
+
+        self.synced = merged;
+    }
+
+    fn unsynced(&self, ranges: &[(U64, U64)]) -> Vec<(U64, U64)> {
+        ranges
+            .iter()
+            .filter(|(_, to_block)| *to_block >= self.next_block)
+            .flat_map(|(from_block, to_block)| {
+                unsynced_ranges((*from_block).max(self.next_block), *to_block, &self.synced)
+            })
+            .collect()
+    }
+
+    /// Forgets everything indexed above `fork_block`.
+    fn rewind(&mut self, fork_block: U64) {
+        self.next_block = self.next_block.min(fork_block + U64::from(1));
+        self.synced.retain(|(synced_from, _)| *synced_from <= fork_block);
+        if let Some((_, synced_to)) = self.synced.last_mut() {
+            *synced_to = (*synced_to).min(fork_block);
+        }
+    }
+}
+
+/// Tracks which ranges of a segmented backfill were indexed. Segments finish out of order, so
+/// the last synced block only moves over the ranges directly following it and the ranges further
+/// ahead are persisted next to it, that way a restart neither skips nor refetches them.
+#[derive(Debug)]
+pub struct BackfillProgress {
+    state: Mutex<BackfillState>,
+}
+
+impl BackfillProgress {
+    pub fn new(from_block: U64, synced: Vec<(U64, U64)>) -> Self {
+        let mut state = BackfillState { next_block: from_block, synced: vec![] };
+        for (synced_from, synced_to) in synced {
+            state.complete(synced_from, synced_to);
+        }
+
+        Self { state: Mutex::new(state) }
+    }
+
+    /// Records `[from_block, to_block]` as indexed and persists the checkpoint.
+    pub async fn checkpoint(
+        &self,
+        config: Arc<EventProcessingConfig>,
+        from_block: U64,
+        to_block: U64,
+        on_complete: impl FnOnce() + Send + 'static,
+    ) {
+        // held while persisting so the stored ranges are always written in order
+        let mut state = self.state.lock().await;
+        let next_block = state.next_block;
+        let synced = state.synced.clone();
+
+        state.complete(from_block, to_block);
+
+        if state.next_block > next_block {
+            update_progress_and_last_synced_task(
+                Arc::clone(&config),
+                state.next_block - U64::from(1),
+                || {},
+            )
+            .await;
+        }
+
+        // written after the last synced block, a crash in between only leaves stale ranges
+        // below it which are ignored on restart
+        if state.synced != synced {
+            update_synced_segments(&config, &state.synced).await;
+        }
+
+        on_complete();
+    }
+
+    /// The parts of `ranges` which are not indexed yet.
+    pub async fn unsynced(&self, ranges: &[(U64, U64)]) -> Vec<(U64, U64)> {
+        let state = self.state.lock().await;
+        state.unsynced(ranges)
+    }
+
+    /// Moves the progress back to `fork_block` after a reorg rolled back the blocks above it, the
+    /// last synced block itself is rewound with the rollback.
+    pub async fn rewind(&self, config: &EventProcessingConfig, fork_block: U64) {
+        let mut state = self.state.lock().await;
+        let synced = state.synced.clone();
+
+        state.rewind(fork_block);
+
+        if state.synced != synced {
+            update_synced_segments(config, &state.synced).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(ranges: &[(u64, u64)]) -> Vec<(U64, U64)> {
+        ranges.iter().map(|(from, to)| (U64::from(*from), U64::from(*to))).collect()
+    }
+
+    #[test]
+    fn test_backfill_segments() {
+        assert_eq!(
+            backfill_segments(U64::from(100), U64::from(399), 3, &[]),
+            vec![ranges(&[(100, 199)]), ranges(&[(200, 299)]), ranges(&[(300, 399)])]
+        );
+        assert_eq!(
+            backfill_segments(U64::from(1), U64::from(2), 4, &[]),
+            vec![ranges(&[(1, 1)]), ranges(&[(2, 2)])]
+        );
+        assert_eq!(
+            backfill_segments(
+                U64::from(100),
+                U64::from(399),
+                2,
+                &ranges(&[(150, 160), (250, 399)])
+            ),
+            vec![ranges(&[(100, 149), (161, 249)])]
+        );
+    }
+
+    #[test]
+    fn test_backfill_progress_moves_over_contiguous_ranges() {
+        let mut state = BackfillState { next_block: U64::from(100), synced: vec![] };
+
+        state.complete(U64::from(300), U64::from(399));
+        state.complete(U64::from(200), U64::from(249));
+        assert_eq!(state.next_block, U64::from(100));
+        assert_eq!(state.synced, ranges(&[(200, 249), (300, 399)]));
+
+        state.complete(U64::from(100), U64::from(199));
+        assert_eq!(state.next_block, U64::from(250));
+        assert_eq!(state.synced, ranges(&[(300, 399)]));
+
+        state.complete(U64::from(250), U64::from(299));
+        assert_eq!(state.next_block, U64::from(400));
+        assert!(state.synced.is_empty());
+    }
+
+    #[test]
+    fn test_backfill_progress_unsynced_ranges() {
+        let state =
+            BackfillState { next_block: U64::from(150), synced: ranges(&[(250, 299), (350, 399)]) };
+
+        assert_eq!(
+            state.unsynced(&ranges(&[(100, 199), (200, 399)])),
+            ranges(&[(150, 199), (200, 249), (300, 349)])
+        );
+        assert!(state.unsynced(&ranges(&[(100, 149), (250, 299)])).is_empty());
+    }
+
+    #[test]
+    fn test_backfill_progress_rewinds_to_fork_block() {
+        let mut state =
+            BackfillState { next_block: U64::from(200), synced: ranges(&[(250, 299), (350, 399)]) };
+
+        state.rewind(U64::from(274));
+        assert_eq!(state.next_block, U64::from(200));
+        assert_eq!(state.synced, ranges(&[(250, 274)]));
+
+        state.rewind(U64::from(149));
+        assert_eq!(state.next_block, U64::from(150));
+        assert!(state.synced.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use std::{cmp, collections::HashSet, sync::Arc};
+
+use alloy::{
+    primitives::{Address, U64},
+    rpc::types::trace::parity::{Action, LocalizedTransactionTrace, TraceOutput},
+};
+use futures::future::try_join_all;
+
+use crate::{
+    event::{callback_registry::TraceResult, config::TraceProcessingConfig, function_call},
+    indexer::{
+        last_synced::evm_trace_update_progress_and_last_synced_task,
+        process::ProcessEventError,
+        task_tracker::{indexing_event_processed, indexing_event_processing},
+    },
+    manifest::native_transfer::TraceProcessingMethod,
+    provider::{JsonRpcCachedProvider, ProviderError},
+};
+
+/// Contract deployments are indexed as an event of the same imaginary `EvmTraces` contract as
+/// native transfers.
+pub const CONTRACT_CREATED_EVENT_NAME: &str = "ContractCreated";
+
+/// Invent an ABI for the deployments so they are stored and streamed like any other event.
+///
+/// The deployed contract is the `address` input, its code hash is the keccak256 of the deployed
+/// code and not of the init code.
+pub const CONTRACT_CREATED_ABI: &str = r#"[{
+    "anonymous": false,
+    "inputs": [
+        {
+            "indexed": true,
+            "name": "deployer",
+            "type": "address"
+        },
+        {
+            "indexed": true,
+            "name": "address",
+            "type": "address"
+        },
+        {
+            "indexed": false,
+            "name": "codeHash",
+            "type": "bytes32"
+        }
+    ],
+    "name": "ContractCreated",
+    "type": "event"
+}]"#;
+
+/// The successful `CREATE` and `CREATE2` of the traces of one block, a deployment is dropped when
+/// it or any call it was made from reverted.
+pub fn contract_created_traces(
+    traces: &[LocalizedTransactionTrace],
+    deployers: Option<&HashSet<Address>>,
+    network_name: &str,
+    from_block: U64,
+    to_block: U64,
+) -> Vec<TraceResult> {
+    let mut deployments = Vec::new();
+
+    for (trace, reverted) in traces.iter().zip(function_call::reverted_traces(traces)) {
+        let (Action::Create(action), Some(TraceOutput::Create(output))) =
+            (&trace.trace.action, &trace.trace.result)
+        else {
+            continue;
+        };
+
+        if reverted || deployers.is_some_and(|deployers| !deployers.contains(&action.from)) {
+            continue;
+        }
+
+        deployments.push(TraceResult::new_contract_created(
+            action,
+            output,
+            trace,
+            deployments.len() as u64,
+            network_name,
+            from_block,
+            to_block,
+        ));
+    }
+
+    deployments
+}
+
+async fn provider_deployment_traces(
+    provider: Arc<JsonRpcCachedProvider>,
+    method: TraceProcessingMethod,
+    block: U64,
+) -> Result<Vec<LocalizedTransactionTrace>, ProviderError> {
+    match method {
+        TraceProcessingMethod::TraceBlock => provider.trace_block(block).await,
+        // the top call only trace used for native transfers would miss deployments by contracts
+        TraceProcessingMethod::DebugTraceBlockByNumber => {
+            let mut traces = provider.debug_trace_block_calls(block).await?;
+            if traces.iter().any(|trace| matches!(trace.trace.action, Action::Create(_))) {
+                provider.fill_traces_block_details(block, &mut traces).await?;
+            }
+            Ok(traces)
+        }
+        TraceProcessingMethod::EthGetBlockByNumber => Err(ProviderError::CustomError(
+            "Contract deployments can only be indexed with trace_block or debug_traceBlockByNumber"
+                .to_string(),
+        )),
+    }
+}
+
+/// Index the contract deployments of the blocks from their traces.
+pub async fn contract_deployment_block_consumer(
+    provider: Arc<JsonRpcCachedProvider>,
+    block_numbers: &[U64],
+    network_name: &str,
+    config: &Arc<TraceProcessingConfig>,
+) -> Result<(), ProcessEventError> {
+    let trace_futures = block_numbers
+        .iter()
+        .map(|n| provider_deployment_traces(provider.clone(), config.method, *n));
+    let block_traces = try_join_all(trace_futures).await?;
+    let (from_block, to_block) = block_numbers
+        .iter()
+        .fold((U64::MAX, U64::ZERO), |(min, max), &num| (cmp::min(min, num), cmp::max(max, num)));
+
+    let deployments = block_traces
+        .iter()
+        .flat_map(|traces| {
+            contract_created_traces(
+                traces,
+                config.deployers.as_ref(),
+                network_name,
+                from_block,
+                to_block,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Important that we call this for every batch even if there are no deployments, the last
+    // seen block number still has to be synced.
+    indexing_event_processing();
+    if !deployments.is_empty() {
+        config.trigger_event(deployments).await;
+    }
+    evm_trace_update_progress_and_last_synced_task(
+        config.clone(),
+        to_block,
+        indexing_event_processed,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, keccak256, Bytes, B256, U256},
+        rpc::types::trace::parity::{
+            CallAction, CallType, CreateAction, CreateOutput, CreationMethod, TransactionTrace,
+        },
+    };
+
+    use super::*;
+
+    fn trace(
+        action: Action,
+        result: Option<TraceOutput>,
+        trace_address: Vec<usize>,
+        error: Option<&str>,
+    ) -> LocalizedTransactionTrace {
+        LocalizedTransactionTrace {
+            trace: TransactionTrace {
+                action,
+                error: error.map(str::to_string),
+                result,
+                subtraces: 0,
+                trace_address,
+            },
+            block_hash: Some(B256::repeat_byte(1)),
+            block_number: Some(100),
+            transaction_hash: Some(B256::repeat_byte(2)),
+            transaction_position: Some(0),
+        }
+    }
+
+    fn create(from: Address, address: Address, code: &'static [u8]) -> (Action, TraceOutput) {
+        (
+            Action::Create(CreateAction {
+                from,
+                gas: 0,
+                init: Bytes::new(),
+                value: U256::ZERO,
+                creation_method: CreationMethod::Create2,
+            }),
+            TraceOutput::Create(CreateOutput {
+                address,
+                code: Bytes::from_static(code),
+                gas_used: 0,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_contract_created_traces() {
+        let factory = address!("0x00000000000000000000000000000000000000fa");
+        let deployed = address!("0x00000000000000000000000000000000000000d1");
+        let other = address!("0x00000000000000000000000000000000000000d2");
+
+        let (first, first_output) = create(factory, deployed, &[1, 2, 3]);
+        let (reverted, reverted_output) = create(factory, other, &[4]);
+        let (by_other, by_other_output) = create(other, other, &[5]);
+        let call = Action::Call(CallAction {
+            from: factory,
+            to: deployed,
+            value: U256::ZERO,
+            gas: 0,
+            input: Bytes::new(),
+            call_type: CallType::Call,
+        });
+
+        let traces = vec![
+            trace(call.clone(), None, vec![], None),
+            trace(first, Some(first_output), vec![0], None),
+            trace(call, None, vec![1], Some("Reverted")),
+            // reverted with its parent
+            trace(reverted, Some(reverted_output), vec![1, 0], None),
+            trace(by_other, Some(by_other_output), vec![2], None),
+        ];
+
+        let deployments =
+            contract_created_traces(&traces, None, "ethereum", U64::from(100), U64::from(100));
+        assert_eq!(deployments.len(), 2);
+        assert_eq!(deployments[0].from, factory);
+        assert_eq!(deployments[0].to, deployed);
+        assert_eq!(deployments[0].code_hash, Some(keccak256([1, 2, 3])));
+        assert_eq!(deployments[1].tx_information.log_index, U256::from(1));
+
+        let deployers = HashSet::from([factory]);
+        let deployments = contract_created_traces(
+            &traces,
+            Some(&deployers),
+            "ethereum",
+            U64::from(100),
+            U64::from(100),
+        );
+        assert_eq!(deployments.len(), 1);
+        assert_eq!(deployments[0].to, deployed);
+        assert_eq!(deployments[0].tx_information.log_index, U256::ZERO);
+    }
+}
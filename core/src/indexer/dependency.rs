@@ -6,7 +6,10 @@ use std::{
 use crate::{
     database::postgres::relationship::Relationship,
     event::{config::EventProcessingConfig, contract_setup::ContractEventMapping},
-    manifest::{contract::DependencyEventTree, core::Manifest},
+    manifest::{
+        contract::{DependencyEventTree, FactoryDetailsYaml},
+        core::Manifest,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -243,11 +246,14 @@ impl ContractEventDependencies {
             match factory_dependency {
                 // When factory filter is set we rewrite dependency settings to put factory event first
                 Some(factory) => {
-                    if !factories.iter().all(|f| {
-                        f.name == factory.name
-                            && f.event_name == factory.event_name
-                            && f.input_name == factory.input_name
-                    }) {
+                    let factory_events = |factory: &FactoryDetailsYaml| {
+                        factory
+                            .levels()
+                            .into_iter()
+                            .map(|f| (f.name.clone(), f.event_name.clone(), f.input_name.clone()))
+                            .collect::<Vec<_>>()
+                    };
+                    if !factories.iter().all(|f| factory_events(f) == factory_events(&factory)) {
                         panic!("Contract using factory filter must use same factory across all networks. Please raise issue in github if you need different factories across networks");
                     }
 
@@ -271,13 +277,17 @@ impl ContractEventDependencies {
                         dependency_event_tree
                     });
 
-                    let factory_dependency_event_tree = DependencyEventTree {
-                        contract_events: vec![ContractEventMapping {
-                            contract_name: factory.name,
-                            event_name: factory.event_name,
-                        }],
-                        then: Some(Box::from(dependency_event_tree)),
-                    };
+                    // a nested factory has to find the factories before their deployments
+                    let factory_dependency_event_tree = factory.levels().into_iter().rev().fold(
+                        dependency_event_tree,
+                        |then, factory| DependencyEventTree {
+                            contract_events: vec![ContractEventMapping {
+                                contract_name: factory.name.clone(),
+                                event_name: factory.event_name.clone(),
+                            }],
+                            then: Some(Box::from(then)),
+                        },
+                    );
 
                     let factory_dependency_tree = EventsDependencyTree::from_dependency_event_tree(
                         &factory_dependency_event_tree,
@@ -2,16 +2,28 @@ use std::{error::Error, str::FromStr, sync::Arc, time::Duration};
 
 use crate::helpers::{halved_block_number, is_relevant_block};
 use crate::{
+    database::postgres::client::PostgresClient,
     event::{config::EventProcessingConfig, RindexerEventFilter},
-    indexer::{reorg::handle_chain_notification, IndexingEventProgressStatus},
+    indexer::{
+        backfill::{backfill_segments, BackfillProgress},
+        last_synced::get_synced_segments,
+        reorg::{
+            detect_reorg, handle_chain_notification, live_safe_block_number,
+            seed_block_hash_tracker, BlockHashTracker, RecentLogs, ReorgRollback,
+            REORG_TRACKING_DEPTH,
+        },
+        IndexingEventProgressStatus,
+    },
     is_running,
-    provider::{JsonRpcCachedProvider, ProviderError},
+    log_source::{CallTraceLogSource, LogSource, RpcLogSource},
+    provider::{JsonRpcCachedProvider, ProviderError, RpcEndpointClient},
+    rpc_endpoints::BlockRangeLimit,
 };
 use alloy::{
     primitives::{B256, U64},
     rpc::types::Log,
 };
-use rand::{random_bool, random_ratio};
+use rand::random_ratio;
 use regex::Regex;
 use tokio::{sync::mpsc, time::Instant};
 use tokio_stream::wrappers::ReceiverStream;
@@ -21,12 +33,95 @@ pub struct FetchLogsResult {
     pub logs: Vec<Log>,
     pub from_block: U64,
     pub to_block: U64,
+    /// Set when a reorg was detected, everything above the fork block has to be rolled back
+    /// before any further logs are handled.
+    pub reorg: Option<ReorgRollback>,
+    /// Set for the results of a segmented backfill, the checkpoint has to go through it as the
+    /// segments finish out of order.
+    pub backfill: Option<Arc<BackfillProgress>>,
 }
 
 pub fn fetch_logs_stream(
     config: Arc<EventProcessingConfig>,
     force_no_live_indexing: bool,
 ) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
+{
+    let filter = config.to_event_filter().unwrap();
+    let info_log_name = config.info_log_name();
+    let backfill_segments = config.config().backfill_segments;
+
+    filter_logs_stream(config, filter, info_log_name, backfill_segments, force_no_live_indexing)
+}
+
+/// Fetches the logs of every event of one contract on one network with a single filter matching
+/// all of their signatures, from the lowest start block of the events. The results hold the logs
+/// of all the events, the caller hands each event its part with [`event_logs_result`].
+pub fn fetch_contract_logs_stream(
+    configs: &[Arc<EventProcessingConfig>],
+    filters: &[RindexerEventFilter],
+    force_no_live_indexing: bool,
+) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
+{
+    let config = Arc::clone(&configs[0]);
+    let from_block = filters.iter().map(|filter| filter.from_block()).min().unwrap_or_default();
+    let to_block = filters.iter().map(|filter| filter.to_block()).max().unwrap_or_default();
+    let filter = filters[0]
+        .clone()
+        .with_event_signatures(filters.iter().map(|filter| filter.event_signature()).collect())
+        .set_from_block(from_block)
+        .set_to_block(to_block);
+    let info_log_name = format!(
+        "{}::{{{}}}",
+        config.contract_name(),
+        configs.iter().map(|config| config.event_name()).collect::<Vec<_>>().join(",")
+    );
+
+    // every event keeps its own checkpoint, so the shared stream is not segmented
+    filter_logs_stream(config, filter, info_log_name, None, force_no_live_indexing)
+}
+
+/// The part of a [`fetch_contract_logs_stream`] result which belongs to the event of `filter`,
+/// `None` while the range is still below the start block of the event or already past the
+/// `end_block` of an event which is not live indexed.
+pub fn event_logs_result(
+    result: &FetchLogsResult,
+    filter: &RindexerEventFilter,
+    end_block: Option<U64>,
+) -> Option<FetchLogsResult> {
+    let start_block = filter.from_block();
+    let end_block = end_block.unwrap_or(U64::MAX);
+    if result.to_block < start_block || result.from_block > end_block {
+        return None;
+    }
+
+    let event_signature = filter.event_signature();
+    let belongs_to_event = |log: &&Log| {
+        log.topic0() == Some(&event_signature)
+            && filter.matches_indexed_topics(log)
+            && log.block_number.is_some_and(|block_number| {
+                (start_block..=end_block).contains(&U64::from(block_number))
+            })
+    };
+
+    Some(FetchLogsResult {
+        logs: result.logs.iter().filter(belongs_to_event).cloned().collect(),
+        from_block: result.from_block.max(start_block),
+        to_block: result.to_block.min(end_block),
+        reorg: result.reorg.as_ref().map(|reorg| ReorgRollback {
+            fork_block: reorg.fork_block,
+            removed_logs: reorg.removed_logs.iter().filter(belongs_to_event).cloned().collect(),
+        }),
+        backfill: None,
+    })
+}
+
+fn filter_logs_stream(
+    config: Arc<EventProcessingConfig>,
+    filter: RindexerEventFilter,
+    info_log_name: String,
+    backfill_segments: Option<usize>,
+    force_no_live_indexing: bool,
+) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
 {
     // If the sink is slower than the producer it can lead to unbounded memory growth and
     // a system OOM kill.
@@ -39,7 +134,7 @@ pub fn fetch_logs_stream(
 
     debug!(
         "{}::{} Configured with {} event buffer",
-        config.info_log_name(),
+        info_log_name,
         config.network_contract().network,
         channel_size
     );
@@ -47,45 +142,88 @@ pub fn fetch_logs_stream(
     let (tx, rx) = mpsc::channel(channel_size);
 
     tokio::spawn(async move {
-        let mut current_filter = config.to_event_filter().unwrap();
+        let mut current_filter = filter;
+        let topic_ids = current_filter.event_signatures();
 
         let snapshot_to_block = current_filter.to_block();
-        let from_block = current_filter.from_block();
 
-        // add any max block range limitation before we start processing
         let original_max_limit = config.network_contract().cached_provider.max_block_range;
-        let mut max_block_range_limitation =
-            config.network_contract().cached_provider.max_block_range;
-        if max_block_range_limitation.is_some() {
-            current_filter = current_filter.set_to_block(calculate_process_historic_log_to_block(
-                &from_block,
-                &snapshot_to_block,
-                &max_block_range_limitation,
-            ));
+        if let Some(original_max_limit) = original_max_limit {
             if random_ratio(1, 20) {
                 warn!(
                     "{}::{} - {} - max block range of {} applied - indexing will be slower than providers supplying the optimal ranges - https://rindexer.xyz/docs/references/rpc-node-providers#rpc-node-providers",
-                    config.info_log_name(),
+                    info_log_name,
                     config.network_contract().network,
                     IndexingEventProgressStatus::Syncing.log(),
-                    max_block_range_limitation.unwrap()
+                    original_max_limit
                 );
             }
         }
-        while current_filter.from_block() <= snapshot_to_block {
+
+        let backfill_segments = backfill_segments
+            .filter(|segments| *segments > 1 && current_filter.from_block() <= snapshot_to_block);
+        let mut backfill = None;
+        if let Some(segments) = backfill_segments {
+            backfill = Some(
+                fetch_historic_segments(
+                    &config,
+                    &tx,
+                    &current_filter,
+                    segments,
+                    snapshot_to_block,
+                    &info_log_name,
+                )
+                .await,
+            );
+
+            current_filter = current_filter
+                .set_from_block(snapshot_to_block + U64::from(1))
+                .set_to_block(snapshot_to_block + U64::from(1));
+        }
+
+        while backfill.is_none() && current_filter.from_block() <= snapshot_to_block {
             if !is_running() {
                 break;
             }
 
-            let result = fetch_historic_logs_stream(
+            let endpoint = config.network_contract().cached_provider.logs_endpoint();
+            let log_source = historic_log_source(
                 &config.network_contract().cached_provider,
+                &endpoint,
+                &current_filter,
+            );
+            let limited_to_block =
+                log_source.limit_to_block(current_filter.from_block(), current_filter.to_block());
+            current_filter = current_filter.set_to_block(limited_to_block);
+
+            // every endpoint learns its own max block range, the endpoint picked for this request
+            // can allow less than the one which returned the current filter
+            let max_block_range_limitation = if log_source.follows_endpoint_block_range() {
+                endpoint.state.max_block_range()
+            } else {
+                None
+            };
+            if max_block_range_limitation.is_some() {
+                let limited_to_block = calculate_process_historic_log_to_block(
+                    &current_filter.from_block(),
+                    &snapshot_to_block,
+                    &max_block_range_limitation,
+                );
+                if limited_to_block < current_filter.to_block() {
+                    current_filter = current_filter.set_to_block(limited_to_block);
+                }
+            }
+
+            let result = fetch_historic_logs_stream(
+                log_source.as_ref(),
                 &tx,
-                &config.topic_id(),
+                &topic_ids,
                 current_filter.clone(),
                 max_block_range_limitation,
                 snapshot_to_block,
-                &config.info_log_name(),
+                &info_log_name,
                 &config.network_contract().network,
+                None,
             )
             .await;
 
@@ -95,7 +233,7 @@ pub fn fetch_logs_stream(
                 if range.to::<u64>() < 5000 && random_ratio(1, 20) {
                     warn!(
                         "{}::{} - RPC PROVIDER IS SLOW - Slow indexing mode enabled, max block range limitation: {} blocks - we advise using a faster provider who can predict the next block ranges.",
-                        &config.info_log_name(),
+                        &info_log_name,
                         &config.network_contract().network,
                         range
                     );
@@ -103,16 +241,11 @@ pub fn fetch_logs_stream(
             }
 
             if let Some(result) = result {
-                // Useful for occasionally breaking out of temporary limitations or parsing errors
-                // that lock down to a `1` block limitation. Returns back to the original
-                let new_max_block_range_limitation = if random_bool(0.10) {
-                    original_max_limit
-                } else {
-                    result.max_block_range_limitation
-                };
+                if log_source.follows_endpoint_block_range() {
+                    learn_block_range(&endpoint, &result);
+                }
 
                 current_filter = result.next;
-                max_block_range_limitation = new_max_block_range_limitation;
             } else {
                 break;
             }
@@ -120,7 +253,7 @@ pub fn fetch_logs_stream(
 
         info!(
             "{}::{} - {} - Finished indexing historic events",
-            &config.info_log_name(),
+            &info_log_name,
             &config.network_contract().network,
             IndexingEventProgressStatus::Completed.log()
         );
@@ -131,13 +264,16 @@ pub fn fetch_logs_stream(
                 &config.network_contract().cached_provider,
                 &tx,
                 snapshot_to_block,
-                &config.topic_id(),
+                &topic_ids,
                 &config.indexing_distance_from_head(),
+                config.reorg_safe_distance(),
                 current_filter,
-                &config.info_log_name(),
+                &info_log_name,
                 config.network_contract().disable_logs_bloom_checks,
                 &config.network_contract().network,
                 original_max_limit,
+                backfill,
+                config.database(),
             )
             .await;
         }
@@ -146,21 +282,258 @@ pub fn fetch_logs_stream(
     ReceiverStream::new(rx)
 }
 
+/// Fetches `[filter.from_block(), snapshot_to_block]` as `segments` ranges at the same time. With
+/// `index_event_in_order` every segment is buffered in its own channel and the channels are
+/// drained one after the other, so the results still arrive in block order.
+async fn fetch_historic_segments(
+    config: &Arc<EventProcessingConfig>,
+    tx: &mpsc::Sender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
+    filter: &RindexerEventFilter,
+    segments: usize,
+    snapshot_to_block: U64,
+    info_log_name: &str,
+) -> Arc<BackfillProgress> {
+    let synced = get_synced_segments(config).await;
+    let backfill = Arc::new(BackfillProgress::new(filter.from_block(), synced.clone()));
+    let segment_ranges =
+        backfill_segments(filter.from_block(), snapshot_to_block, segments, &synced);
+
+    info!(
+        "{}::{} - {} - Backfilling {} - {} in {} segments",
+        info_log_name,
+        config.network_contract().network,
+        IndexingEventProgressStatus::Syncing.log(),
+        filter.from_block(),
+        snapshot_to_block,
+        segment_ranges.len()
+    );
+
+    let index_event_in_order = config.index_event_in_order();
+    let mut segment_receivers = Vec::new();
+    let mut tasks = Vec::new();
+
+    for ranges in segment_ranges {
+        let ranges_to_retry = ranges.clone();
+        let segment_tx = if index_event_in_order {
+            let (segment_tx, segment_rx) = mpsc::channel(config.config().buffer.unwrap_or(4));
+            segment_receivers.push(segment_rx);
+            segment_tx
+        } else {
+            tx.clone()
+        };
+
+        tasks.push((
+            ranges_to_retry,
+            tokio::spawn(fetch_historic_segment(
+                Arc::clone(config),
+                segment_tx,
+                filter.clone(),
+                ranges,
+                info_log_name.to_string(),
+                Arc::clone(&backfill),
+            )),
+        ));
+    }
+
+    // dropping the remaining receivers when the consumer is gone stops their segments
+    'segments: for mut segment_rx in segment_receivers {
+        while let Some(result) = segment_rx.recv().await {
+            if tx.send(result).await.is_err() {
+                break 'segments;
+            }
+        }
+    }
+
+    for (ranges, task) in tasks {
+        if let Err(e) = task.await {
+            error!(
+                "{}::{} - {} - Backfill segment failed: {}",
+                info_log_name,
+                config.network_contract().network,
+                IndexingEventProgressStatus::Syncing.log(),
+                e
+            );
+            retry_historic_segment(config, tx, filter, ranges, info_log_name, &backfill).await;
+        }
+    }
+
+    backfill
+}
+
+/// A failed segment leaves a gap the last synced block can not move past, live indexing included,
+/// so the ranges it did not index are fetched again before going live. They arrive after the
+/// other segments even with `index_event_in_order`, and batches still being processed can be
+/// fetched twice which postgres skips as the rows already exist.
+async fn retry_historic_segment(
+    config: &Arc<EventProcessingConfig>,
+    tx: &mpsc::Sender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
+    filter: &RindexerEventFilter,
+    ranges: Vec<(U64, U64)>,
+    info_log_name: &str,
+    backfill: &Arc<BackfillProgress>,
+) {
+    const BACKFILL_SEGMENT_MAX_ATTEMPTS: u32 = 3;
+
+    for attempt in 1..=BACKFILL_SEGMENT_MAX_ATTEMPTS {
+        let ranges = backfill.unsynced(&ranges).await;
+        if ranges.is_empty() || !is_running() || tx.is_closed() {
+            return;
+        }
+
+        warn!(
+            "{}::{} - {} - Retrying backfill segment {:?}, attempt {}/{}",
+            info_log_name,
+            config.network_contract().network,
+            IndexingEventProgressStatus::Syncing.log(),
+            ranges,
+            attempt,
+            BACKFILL_SEGMENT_MAX_ATTEMPTS
+        );
+
+        let task = tokio::spawn(fetch_historic_segment(
+            Arc::clone(config),
+            tx.clone(),
+            filter.clone(),
+            ranges,
+            info_log_name.to_string(),
+            Arc::clone(backfill),
+        ));
+        match task.await {
+            Ok(()) => return,
+            Err(e) => error!(
+                "{}::{} - {} - Backfill segment failed again: {}",
+                info_log_name,
+                config.network_contract().network,
+                IndexingEventProgressStatus::Syncing.log(),
+                e
+            ),
+        }
+    }
+
+    error!(
+        "{}::{} - {} - Backfill segment {:?} could not be indexed, the last synced block stays below it until the next start",
+        info_log_name,
+        config.network_contract().network,
+        IndexingEventProgressStatus::Syncing.log(),
+        ranges
+    );
+}
+
+/// Walks the block ranges of one backfill segment. Segments share the block range each endpoint
+/// learned with the unsegmented sync, so what one of them learns is used and persisted for all.
+async fn fetch_historic_segment(
+    config: Arc<EventProcessingConfig>,
+    tx: mpsc::Sender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
+    filter: RindexerEventFilter,
+    ranges: Vec<(U64, U64)>,
+    info_log_name: String,
+    backfill: Arc<BackfillProgress>,
+) {
+    let cached_provider = &config.network_contract().cached_provider;
+    let network = &config.network_contract().network;
+    let topic_ids = filter.event_signatures();
+
+    for (from_block, to_block) in ranges {
+        let mut current_filter = filter.clone().set_from_block(from_block).set_to_block(to_block);
+
+        while current_filter.from_block() <= to_block {
+            if !is_running() || tx.is_closed() {
+                return;
+            }
+
+            let endpoint = cached_provider.logs_endpoint();
+            let log_source = historic_log_source(cached_provider, &endpoint, &current_filter);
+            let limited_to_block =
+                log_source.limit_to_block(current_filter.from_block(), current_filter.to_block());
+            current_filter = current_filter.set_to_block(limited_to_block);
+
+            let max_block_range_limitation = if log_source.follows_endpoint_block_range() {
+                endpoint.state.max_block_range()
+            } else {
+                None
+            };
+            if max_block_range_limitation.is_some() {
+                let limited_to_block = calculate_process_historic_log_to_block(
+                    &current_filter.from_block(),
+                    &to_block,
+                    &max_block_range_limitation,
+                );
+                if limited_to_block < current_filter.to_block() {
+                    current_filter = current_filter.set_to_block(limited_to_block);
+                }
+            }
+
+            let result = fetch_historic_logs_stream(
+                log_source.as_ref(),
+                &tx,
+                &topic_ids,
+                current_filter.clone(),
+                max_block_range_limitation,
+                to_block,
+                &info_log_name,
+                network,
+                Some(&backfill),
+            )
+            .await;
+
+            let Some(result) = result else {
+                break;
+            };
+
+            if log_source.follows_endpoint_block_range() {
+                learn_block_range(&endpoint, &result);
+            }
+            current_filter = result.next;
+        }
+    }
+}
+
+/// The call traces for function call events, the logs archive of the network when it holds the
+/// logs of the filter's from block, otherwise the rpc endpoint picked for the request.
+fn historic_log_source(
+    cached_provider: &Arc<JsonRpcCachedProvider>,
+    endpoint: &RpcEndpointClient,
+    filter: &RindexerEventFilter,
+) -> Arc<dyn LogSource> {
+    if filter.function_call().is_some() {
+        return Arc::new(CallTraceLogSource::new(Arc::clone(cached_provider)));
+    }
+
+    match cached_provider.logs_archive() {
+        Some(archive) if archive.contains(filter.from_block()) => archive,
+        _ => Arc::new(RpcLogSource::new(Arc::clone(cached_provider), endpoint.clone())),
+    }
+}
+
+/// Narrows the block range of the endpoint to what its error taught, or lets a guessed range
+/// grow back once requests succeed again.
+fn learn_block_range(endpoint: &RpcEndpointClient, result: &ProcessHistoricLogsStreamResult) {
+    match result.limit {
+        Some(limit) => endpoint.state.limit_block_range(limit),
+        None if result.fetched => endpoint.state.record_block_range_success(),
+        None => {}
+    }
+}
+
 struct ProcessHistoricLogsStreamResult {
     pub next: RindexerEventFilter,
-    pub max_block_range_limitation: Option<U64>,
+    /// Set when the request fetched the logs of its whole range.
+    pub fetched: bool,
+    /// The block range the endpoint is limited to from here on, learned from its error.
+    pub limit: Option<BlockRangeLimit>,
 }
 
 #[allow(clippy::too_many_arguments)]
 async fn fetch_historic_logs_stream(
-    cached_provider: &Arc<JsonRpcCachedProvider>,
+    log_source: &dyn LogSource,
     tx: &mpsc::Sender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
-    topic_id: &B256,
+    topic_ids: &[B256],
     current_filter: RindexerEventFilter,
     max_block_range_limitation: Option<U64>,
     snapshot_to_block: U64,
     info_log_name: &str,
     network: &str,
+    backfill: Option<&Arc<BackfillProgress>>,
 ) -> Option<ProcessHistoricLogsStreamResult> {
     let from_block = current_filter.from_block();
     let to_block = current_filter.to_block();
@@ -185,7 +558,8 @@ async fn fetch_historic_logs_stream(
 
         return Some(ProcessHistoricLogsStreamResult {
             next: current_filter.set_from_block(to_block).set_to_block(to_block + U64::from(1)),
-            max_block_range_limitation,
+            fetched: false,
+            limit: None,
         });
     }
 
@@ -207,13 +581,13 @@ async fn fetch_historic_logs_stream(
         );
     }
 
-    match cached_provider.get_logs(&current_filter).await {
+    match log_source.get_logs(&current_filter).await {
         Ok(logs) => {
             debug!(
-                "{} - {} - topic_id {}, Logs: {} from {} to {}",
+                "{} - {} - topic_ids {:?}, Logs: {} from {} to {}",
                 info_log_name,
                 IndexingEventProgressStatus::Syncing.log(),
-                topic_id,
+                topic_ids,
                 logs.len(),
                 from_block,
                 to_block
@@ -235,7 +609,13 @@ async fn fetch_historic_logs_stream(
                 );
             }
 
-            sender.send(Ok(FetchLogsResult { logs, from_block, to_block }));
+            sender.send(Ok(FetchLogsResult {
+                logs,
+                from_block,
+                to_block,
+                reorg: None,
+                backfill: backfill.cloned(),
+            }));
 
             if logs_empty {
                 let next_from_block = to_block + U64::from(1);
@@ -269,7 +649,8 @@ async fn fetch_historic_logs_stream(
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
-                        max_block_range_limitation,
+                        fetched: true,
+                        limit: None,
                     })
                 };
             }
@@ -306,7 +687,8 @@ async fn fetch_historic_logs_stream(
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
-                        max_block_range_limitation,
+                        fetched: true,
+                        limit: None,
                     })
                 };
             }
@@ -337,8 +719,8 @@ async fn fetch_historic_logs_stream(
                         retry_result.from,
                         retry_result.to,
                         retry_result
-                            .max_block_range
-                            .map(|m| format!(" (max {m})"))
+                            .limit
+                            .map(|limit| format!(" (max {})", limit.range()))
                             .unwrap_or("".to_owned()),
                     );
                 }
@@ -347,7 +729,8 @@ async fn fetch_historic_logs_stream(
                     next: current_filter
                         .set_from_block(U64::from(retry_result.from))
                         .set_to_block(U64::from(retry_result.to)),
-                    max_block_range_limitation: retry_result.max_block_range,
+                    fetched: false,
+                    limit: retry_result.limit,
                 });
             }
 
@@ -368,7 +751,8 @@ async fn fetch_historic_logs_stream(
 
             return Some(ProcessHistoricLogsStreamResult {
                 next: current_filter.set_from_block(from_block).set_to_block(halved_to_block),
-                max_block_range_limitation,
+                fetched: false,
+                limit: None,
             });
         }
     }
@@ -383,13 +767,16 @@ async fn live_indexing_stream(
     cached_provider: &Arc<JsonRpcCachedProvider>,
     tx: &mpsc::Sender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
     last_seen_block_number: U64,
-    topic_id: &B256,
-    reorg_safe_distance: &U64,
+    topic_ids: &[B256],
+    indexing_distance_from_head: &U64,
+    reorg_safe_distance: bool,
     mut current_filter: RindexerEventFilter,
     info_log_name: &str,
     disable_logs_bloom_checks: bool,
     network: &str,
     original_max_limit: Option<U64>,
+    backfill: Option<Arc<BackfillProgress>>,
+    database: Option<Arc<PostgresClient>>,
 ) {
     let mut last_seen_block_number = last_seen_block_number;
     let mut log_response_to_large_to_block: Option<U64> = None;
@@ -399,6 +786,37 @@ async fn live_indexing_stream(
 
     let chain_state_notification = cached_provider.get_chain_state_notification();
 
+    // Reth networks get reorgs pushed through the chain state notifications, plain RPC networks
+    // have to detect them by following the block hashes themselves.
+    let mut block_hash_tracker = if chain_state_notification.is_none() {
+        Some(BlockHashTracker::new(REORG_TRACKING_DEPTH))
+    } else {
+        None
+    };
+
+    // networks which store their block headers carry the hashes seen before a restart over, so
+    // blocks reorged out while rindexer was down are still caught
+    if let (Some(tracker), Some(database)) =
+        (block_hash_tracker.as_mut(), database.filter(|_| cached_provider.stores_block_headers()))
+    {
+        seed_block_hash_tracker(tracker, &database, network, last_seen_block_number).await;
+    }
+
+    // The logs already sent downstream, so they can be retracted if their blocks are reorged out.
+    let mut recent_logs = RecentLogs::default();
+
+    // With a websocket subscription the loop sleeps until the chain moves instead of polling,
+    // whatever was missed while it slept is still fetched through `get_logs`. Function calls
+    // never show up in a logs subscription, those keep polling.
+    let mut live_wake = cached_provider
+        .ws_subscriptions()
+        .filter(|_| current_filter.function_call().is_none())
+        .map(|subscriptions| subscriptions.live_wake(current_filter.subscription_filter()));
+
+    // Reth reorg and revert notifications are forwarded as fork blocks to the live loop, which
+    // owns the filter and can rewind it.
+    let (reorg_tx, mut reorg_rx) = mpsc::unbounded_channel::<U64>();
+
     // Spawn a separate task to handle notifications
     if let Some(notifications) = chain_state_notification {
         let info_log_name = info_log_name.to_string();
@@ -406,13 +824,20 @@ async fn live_indexing_stream(
         tokio::spawn(async move {
             let mut notifications_clone = notifications.subscribe();
             while let Ok(notification) = notifications_clone.recv().await {
-                handle_chain_notification(notification, &info_log_name, &network);
+                if let Some(fork_block) =
+                    handle_chain_notification(notification, &info_log_name, &network)
+                {
+                    if reorg_tx.send(fork_block).is_err() {
+                        break;
+                    }
+                }
             }
         });
     }
 
     loop {
         let iteration_start = Instant::now();
+        let mut caught_up = false;
 
         if !is_running() {
             break;
@@ -422,10 +847,82 @@ async fn live_indexing_stream(
         match latest_block {
             Ok(latest_block) => {
                 if let Some(latest_block) = latest_block {
+                    let fork_block = match block_hash_tracker.as_mut() {
+                        Some(tracker) => {
+                            match detect_reorg(cached_provider, tracker, &latest_block).await {
+                                Ok(fork_block) => fork_block,
+                                Err(e) => {
+                                    error!(
+                                        "{}::{} - {} - Error checking for reorgs, will try again in 1 seconds - err: {}",
+                                        info_log_name,
+                                        network,
+                                        IndexingEventProgressStatus::Live.log(),
+                                        e
+                                    );
+                                    tokio::time::sleep(Duration::from_secs(1)).await;
+                                    continue;
+                                }
+                            }
+                        }
+                        // several notifications can arrive between polls, the deepest fork wins
+                        None => std::iter::from_fn(|| reorg_rx.try_recv().ok()).min(),
+                    };
+
+                    if let Some(fork_block) = fork_block {
+                        let next_from_block = current_filter.from_block();
+                        if fork_block + U64::from(1) < next_from_block {
+                            warn!(
+                                "{}::{} - {} - REORG DETECTED! Rolling back blocks {} to {} and re-indexing",
+                                info_log_name,
+                                network,
+                                IndexingEventProgressStatus::Live.log(),
+                                fork_block + U64::from(1),
+                                next_from_block - U64::from(1)
+                            );
+
+                            if let Err(e) = tx
+                                .send(Ok(FetchLogsResult {
+                                    logs: vec![],
+                                    from_block: fork_block + U64::from(1),
+                                    to_block: next_from_block - U64::from(1),
+                                    reorg: Some(ReorgRollback {
+                                        fork_block,
+                                        removed_logs: recent_logs.take_removed(fork_block),
+                                    }),
+                                    backfill: backfill.clone(),
+                                }))
+                                .await
+                            {
+                                error!(
+                                    "{}::{} - {} - Failed to send reorg rollback to stream consumer! Err: {}",
+                                    info_log_name,
+                                    network,
+                                    IndexingEventProgressStatus::Live.log(),
+                                    e
+                                );
+                                break;
+                            }
+
+                            current_filter =
+                                current_filter.set_from_block(fork_block + U64::from(1));
+                            last_seen_block_number = fork_block;
+                            log_response_to_large_to_block = None;
+                        } else {
+                            debug!(
+                                "{}::{} - {} - Reorg above indexed blocks at fork block {}, nothing to roll back",
+                                info_log_name,
+                                network,
+                                IndexingEventProgressStatus::Live.log(),
+                                fork_block
+                            );
+                        }
+                    }
+
                     let to_block_number = log_response_to_large_to_block
                         .unwrap_or(U64::from(latest_block.header.number));
 
                     if last_seen_block_number == to_block_number {
+                        caught_up = true;
                         debug!(
                             "{} - {} - No new blocks to process...",
                             info_log_name,
@@ -450,9 +947,30 @@ async fn live_indexing_stream(
                             last_seen_block_number
                         );
 
-                        let safe_block_number = to_block_number - reorg_safe_distance;
+                        let safe_block_number = match live_safe_block_number(
+                            cached_provider,
+                            to_block_number,
+                            reorg_safe_distance,
+                            *indexing_distance_from_head,
+                        )
+                        .await
+                        {
+                            Ok(safe_block_number) => safe_block_number,
+                            Err(e) => {
+                                error!(
+                                    "{}::{} - {} - Error getting safe block number, will try again in 1 seconds - err: {}",
+                                    info_log_name,
+                                    network,
+                                    IndexingEventProgressStatus::Live.log(),
+                                    e
+                                );
+                                tokio::time::sleep(Duration::from_secs(1)).await;
+                                continue;
+                            }
+                        };
                         let from_block = current_filter.from_block();
                         if from_block > safe_block_number {
+                            caught_up = true;
                             info!(
                                 "{} - {} - not in safe reorg block range yet block: {} > range: {}",
                                 info_log_name,
@@ -463,10 +981,20 @@ async fn live_indexing_stream(
                         } else {
                             let contract_address = current_filter.contract_addresses().await;
 
-                            let to_block = safe_block_number;
+                            // kept to what the endpoint allows, the blocks after it are fetched on
+                            // the next poll
+                            let endpoint = cached_provider.logs_endpoint();
+                            let log_source =
+                                RpcLogSource::new(Arc::clone(cached_provider), endpoint.clone());
+                            let to_block = calculate_process_historic_log_to_block(
+                                &from_block,
+                                &safe_block_number,
+                                &endpoint.state.max_block_range(),
+                            );
                             if from_block == to_block
                                 && !disable_logs_bloom_checks
-                                && !is_relevant_block(&contract_address, topic_id, &latest_block)
+                                && current_filter.function_call().is_none()
+                                && !is_relevant_block(&contract_address, topic_ids, &latest_block)
                             {
                                 debug!(
                                     "{} - {} - Skipping block {} as it's not relevant",
@@ -493,13 +1021,13 @@ async fn live_indexing_stream(
                                     current_filter
                                 );
 
-                                match cached_provider.get_logs(&current_filter).await {
+                                match log_source.get_logs(&current_filter).await {
                                     Ok(logs) => {
                                         debug!(
-                                            "{} - {} - Live topic_id {}, Logs: {} from {} to {}",
+                                            "{} - {} - Live topic_ids {:?}, Logs: {} from {} to {}",
                                             info_log_name,
                                             IndexingEventProgressStatus::Live.log(),
-                                            topic_id,
+                                            topic_ids,
                                             logs.len(),
                                             from_block,
                                             to_block
@@ -518,6 +1046,7 @@ async fn live_indexing_stream(
 
                                         let logs_empty = logs.is_empty();
                                         let last_log = logs.last().cloned();
+                                        recent_logs.record(&logs, to_block);
 
                                         if tx.capacity() == 0 {
                                             warn!(
@@ -533,6 +1062,8 @@ async fn live_indexing_stream(
                                                 logs,
                                                 from_block,
                                                 to_block,
+                                                reorg: None,
+                                                backfill: backfill.clone(),
                                             }))
                                             .await
                                         {
@@ -632,6 +1163,15 @@ async fn live_indexing_stream(
             }
         }
 
+        // a dropped socket makes this fall through to polling until it reconnects
+        if caught_up {
+            if let Some(live_wake) = live_wake.as_mut() {
+                if live_wake.wait(current_filter.from_block()).await {
+                    continue;
+                }
+            }
+        }
+
         let elapsed = iteration_start.elapsed();
         if elapsed < target_iteration_duration {
             tokio::time::sleep(target_iteration_duration - elapsed).await;
@@ -647,7 +1187,7 @@ struct RetryWithBlockRangeResult {
     // who doesn't give block ranges, this tends to be providers
     // which are a lot slower than others, expect these providers
     // to be slow
-    max_block_range: Option<U64>,
+    limit: Option<BlockRangeLimit>,
 }
 
 /// Attempts to retry with a new block range based on the error message.
@@ -704,14 +1244,14 @@ async fn retry_with_block_range(
                         return Some(RetryWithBlockRangeResult {
                             from: from_block,
                             to,
-                            max_block_range: max_block_range_limitation,
+                            limit: None,
                         });
                     }
 
                     return Some(RetryWithBlockRangeResult {
                         from: U64::from(from),
                         to: U64::from(to),
-                        max_block_range: max_block_range_limitation,
+                        limit: None,
                     });
                 } else {
                     info!(
@@ -736,7 +1276,7 @@ async fn retry_with_block_range(
                     return Some(RetryWithBlockRangeResult {
                         from: U64::from(from),
                         to: U64::from(to),
-                        max_block_range: max_block_range_limitation,
+                        limit: None,
                     });
                 }
             }
@@ -753,7 +1293,7 @@ async fn retry_with_block_range(
         return Some(RetryWithBlockRangeResult {
             from: from_block,
             to: from_block + suggested_range,
-            max_block_range: Some(suggested_range),
+            limit: Some(BlockRangeLimit::Guessed(suggested_range)),
         });
     }
 
@@ -771,7 +1311,7 @@ async fn retry_with_block_range(
                     return Some(RetryWithBlockRangeResult {
                         from: from_block,
                         to: from_block + suggested_range,
-                        max_block_range: Some(suggested_range),
+                        limit: Some(BlockRangeLimit::Stated(range)),
                     });
                 }
             }
@@ -788,7 +1328,7 @@ async fn retry_with_block_range(
         return Some(RetryWithBlockRangeResult {
             from: from_block,
             to: from_block + suggested_range,
-            max_block_range: Some(suggested_range),
+            limit: Some(BlockRangeLimit::Guessed(suggested_range)),
         });
     }
 
@@ -800,7 +1340,7 @@ async fn retry_with_block_range(
         return Some(RetryWithBlockRangeResult {
             from: from_block,
             to: halved_to_block,
-            max_block_range: max_block_range_limitation,
+            limit: None,
         });
     }
 
@@ -810,7 +1350,7 @@ async fn retry_with_block_range(
         return Some(RetryWithBlockRangeResult {
             from: from_block,
             to: halved_block_number(to_block, from_block),
-            max_block_range: max_block_range_limitation,
+            limit: None,
         });
     }
 
@@ -837,7 +1377,7 @@ async fn retry_with_block_range(
             return Some(RetryWithBlockRangeResult {
                 from: from_block,
                 to: halved_block_number(to_block, from_block),
-                max_block_range: max_block_range_limitation,
+                limit: None,
             });
         }
 
@@ -850,7 +1390,7 @@ async fn retry_with_block_range(
         return Some(RetryWithBlockRangeResult {
             from: from_block,
             to: from_block + suggested_range,
-            max_block_range: Some(suggested_range),
+            limit: Some(BlockRangeLimit::Guessed(suggested_range)),
         });
     }
 
@@ -964,3 +1504,108 @@ fn calculate_process_historic_log_to_block(
         *snapshot_to_block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, Bytes, LogData},
+        rpc::types::ValueOrArray,
+    };
+
+    use super::*;
+    use crate::{
+        event::contract_setup::FilterDetails, manifest::contract::EventInputIndexedFilters,
+    };
+
+    fn log_at(block_number: u64, topics: Vec<B256>) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(topics, Bytes::new()),
+            },
+            block_number: Some(block_number),
+            ..Log::default()
+        }
+    }
+
+    fn event_filter(
+        topic_id: B256,
+        indexed_1: Option<&str>,
+        start_block: u64,
+    ) -> RindexerEventFilter {
+        let filter_details = FilterDetails {
+            events: ValueOrArray::Value("Event".to_string()),
+            indexed_filters: Some(vec![EventInputIndexedFilters {
+                event_name: "Event".to_string(),
+                indexed_1: indexed_1.map(|value| vec![value.to_string()]),
+                indexed_2: None,
+                indexed_3: None,
+            }]),
+        };
+
+        RindexerEventFilter::new_filter(
+            &topic_id,
+            "Event",
+            &filter_details,
+            U64::from(start_block),
+            U64::from(200),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_event_logs_result_routes_logs_to_their_event() {
+        let transfer = B256::from([1u8; 32]);
+        let approval = B256::from([2u8; 32]);
+        let owner = B256::from([3u8; 32]);
+
+        let result = FetchLogsResult {
+            logs: vec![
+                log_at(100, vec![transfer, owner]),
+                log_at(101, vec![approval, owner]),
+                log_at(120, vec![transfer, B256::ZERO]),
+                log_at(150, vec![approval, B256::ZERO]),
+            ],
+            from_block: U64::from(100),
+            to_block: U64::from(150),
+            reorg: None,
+            backfill: None,
+        };
+
+        let transfers =
+            event_logs_result(&result, &event_filter(transfer, None, 100), None).unwrap();
+        assert_eq!(
+            transfers.logs.iter().map(|log| log.block_number.unwrap()).collect::<Vec<_>>(),
+            vec![100, 120]
+        );
+
+        // the indexed topic filter and the checkpoint of the event both apply
+        let approvals =
+            event_logs_result(&result, &event_filter(approval, Some("0"), 110), None).unwrap();
+        assert_eq!(
+            approvals.logs.iter().map(|log| log.block_number.unwrap()).collect::<Vec<_>>(),
+            vec![150]
+        );
+        assert_eq!(approvals.from_block, U64::from(110));
+
+        assert!(event_logs_result(&result, &event_filter(approval, None, 151), None).is_none());
+
+        // an event which is not live indexed stops at its own end block
+        let transfers =
+            event_logs_result(&result, &event_filter(transfer, None, 100), Some(U64::from(110)))
+                .unwrap();
+        assert_eq!(
+            transfers.logs.iter().map(|log| log.block_number.unwrap()).collect::<Vec<_>>(),
+            vec![100]
+        );
+        assert_eq!(transfers.to_block, U64::from(110));
+
+        let later = FetchLogsResult { from_block: U64::from(111), ..result };
+        assert!(event_logs_result(
+            &later,
+            &event_filter(transfer, None, 100),
+            Some(U64::from(110))
+        )
+        .is_none());
+    }
+}
@@ -68,6 +68,47 @@ fn build_last_synced_block_number_file(
     path.to_string_lossy().into_owned()
 }
 
+fn build_synced_segments_file(
+    full_path: &Path,
+    contract_name: &str,
+    network: &str,
+    event_name: &str,
+) -> String {
+    let path = full_path.join(contract_name).join("last-synced-blocks").join(format!(
+        "{}-{}-{}-segments.txt",
+        contract_name.to_lowercase(),
+        network.to_lowercase(),
+        event_name.to_lowercase()
+    ));
+
+    path.to_string_lossy().into_owned()
+}
+
+/// Synced segments are stored as `from-to` block ranges separated by commas.
+fn format_synced_segments(segments: &[(U64, U64)]) -> String {
+    segments.iter().map(|(from, to)| format!("{from}-{to}")).collect::<Vec<_>>().join(",")
+}
+
+fn parse_synced_segments(value: &str) -> Result<Vec<(U64, U64)>, UpdateLastSyncedBlockNumberFile> {
+    let mut segments = value
+        .split(',')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let parse_error = |e: &dyn std::fmt::Display| {
+                UpdateLastSyncedBlockNumberFile::ParseError(segment.to_string(), e.to_string())
+            };
+            let (from, to) = segment.split_once('-').ok_or_else(|| parse_error(&"missing `-`"))?;
+            let from = U64::from_str(from.trim()).map_err(|e| parse_error(&e))?;
+            let to = U64::from_str(to.trim()).map_err(|e| parse_error(&e))?;
+            Ok((from, to))
+        })
+        .collect::<Result<Vec<_>, UpdateLastSyncedBlockNumberFile>>()?;
+
+    segments.sort();
+    Ok(segments)
+}
+
 pub struct SyncConfig<'a> {
     pub project_path: &'a Path,
     pub database: &'a Option<Arc<PostgresClient>>,
@@ -211,6 +252,228 @@ async fn update_last_synced_block_number_for_file(
     Ok(())
 }
 
+async fn rewind_last_synced_block_number_for_file(
+    contract_name: &str,
+    network: &str,
+    event_name: &str,
+    full_path: &Path,
+    to_block: U64,
+) -> Result<(), UpdateLastSyncedBlockNumberFile> {
+    let file_path =
+        build_last_synced_block_number_file(full_path, contract_name, network, event_name);
+
+    let last_block =
+        get_last_synced_block_number_file(full_path, contract_name, network, event_name).await?;
+
+    if last_block.is_some_and(|last_block_value| last_block_value > to_block) {
+        let temp_file_path = format!("{file_path}.tmp");
+
+        let mut file = File::create(&temp_file_path).await?;
+        file.write_all(to_block.to_string().as_bytes()).await?;
+        file.sync_all().await?;
+
+        fs::rename(temp_file_path, file_path).await?;
+    }
+
+    Ok(())
+}
+
+async fn get_synced_segments_file(
+    full_path: &Path,
+    contract_name: &str,
+    network: &str,
+    event_name: &str,
+) -> Result<Vec<(U64, U64)>, UpdateLastSyncedBlockNumberFile> {
+    let file_path = build_synced_segments_file(full_path, contract_name, network, event_name);
+
+    match fs::read_to_string(&file_path).await {
+        Ok(value) => parse_synced_segments(&value),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn update_synced_segments_for_file(
+    contract_name: &str,
+    network: &str,
+    event_name: &str,
+    full_path: &Path,
+    segments: &[(U64, U64)],
+) -> Result<(), UpdateLastSyncedBlockNumberFile> {
+    let file_path = build_synced_segments_file(full_path, contract_name, network, event_name);
+    let temp_file_path = format!("{file_path}.tmp");
+
+    let mut file = File::create(&temp_file_path).await?;
+    file.write_all(format_synced_segments(segments).as_bytes()).await?;
+    file.sync_all().await?;
+
+    fs::rename(temp_file_path, file_path).await?;
+
+    Ok(())
+}
+
+/// The storage holding the last synced block of the event, the synced segments are kept next to
+/// it.
+fn last_synced_file_path(config: &EventProcessingConfig) -> Option<std::path::PathBuf> {
+    if let Some(csv_details) = &config.csv_details() {
+        Some(get_full_path(&config.project_path(), &csv_details.path).unwrap_or_else(|_| {
+            panic!("failed to get full path {}", config.project_path().display())
+        }))
+    } else {
+        config.stream_last_synced_block_file_path().map(|stream_last_synced_block_file_path| {
+            config
+                .project_path()
+                .join(stream_last_synced_block_file_path)
+                .canonicalize()
+                .expect("Failed to canonicalize path")
+        })
+    }
+}
+
+/// The block ranges above the last synced block which a segmented backfill already indexed.
+pub async fn get_synced_segments(config: &EventProcessingConfig) -> Vec<(U64, U64)> {
+    let network = &config.network_contract().network;
+
+    if let Some(database) = &config.database() {
+        let schema =
+            generate_indexer_contract_schema_name(&config.indexer_name(), &config.contract_name());
+        let table_name = generate_internal_event_table_name(&schema, &config.event_name());
+        let query = format!(
+            "SELECT synced_segments FROM rindexer_internal.{table_name} WHERE network = $1"
+        );
+
+        return match database.query_one(&query, &[network]).await {
+            Ok(row) => {
+                let value: Option<String> = row.get("synced_segments");
+                parse_synced_segments(&value.unwrap_or_default()).unwrap_or_else(|e| {
+                    error!("Error parsing db synced segments: {:?}", e);
+                    vec![]
+                })
+            }
+            Err(e) => {
+                error!("Error fetching synced segments: {:?}", e);
+                vec![]
+            }
+        };
+    }
+
+    if let Some(full_path) = last_synced_file_path(config) {
+        return get_synced_segments_file(
+            &full_path,
+            &config.contract_name(),
+            network,
+            &config.event_name(),
+        )
+        .await
+        .unwrap_or_else(|e| {
+            error!("Error fetching synced segments from file: {:?}", e);
+            vec![]
+        });
+    }
+
+    vec![]
+}
+
+/// Persist the block ranges above the last synced block which a segmented backfill indexed.
+pub async fn update_synced_segments(config: &EventProcessingConfig, segments: &[(U64, U64)]) {
+    let network = &config.network_contract().network;
+
+    if let Some(database) = &config.database() {
+        let schema =
+            generate_indexer_contract_schema_name(&config.indexer_name(), &config.contract_name());
+        let table_name = generate_internal_event_table_name(&schema, &config.event_name());
+        let query = format!(
+            "UPDATE rindexer_internal.{table_name} SET synced_segments = $1 WHERE network = $2"
+        );
+        let result = database
+            .execute(
+                &query,
+                &[&EthereumSqlTypeWrapper::String(format_synced_segments(segments)), network],
+            )
+            .await;
+
+        if let Err(e) = result {
+            error!("Error updating db synced segments: {:?}", e);
+        }
+    }
+
+    if let Some(full_path) = last_synced_file_path(config) {
+        if let Err(e) = update_synced_segments_for_file(
+            &config.contract_name(),
+            network,
+            &config.event_name(),
+            &full_path,
+            segments,
+        )
+        .await
+        {
+            error!("Error updating synced segments file - error - {:?}", e);
+        }
+    }
+}
+
+/// Move the last synced block backwards, used when a reorg has rolled back indexed blocks.
+///
+/// Unlike [`update_progress_and_last_synced_task`] this only ever lowers the checkpoint.
+pub async fn rewind_last_synced_block(config: &EventProcessingConfig, to_block: U64) {
+    let network = &config.network_contract().network;
+
+    if let Some(database) = &config.database() {
+        let schema =
+            generate_indexer_contract_schema_name(&config.indexer_name(), &config.contract_name());
+        let table_name = generate_internal_event_table_name(&schema, &config.event_name());
+        let query = format!(
+            "UPDATE rindexer_internal.{table_name} SET last_synced_block = $1 WHERE network = $2 AND last_synced_block > $1"
+        );
+        let result =
+            database.execute(&query, &[&EthereumSqlTypeWrapper::U64(to_block), network]).await;
+
+        if let Err(e) = result {
+            error!("Error rewinding db last synced block: {:?}", e);
+        }
+    }
+
+    if let Some(csv_details) = &config.csv_details() {
+        if let Err(e) = rewind_last_synced_block_number_for_file(
+            &config.contract_name(),
+            network,
+            &config.event_name(),
+            &get_full_path(&config.project_path(), &csv_details.path).unwrap_or_else(|_| {
+                panic!("failed to get full path {}", config.project_path().display())
+            }),
+            to_block,
+        )
+        .await
+        {
+            error!(
+                "Error rewinding last synced block in CSV - path - {} error - {:?}",
+                csv_details.path, e
+            );
+        }
+    } else if let Some(stream_last_synced_block_file_path) =
+        &config.stream_last_synced_block_file_path()
+    {
+        if let Err(e) = rewind_last_synced_block_number_for_file(
+            &config.contract_name(),
+            network,
+            &config.event_name(),
+            &config
+                .project_path()
+                .join(stream_last_synced_block_file_path)
+                .canonicalize()
+                .expect("Failed to canonicalize path"),
+            to_block,
+        )
+        .await
+        {
+            error!(
+                "Error rewinding last synced block in stream - path - {} error - {:?}",
+                stream_last_synced_block_file_path, e
+            );
+        }
+    }
+}
+
 /// Update the last indexed block.
 ///
 /// Note: this is an async task and should be awaited rather than spawned in the background
@@ -384,3 +647,18 @@ pub async fn evm_trace_update_progress_and_last_synced_task(
 
     on_complete();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synced_segments_round_trip() {
+        let segments = vec![(U64::from(10), U64::from(20)), (U64::from(40), U64::from(55))];
+
+        assert_eq!(format_synced_segments(&segments), "10-20,40-55");
+        assert_eq!(parse_synced_segments("40-55, 10-20").unwrap(), segments);
+        assert!(parse_synced_segments("").unwrap().is_empty());
+        assert!(parse_synced_segments("10").is_err());
+    }
+}
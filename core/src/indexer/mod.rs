@@ -4,6 +4,8 @@ mod progress;
 pub use progress::{IndexingEventProgressStatus, IndexingEventsProgressState};
 use serde::{Deserialize, Serialize};
 
+mod backfill;
+pub mod contract_deployment;
 mod dependency;
 pub use dependency::ContractEventDependenciesMapFromRelationshipsError;
 mod fetch_logs;
@@ -17,7 +19,9 @@ pub mod task_tracker;
 
 pub use dependency::{ContractEventDependencies, EventDependencies, EventsDependencyTree};
 
-use crate::manifest::{contract::Contract, native_transfer::NativeTransfers};
+use crate::manifest::{
+    contract::Contract, contract_deployment::ContractDeployments, native_transfer::NativeTransfers,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Indexer {
@@ -26,4 +30,6 @@ pub struct Indexer {
     pub contracts: Vec<Contract>,
 
     pub native_transfers: NativeTransfers,
+    #[serde(default)]
+    pub contract_deployments: ContractDeployments,
 }
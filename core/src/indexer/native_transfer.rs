@@ -17,11 +17,13 @@ use crate::{
     event::{
         callback_registry::{TraceResult, TxInformation},
         config::TraceProcessingConfig,
+        contract_setup::TraceEventKind,
     },
     indexer::{
+        contract_deployment::contract_deployment_block_consumer,
         last_synced::evm_trace_update_progress_and_last_synced_task,
         process::ProcessEventError,
-        reorg::handle_chain_notification,
+        reorg::{handle_chain_notification, live_safe_block_number},
         task_tracker::{indexing_event_processed, indexing_event_processing},
     },
     manifest::native_transfer::TraceProcessingMethod,
@@ -110,6 +112,7 @@ pub async fn native_transfer_block_fetch(
     start_block: U64,
     end_block: Option<U64>,
     indexing_distance_from_head: U64,
+    reorg_safe_distance: bool,
     network: String,
 ) -> Result<(), ProcessEventError> {
     let mut last_seen_block = start_block;
@@ -141,7 +144,21 @@ pub async fn native_transfer_block_fetch(
                 let block = U64::from(latest_block.header.number);
 
                 // Always trim back to the safe indexing threshold (which is zero if disabled)
-                let block = block - indexing_distance_from_head;
+                let block = match live_safe_block_number(
+                    &publisher,
+                    block,
+                    reorg_safe_distance,
+                    indexing_distance_from_head,
+                )
+                .await
+                {
+                    Ok(block) => block,
+                    Err(e) => {
+                        error!("Error fetching '{}' safe block: {}", network, e.to_string());
+                        sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
 
                 if block > last_seen_block {
                     let to_block = end_block.map(|end| block.min(end)).unwrap_or(block);
@@ -169,6 +186,8 @@ pub async fn native_transfer_block_fetch(
     }
 }
 
+/// Block consumer, also used for contract deployments which are the other event of the
+/// `EvmTraces` contract.
 pub async fn native_transfer_block_processor(
     network_name: String,
     provider: Arc<JsonRpcCachedProvider>,
@@ -208,13 +227,26 @@ pub async fn native_transfer_block_processor(
             continue;
         }
 
-        let processed_block = native_transfer_block_consumer(
-            provider.clone(),
-            &buffer[..recv],
-            &network_name,
-            &config,
-        )
-        .await;
+        let processed_block = match config.kind {
+            TraceEventKind::ContractDeployment => {
+                contract_deployment_block_consumer(
+                    provider.clone(),
+                    &buffer[..recv],
+                    &network_name,
+                    &config,
+                )
+                .await
+            }
+            TraceEventKind::NativeTransfer => {
+                native_transfer_block_consumer(
+                    provider.clone(),
+                    &buffer[..recv],
+                    &network_name,
+                    &config,
+                )
+                .await
+            }
+        };
 
         // If this has an error, we need to not and reconsume the blocks. We don't have
         // to worry about double-publish because the failure point is on the provider
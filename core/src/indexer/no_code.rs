@@ -7,26 +7,33 @@ use std::{
 use alloy::{
     dyn_abi::DynSolValue,
     json_abi::{Event, JsonAbi},
+    primitives::U64,
 };
 use colored::Colorize;
 use serde_json::Value;
-use tokio_postgres::types::Type as PgType;
+use tokio_postgres::types::{ToSql, Type as PgType};
 use tracing::{debug, error, info, warn};
 
-use super::native_transfer::{NATIVE_TRANSFER_ABI, NATIVE_TRANSFER_CONTRACT_NAME};
+use super::{
+    contract_deployment::{CONTRACT_CREATED_ABI, CONTRACT_CREATED_EVENT_NAME},
+    native_transfer::{NATIVE_TRANSFER_ABI, NATIVE_TRANSFER_CONTRACT_NAME},
+};
 use crate::helpers::{map_log_params_to_raw_values, parse_log};
 use crate::manifest::contract::Contract;
 use crate::{
     abi::{ABIItem, CreateCsvFileForEvent, EventInfo, ParamTypeError, ReadAbiError},
     chat::ChatClients,
     database::postgres::{
-        client::PostgresClient,
+        client::{BulkInsertPostgresError, PostgresClient},
         generate::{
             generate_column_names_only_with_base_properties, generate_event_table_full_name,
+            generate_indexer_contract_schema_name, generate_internal_event_table_name,
+            TX_CONTEXT_COLUMNS,
         },
         setup::{setup_postgres, SetupPostgresError},
         sql_type_wrapper::{
-            map_ethereum_wrapper_to_json, map_log_params_to_ethereum_wrapper,
+            map_block_timestamp_to_ethereum_wrapper, map_ethereum_wrapper_to_json,
+            map_log_params_to_ethereum_wrapper, map_tx_context_to_ethereum_wrapper,
             EthereumSqlTypeWrapper,
         },
     },
@@ -37,12 +44,14 @@ use crate::{
             TraceCallbackType, TxInformation,
         },
         contract_setup::{ContractInformation, CreateContractInformationError, TraceInformation},
-        EventMessage,
+        EventMessage, EventMessageType,
     },
     generate_random_id,
     manifest::{
+        chat::ChatConfig,
         contract::ParseAbiError,
         core::Manifest,
+        stream::StreamsConfig,
         yaml::{read_manifest, ReadManifestError},
     },
     provider::{CreateNetworkProvider, RetryClientError},
@@ -52,6 +61,10 @@ use crate::{
     AsyncCsvAppender, FutureExt, IndexingDetails, StartDetails, StartNoCodeDetails,
 };
 
+/// Retracted events are deleted this many at a time, two parameters each keep a statement well
+/// under the postgres parameter limit.
+const RETRACTED_EVENTS_DELETE_CHUNK_SIZE: usize = 10_000;
+
 #[derive(thiserror::Error, Debug)]
 pub enum SetupNoCodeError {
     #[error("Could not work out project path from the parent of the manifest")]
@@ -144,9 +157,27 @@ pub async fn setup_no_code(
                 );
             }
 
+            if manifest.contract_deployments.enabled {
+                info!(
+                    "Contract deployments to index: {}",
+                    manifest
+                        .contract_deployments
+                        .networks
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|network| network.network.clone())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                );
+            }
+
             Ok(StartDetails {
                 manifest_path: details.manifest_path,
-                indexing_details: Some(IndexingDetails { registry, trace_registry }),
+                indexing_details: Some(IndexingDetails {
+                    registry,
+                    trace_registry,
+                    replay_failed_batches: details.indexing_details.replay_failed_batches,
+                }),
                 graphql_details: details.graphql_details,
             })
         }
@@ -161,14 +192,84 @@ struct NoCodeCallbackParams {
     contract_name: String,
     event: Event,
     index_event_in_order: bool,
+    include_tx_context: bool,
     csv: Option<Arc<AsyncCsvAppender>>,
     postgres: Option<Arc<PostgresClient>>,
     postgres_event_table_name: String,
     postgres_column_names: Vec<String>,
+    postgres_checkpoint_table_name: Option<String>,
     streams_clients: Arc<Option<StreamsClients>>,
     chat_clients: Arc<Option<ChatClients>>,
 }
 
+/// No-code writes the block timestamp on top of the base columns, it stays `NULL` unless the
+/// contract or network has `include_block_timestamp` enabled or the node returns it. Contracts
+/// with `include_tx_context` get the transaction columns after it.
+fn no_code_column_names(event_info: &EventInfo, include_tx_context: bool) -> Vec<String> {
+    let mut column_names = generate_column_names_only_with_base_properties(&event_info.inputs);
+    column_names.push("block_timestamp".to_string());
+    if include_tx_context {
+        column_names.extend(TX_CONTEXT_COLUMNS.iter().map(|(name, _)| name.to_string()));
+    }
+    column_names
+}
+
+/// The internal table holding the `last_synced_block` checkpoint for the event, only returned
+/// when the checkpoint should be committed in the same transaction as the event rows.
+///
+/// Segmented backfills finish their ranges out of order, their checkpoint is only moved once the
+/// ranges below it are indexed so it is left to them.
+fn postgres_checkpoint_table_name(
+    manifest: &Manifest,
+    contract_name: &str,
+    event_name: &str,
+) -> Option<String> {
+    if !manifest.storage.postgres_atomic_checkpoint()
+        || manifest.config.backfill_segments.is_some_and(|segments| segments > 1)
+    {
+        return None;
+    }
+
+    let schema = generate_indexer_contract_schema_name(&manifest.name, contract_name);
+    Some(generate_internal_event_table_name(&schema, event_name))
+}
+
+/// Writes the event rows and moves the `last_synced_block` checkpoint forward in one
+/// transaction, so a crash can never leave one committed without the other.
+async fn insert_bulk_with_checkpoint(
+    postgres: &PostgresClient,
+    params: Arc<NoCodeCallbackParams>,
+    checkpoint_table_name: String,
+    network: String,
+    to_block: U64,
+    postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>>,
+) -> Result<(), BulkInsertPostgresError> {
+    postgres
+        .with_transaction(move |transaction| {
+            Box::pin(async move {
+                transaction
+                    .insert_bulk(
+                        &params.postgres_event_table_name,
+                        &params.postgres_column_names,
+                        &postgres_bulk_data,
+                    )
+                    .await?;
+
+                transaction
+                    .execute(
+                        &format!(
+                            "UPDATE rindexer_internal.{checkpoint_table_name} SET last_synced_block = $1 WHERE network = $2 AND $1 > last_synced_block"
+                        ),
+                        &[&EthereumSqlTypeWrapper::U64(to_block), &network],
+                    )
+                    .await?;
+
+                Ok(())
+            })
+        })
+        .await
+}
+
 struct EventCallbacks {
     event_callback: EventCallbackType,
     trace_callback: TraceCallbackType,
@@ -218,6 +319,13 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 }
             };
 
+            // a batch of removed logs retracts events which were reorged out of the chain
+            let is_removed = match &results {
+                CallbackResult::Event(events) => events.iter().all(|event| event.log.removed),
+                CallbackResult::Trace(_) => false,
+            };
+            let mut removed_events = Vec::new();
+
             let mut indexed_count = 0;
             let mut postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = Vec::new();
             let mut postgres_bulk_column_types: Vec<PgType> = Vec::new();
@@ -236,9 +344,11 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                         let transaction_hash = result.tx_information.transaction_hash;
                         let block_number = result.tx_information.block_number;
                         let block_hash = result.tx_information.block_hash;
+                        let block_timestamp = result.tx_information.block_timestamp;
                         let network = result.tx_information.network.to_string();
                         let transaction_index = result.tx_information.transaction_index;
                         let log_index = result.tx_information.log_index;
+                        let tx_context = result.tx_information.tx_context.clone();
 
                         let event_parameters: Vec<EthereumSqlTypeWrapper> =
                             map_log_params_to_ethereum_wrapper(
@@ -247,14 +357,19 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                             );
 
                         let contract_address = EthereumSqlTypeWrapper::Address(address);
-                        let end_global_parameters = vec![
+                        let mut end_global_parameters = vec![
                             EthereumSqlTypeWrapper::B256(transaction_hash),
                             EthereumSqlTypeWrapper::U64(block_number),
                             EthereumSqlTypeWrapper::B256(block_hash),
                             EthereumSqlTypeWrapper::String(network.to_string()),
                             EthereumSqlTypeWrapper::U64(transaction_index),
                             EthereumSqlTypeWrapper::U256(log_index),
+                            map_block_timestamp_to_ethereum_wrapper(&result.tx_information),
                         ];
+                        if params.include_tx_context {
+                            end_global_parameters
+                                .extend(map_tx_context_to_ethereum_wrapper(&result.tx_information));
+                        }
 
                         Some((
                             log.params,
@@ -264,6 +379,8 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                             transaction_index,
                             block_number,
                             block_hash,
+                            block_timestamp,
+                            tx_context,
                             network,
                             contract_address,
                             event_parameters,
@@ -274,22 +391,47 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 CallbackResult::Trace(events) => events
                     .iter()
                     .map(|result| {
-                        let log_params = vec![
-                            LogParam::new("from".to_string(), DynSolValue::Address(result.from)),
-                            LogParam::new("to".to_string(), DynSolValue::Address(result.to)),
-                            LogParam::new(
-                                "value".to_string(),
-                                DynSolValue::Uint(result.value, 256),
-                            ),
-                        ];
+                        let log_params = if params.event_info.name == CONTRACT_CREATED_EVENT_NAME {
+                            vec![
+                                LogParam::new(
+                                    "deployer".to_string(),
+                                    DynSolValue::Address(result.from),
+                                ),
+                                LogParam::new(
+                                    "address".to_string(),
+                                    DynSolValue::Address(result.to),
+                                ),
+                                LogParam::new(
+                                    "codeHash".to_string(),
+                                    DynSolValue::FixedBytes(
+                                        result.code_hash.unwrap_or_default(),
+                                        32,
+                                    ),
+                                ),
+                            ]
+                        } else {
+                            vec![
+                                LogParam::new(
+                                    "from".to_string(),
+                                    DynSolValue::Address(result.from),
+                                ),
+                                LogParam::new("to".to_string(), DynSolValue::Address(result.to)),
+                                LogParam::new(
+                                    "value".to_string(),
+                                    DynSolValue::Uint(result.value, 256),
+                                ),
+                            ]
+                        };
 
                         let address = result.tx_information.address;
                         let transaction_hash = result.tx_information.transaction_hash;
                         let block_number = result.tx_information.block_number;
                         let block_hash = result.tx_information.block_hash;
+                        let block_timestamp = result.tx_information.block_timestamp;
                         let network = result.tx_information.network.to_string();
                         let transaction_index = result.tx_information.transaction_index;
                         let log_index = result.tx_information.log_index;
+                        let tx_context = result.tx_information.tx_context.clone();
 
                         let event_parameters: Vec<EthereumSqlTypeWrapper> =
                             map_log_params_to_ethereum_wrapper(
@@ -305,6 +447,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                             EthereumSqlTypeWrapper::String(network.to_string()),
                             EthereumSqlTypeWrapper::U64(transaction_index),
                             EthereumSqlTypeWrapper::U256(log_index),
+                            map_block_timestamp_to_ethereum_wrapper(&result.tx_information),
                         ];
 
                         (
@@ -315,6 +458,8 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                             transaction_index,
                             block_number,
                             block_hash,
+                            block_timestamp,
+                            tx_context,
                             network,
                             contract_address,
                             event_parameters,
@@ -332,6 +477,8 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 transaction_index,
                 block_number,
                 block_hash,
+                block_timestamp,
+                tx_context,
                 network,
                 contract_address,
                 event_parameters,
@@ -348,15 +495,22 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                             block_hash,
                             block_number,
                             transaction_hash,
-                            block_timestamp: None,
+                            block_timestamp,
                             log_index,
                             transaction_index,
+                            tx_context,
+                            removed: is_removed,
                         },
                         false,
                     );
                     event_message_data.push(event_result);
                 }
 
+                if is_removed {
+                    removed_events.push((transaction_hash, log_index, block_hash));
+                    continue;
+                }
+
                 let mut all_params: Vec<EthereumSqlTypeWrapper> = vec![contract_address];
                 all_params.extend(event_parameters);
                 all_params.extend(end_global_parameters);
@@ -389,9 +543,75 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 indexed_count += 1;
             }
 
-            if let Some(postgres) = &params.postgres {
+            if is_removed {
+                // a reorg rollback has already removed these rows, the node can also flag logs as
+                // removed on its own though so they are deleted in one statement per chunk
+                if let Some(postgres) = &params.postgres {
+                    for chunk in removed_events.chunks(RETRACTED_EVENTS_DELETE_CHUNK_SIZE) {
+                        let keys = chunk
+                            .iter()
+                            .enumerate()
+                            .map(|(index, _)| format!("(${}, ${})", index * 2 + 2, index * 2 + 3))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let query = format!(
+                            "DELETE FROM {} WHERE network = $1 AND (tx_hash, log_index) IN ({keys})",
+                            params.postgres_event_table_name
+                        );
+                        let values = chunk
+                            .iter()
+                            .flat_map(|(transaction_hash, log_index, _)| {
+                                [
+                                    EthereumSqlTypeWrapper::B256(*transaction_hash),
+                                    EthereumSqlTypeWrapper::U256(*log_index),
+                                ]
+                            })
+                            .collect::<Vec<_>>();
+                        let mut query_params: Vec<&(dyn ToSql + Sync)> = vec![&network];
+                        query_params.extend(values.iter().map(|value| value as &(dyn ToSql + Sync)));
+
+                        if let Err(e) = postgres.execute(&query, &query_params).await {
+                            error!(
+                                "{}::{} - Error removing retracted events: {}",
+                                params.contract_name, params.event_info.name, e
+                            );
+                            return Err(e.to_string());
+                        }
+                    }
+                }
+
+                // csv rows have no log index, but every log of a removed block is removed
+                if let Some(csv) = &params.csv {
+                    let block_hashes =
+                        removed_events.iter().map(|(_, _, block_hash)| *block_hash).collect();
+                    if let Err(e) = csv.remove_blocks(&network, block_hashes).await {
+                        error!(
+                            "{}::{} - Error removing retracted events from csv: {}",
+                            params.contract_name, params.event_info.name, e
+                        );
+                        return Err(e.to_string());
+                    }
+                }
+            } else if let Some(postgres) = &params.postgres {
                 let bulk_data_length = postgres_bulk_data.len();
-                if bulk_data_length > 0 {
+                if let Some(checkpoint_table_name) = &params.postgres_checkpoint_table_name {
+                    if let Err(e) = insert_bulk_with_checkpoint(
+                        postgres,
+                        Arc::clone(&params),
+                        checkpoint_table_name.clone(),
+                        network.clone(),
+                        to_block,
+                        postgres_bulk_data,
+                    )
+                    .await
+                    {
+                        error!(
+                            "{}::{} - Error performing bulk insert: {}",
+                            params.contract_name, params.event_info.name, e
+                        );
+                        return Err(e.to_string());
+                    }
+                } else if bulk_data_length > 0 {
                     // anything over 100 events is considered bulk and goes the COPY route
                     if bulk_data_length > 100 {
                         if let Err(e) = postgres
@@ -439,6 +659,11 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 event_data: Value::Array(event_message_data),
                 event_signature_hash: params.event.selector(),
                 network: network.clone(),
+                message_type: if is_removed {
+                    EventMessageType::Removed
+                } else {
+                    EventMessageType::Event
+                },
             };
 
             if let Some(streams_clients) = params.streams_clients.as_ref() {
@@ -478,6 +703,19 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbacks {
                 }
             }
 
+            if is_removed {
+                info!(
+                    "{}::{} - {} - {} events {}",
+                    params.contract_name,
+                    params.event_info.name,
+                    "REMOVED".yellow(),
+                    removed_events.len(),
+                    format!("- blocks: {} - {} - network: {}", from_block, to_block, network)
+                );
+
+                return Ok(());
+            }
+
             if let Some(chat_clients) = params.chat_clients.as_ref() {
                 if !chat_clients.is_in_block_range_to_send(&from_block, &to_block) {
                     warn!(
@@ -658,10 +896,12 @@ async fn process_contract(
             csv = Some(Arc::new(csv_appender));
         }
 
-        let postgres_column_names =
-            generate_column_names_only_with_base_properties(&event_info.inputs);
+        let include_tx_context = contract.include_tx_context.unwrap_or_default();
+        let postgres_column_names = no_code_column_names(&event_info, include_tx_context);
         let postgres_event_table_name =
             generate_event_table_full_name(&manifest.name, &contract.name, &event_info.name);
+        let postgres_checkpoint_table_name =
+            postgres_checkpoint_table_name(manifest, &contract.name, &event_info.name);
 
         let streams_client = if let Some(streams) = &contract.streams {
             Some(StreamsClients::new(streams.clone()).await)
@@ -685,6 +925,7 @@ async fn process_contract(
             indexer_name: manifest.name.clone(),
             event_name: event_info.name.clone(),
             index_event_in_order,
+            on_error: contract.on_error_for_event(&event_info.name),
             topic_id: event_info.topic_id(),
             contract: contract_information,
             callback: no_code_callback(Arc::new(NoCodeCallbackParams {
@@ -693,10 +934,12 @@ async fn process_contract(
                 contract_name: contract.name.clone(),
                 event: event.clone(),
                 index_event_in_order,
+                include_tx_context,
                 csv,
                 postgres: postgres.clone(),
                 postgres_event_table_name,
                 postgres_column_names,
+                postgres_checkpoint_table_name,
                 streams_clients: Arc::new(streams_client),
                 chat_clients: Arc::new(chat_clients),
             }))
@@ -717,18 +960,67 @@ pub async fn process_trace_events(
 ) -> Result<Vec<TraceCallbackRegistryInformation>, ProcessIndexersError> {
     let mut events: Vec<TraceCallbackRegistryInformation> = vec![];
 
-    if !manifest.has_enabled_native_transfers() {
-        return Ok(events);
+    if manifest.has_enabled_native_transfers() {
+        let native_transfers = &manifest.native_transfers;
+        let settings = TraceEventsSettings {
+            abi_str: NATIVE_TRANSFER_ABI,
+            trace_information: TraceInformation::create(
+                native_transfers.clone(),
+                network_providers,
+            )?,
+            streams: native_transfers.streams.clone(),
+            chat: native_transfers.chat.clone(),
+            generate_csv: native_transfers.generate_csv,
+        };
+        events.extend(
+            process_trace_contract_events(project_path, manifest, postgres.clone(), settings)
+                .await?,
+        );
     }
 
-    let abi_str = NATIVE_TRANSFER_ABI;
-    let abi: JsonAbi = serde_json::from_str(abi_str)?;
+    if manifest.has_enabled_contract_deployments() {
+        let contract_deployments = &manifest.contract_deployments;
+        let settings = TraceEventsSettings {
+            abi_str: CONTRACT_CREATED_ABI,
+            trace_information: TraceInformation::create_contract_deployments(
+                contract_deployments.clone(),
+                network_providers,
+            )?,
+            streams: contract_deployments.streams.clone(),
+            chat: contract_deployments.chat.clone(),
+            generate_csv: contract_deployments.generate_csv,
+        };
+        events.extend(
+            process_trace_contract_events(project_path, manifest, postgres, settings).await?,
+        );
+    }
+
+    Ok(events)
+}
+
+/// What differs between the trace sources of the imaginary `EvmTraces` contract.
+struct TraceEventsSettings {
+    abi_str: &'static str,
+    trace_information: TraceInformation,
+    streams: Option<StreamsConfig>,
+    chat: Option<ChatConfig>,
+    generate_csv: Option<bool>,
+}
+
+async fn process_trace_contract_events(
+    project_path: &Path,
+    manifest: &Manifest,
+    postgres: Option<Arc<PostgresClient>>,
+    settings: TraceEventsSettings,
+) -> Result<Vec<TraceCallbackRegistryInformation>, ProcessIndexersError> {
+    let mut events: Vec<TraceCallbackRegistryInformation> = vec![];
+
+    let abi: JsonAbi = serde_json::from_str(settings.abi_str)?;
 
     #[allow(clippy::useless_conversion)]
-    let abi_items: Vec<ABIItem> = serde_json::from_str(abi_str)?;
+    let abi_items: Vec<ABIItem> = serde_json::from_str(settings.abi_str)?;
     let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
 
-    let contract = &manifest.native_transfers;
     let contract_name = NATIVE_TRANSFER_CONTRACT_NAME.to_string();
 
     for event_info in event_names {
@@ -753,11 +1045,10 @@ pub async fn process_trace_events(
             })?
             .clone();
 
-        let trace_information =
-            TraceInformation::create(manifest.native_transfers.clone(), network_providers)?;
+        let trace_information = settings.trace_information.clone();
 
         let mut csv: Option<Arc<AsyncCsvAppender>> = None;
-        if contract.generate_csv.unwrap_or(true) && manifest.storage.csv_enabled() {
+        if settings.generate_csv.unwrap_or(true) && manifest.storage.csv_enabled() {
             let csv_path =
                 manifest.storage.csv.as_ref().map_or(PathBuf::from("generated_csv"), |c| {
                     PathBuf::from(c.path.strip_prefix("./").unwrap())
@@ -775,18 +1066,17 @@ pub async fn process_trace_events(
             csv = Some(Arc::new(csv_appender));
         }
 
-        let postgres_column_names =
-            generate_column_names_only_with_base_properties(&event_info.inputs);
+        let postgres_column_names = no_code_column_names(&event_info, false);
         let postgres_event_table_name =
             generate_event_table_full_name(&manifest.name, &contract_name, &event_info.name);
 
-        let streams_client = if let Some(streams) = &contract.streams {
+        let streams_client = if let Some(streams) = &settings.streams {
             Some(StreamsClients::new(streams.clone()).await)
         } else {
             None
         };
 
-        let chat_clients = if let Some(chats) = &contract.chat {
+        let chat_clients = if let Some(chats) = &settings.chat {
             Some(ChatClients::new(chats.clone()).await)
         } else {
             None
@@ -798,10 +1088,16 @@ pub async fn process_trace_events(
             contract_name: contract_name.clone(),
             event: event.clone(),
             index_event_in_order: false,
+            include_tx_context: false,
             csv,
             postgres: postgres.clone(),
             postgres_event_table_name,
             postgres_column_names,
+            postgres_checkpoint_table_name: postgres_checkpoint_table_name(
+                manifest,
+                &contract_name,
+                &event_info.name,
+            ),
             streams_clients: Arc::new(streams_client),
             chat_clients: Arc::new(chat_clients),
         });
@@ -1,4 +1,7 @@
-use alloy::primitives::{B256, U64};
+use alloy::{
+    primitives::{B256, U64},
+    rpc::types::Log,
+};
 
 use futures::future::join_all;
 use futures::StreamExt;
@@ -14,14 +17,19 @@ use tracing::{debug, error, info};
 use crate::helpers::is_relevant_block;
 use crate::{
     event::{
-        callback_registry::EventResult, config::EventProcessingConfig, BuildRindexerFilterError,
-        RindexerEventFilter,
+        callback_registry::{CallbackOutcome, EventResult},
+        config::EventProcessingConfig,
+        BuildRindexerFilterError, RindexerEventFilter,
     },
     indexer::{
+        backfill::BackfillProgress,
         dependency::{ContractEventsDependenciesConfig, EventDependencies},
-        fetch_logs::{fetch_logs_stream, FetchLogsResult},
+        fetch_logs::{
+            event_logs_result, fetch_contract_logs_stream, fetch_logs_stream, FetchLogsResult,
+        },
         last_synced::update_progress_and_last_synced_task,
         progress::IndexingEventProgressStatus,
+        reorg::{live_safe_block_number, rollback_event_to_block},
         task_tracker::{indexing_event_processed, indexing_event_processing},
     },
     is_running,
@@ -51,6 +59,66 @@ pub async fn process_event(
     Ok(())
 }
 
+/// Processes the events of a contract with `logs_fetch_mode: per_contract` from one logs stream,
+/// every event still gets its own callbacks and keeps its own last synced block.
+pub async fn process_contract_events(
+    configs: Vec<Arc<EventProcessingConfig>>,
+) -> Result<(), ProcessEventError> {
+    let filters =
+        configs.iter().map(|config| config.to_event_filter()).collect::<Result<Vec<_>, _>>()?;
+    let callback_permits = configs
+        .iter()
+        .map(|config| Arc::new(Semaphore::new(callback_concurrency(config))))
+        .collect::<Vec<_>>();
+
+    debug!("{} - Processing events of the contract", configs[0].contract_name());
+
+    let mut logs_stream = fetch_contract_logs_stream(&configs, &filters, false);
+    let mut tasks = Vec::new();
+
+    while let Some(result) = logs_stream.next().await {
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                error!(
+                    "[{}] - {} - {} - Error fetching logs: {}",
+                    configs[0].network_contract().network,
+                    configs[0].contract_name(),
+                    IndexingEventProgressStatus::Live.log(),
+                    e
+                );
+                return Err(ProcessEventError::ProcessLogs(Box::new(ProviderError::CustomError(
+                    e.to_string(),
+                ))));
+            }
+        };
+
+        for ((config, filter), callback_permits) in
+            configs.iter().zip(filters.iter()).zip(callback_permits.iter())
+        {
+            // the stream runs to the latest end block of the events, the others stop at their own
+            let end_block = (!config.live_indexing()).then(|| filter.to_block());
+            let Some(event_result) = event_logs_result(&result, filter, end_block) else {
+                continue;
+            };
+
+            let task =
+                handle_logs_result(Arc::clone(config), callback_permits.clone(), Ok(event_result))
+                    .await
+                    .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+            tasks.push(task);
+        }
+    }
+
+    if !tasks.is_empty() {
+        futures::future::try_join_all(tasks)
+            .await
+            .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+    }
+
+    Ok(())
+}
+
 /// note block_until_indexed:
 /// Whether to wait for all indexing tasks to complete for an event before returning
 //  (needed for dependency indexing)
@@ -59,18 +127,7 @@ async fn process_event_logs(
     force_no_live_indexing: bool,
     block_until_indexed: bool,
 ) -> Result<(), Box<ProviderError>> {
-    // The concurrency with which we can call the trigger. If the indexer is running in-order
-    // we can only call one at a time, otherwise we can call multiple in parallel based on what is
-    // best for the application.
-    //
-    // We default to `2`, but the user will ideally override this based on the logic in the handler.
-    let callback_concurrency = if config.index_event_in_order() {
-        1usize
-    } else {
-        config.config().callback_concurrency.unwrap_or(2)
-    };
-
-    let callback_permits = Arc::new(Semaphore::new(callback_concurrency));
+    let callback_permits = Arc::new(Semaphore::new(callback_concurrency(&config)));
 
     let mut logs_stream = fetch_logs_stream(Arc::clone(&config), force_no_live_indexing);
     let mut tasks = Vec::new();
@@ -97,6 +154,19 @@ async fn process_event_logs(
     Ok(())
 }
 
+/// The concurrency with which we can call the trigger. If the indexer is running in-order
+/// we can only call one at a time, otherwise we can call multiple in parallel based on what is
+/// best for the application.
+///
+/// We default to `2`, but the user will ideally override this based on the logic in the handler.
+fn callback_concurrency(config: &EventProcessingConfig) -> usize {
+    if config.index_event_in_order() {
+        1usize
+    } else {
+        config.config().callback_concurrency.unwrap_or(2)
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ProcessContractsEventsWithDependenciesError {
     #[error("{0}")]
@@ -340,8 +410,26 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                 latest_block_number,
                                 ordering_live_indexing_details.last_seen_block_number
                             );
-                            let reorg_safe_distance = &config.indexing_distance_from_head();
-                            let safe_block_number = latest_block_number - reorg_safe_distance;
+                            let safe_block_number = match live_safe_block_number(
+                                &config.network_contract().cached_provider,
+                                latest_block_number,
+                                config.reorg_safe_distance(),
+                                config.indexing_distance_from_head(),
+                            )
+                            .await
+                            {
+                                Ok(safe_block_number) => safe_block_number,
+                                Err(e) => {
+                                    error!(
+                                        "{}::{} - {} - Error getting safe block number - err: {}",
+                                        &config.info_log_name(),
+                                        &config.network_contract().network,
+                                        IndexingEventProgressStatus::Live.log(),
+                                        e
+                                    );
+                                    continue;
+                                }
+                            };
                             let from_block = ordering_live_indexing_details.filter.from_block();
                             // check reorg distance and skip if not safe
                             if from_block > safe_block_number {
@@ -359,12 +447,13 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                             let to_block = safe_block_number;
                             if from_block == to_block
                                 && !config.network_contract().disable_logs_bloom_checks
+                                && ordering_live_indexing_details.filter.function_call().is_none()
                                 && !is_relevant_block(
                                     &ordering_live_indexing_details
                                         .filter
                                         .contract_addresses()
                                         .await,
-                                    &config.topic_id(),
+                                    &[config.topic_id()],
                                     latest_block,
                                 )
                             {
@@ -440,8 +529,13 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                     // clone here over the full logs way less overhead
                                     let last_log = logs.last().cloned();
 
-                                    let fetched_logs =
-                                        Ok(FetchLogsResult { logs, from_block, to_block });
+                                    let fetched_logs = Ok(FetchLogsResult {
+                                        logs,
+                                        from_block,
+                                        to_block,
+                                        reorg: None,
+                                        backfill: None,
+                                    });
 
                                     let result = handle_logs_result(
                                         Arc::clone(config),
@@ -548,7 +642,9 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
 async fn trigger_event(
     config: Arc<EventProcessingConfig>,
     fn_data: Vec<EventResult>,
+    from_block: U64,
     to_block: U64,
+    backfill: Option<Arc<BackfillProgress>>,
 ) {
     if config.is_factory_event() {
         indexing_event_processing();
@@ -560,12 +656,158 @@ async fn trigger_event(
     }
 
     indexing_event_processing();
-    if !fn_data.is_empty() {
-        config.trigger_event(fn_data).await;
+    if !fn_data.is_empty() && config.trigger_event(fn_data).await == CallbackOutcome::Stopped {
+        // the batch was not processed, it has to be indexed again on the next start
+        indexing_event_processed();
+        return;
     }
     // TODO: There is a double-index race condition here. If we get a crash or failure between
     //       triggering the event and syncing the last updated block, we may double index.
-    update_progress_and_last_synced_task(config, to_block, indexing_event_processed).await;
+    match backfill {
+        Some(backfill) => {
+            backfill.checkpoint(config, from_block, to_block, indexing_event_processed).await
+        }
+        None => {
+            update_progress_and_last_synced_task(config, to_block, indexing_event_processed).await
+        }
+    }
+}
+
+/// Fill in `block_timestamp` on logs the node returned without one, for network contracts that
+/// opted in with `include_block_timestamp`. Keeps retrying as the logs can not be indexed
+/// without it.
+async fn with_block_timestamps(config: &EventProcessingConfig, mut logs: Vec<Log>) -> Vec<Log> {
+    if !config.network_contract().include_block_timestamp {
+        return logs;
+    }
+
+    let blocks = logs
+        .iter()
+        .filter(|log| log.block_timestamp.is_none())
+        .filter_map(|log| Some((U64::from(log.block_number?), log.block_hash?)))
+        .collect::<Vec<_>>();
+
+    if blocks.is_empty() {
+        return logs;
+    }
+
+    loop {
+        match config.network_contract().cached_provider.get_block_timestamps(&blocks).await {
+            Ok(timestamps) => {
+                for log in logs.iter_mut().filter(|log| log.block_timestamp.is_none()) {
+                    log.block_timestamp =
+                        log.block_hash.and_then(|hash| timestamps.get(&hash).copied());
+                }
+
+                return logs;
+            }
+            Err(e) => {
+                error!(
+                    "{}::{} - Error fetching block timestamps, will try again in 1 seconds - err: {}",
+                    config.info_log_name(),
+                    config.network_contract().network,
+                    e
+                );
+
+                if !is_running() {
+                    return logs;
+                }
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Attach the `TxContext` of their transaction to the events of network contracts that opted in
+/// with `include_tx_context`. Every transaction of the block range is fetched once however many
+/// events it emitted, keeps retrying as the events can not be indexed without it.
+async fn with_tx_contexts(
+    config: &EventProcessingConfig,
+    mut results: Vec<EventResult>,
+) -> Vec<EventResult> {
+    // after this many failed attempts the events go through without a tx context rather than
+    // stalling the indexer on an rpc which can not serve them
+    const TX_CONTEXT_MAX_ATTEMPTS: u32 = 5;
+
+    if !config.network_contract().include_tx_context || results.is_empty() {
+        return results;
+    }
+
+    let mut hashes =
+        results.iter().map(|result| result.tx_information.transaction_hash).collect::<Vec<_>>();
+    hashes.sort();
+    hashes.dedup();
+
+    for attempt in 1..=TX_CONTEXT_MAX_ATTEMPTS {
+        match config.network_contract().cached_provider.get_tx_contexts(&hashes).await {
+            Ok(contexts) => {
+                for result in results.iter_mut() {
+                    result.tx_information.tx_context =
+                        contexts.get(&result.tx_information.transaction_hash).cloned();
+                }
+
+                return results;
+            }
+            Err(e) => {
+                error!(
+                    "{}::{} - Error fetching tx receipts, attempt {}/{} - err: {}",
+                    config.info_log_name(),
+                    config.network_contract().network,
+                    attempt,
+                    TX_CONTEXT_MAX_ATTEMPTS,
+                    e
+                );
+
+                if !is_running() {
+                    return results;
+                }
+                if attempt == TX_CONTEXT_MAX_ATTEMPTS {
+                    break;
+                }
+
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    error!(
+        "{}::{} - Indexing {} events without their tx context",
+        config.info_log_name(),
+        config.network_contract().network,
+        results.len()
+    );
+    results
+}
+
+fn to_event_results(
+    config: &EventProcessingConfig,
+    logs: Vec<Log>,
+    from_block: U64,
+    to_block: U64,
+) -> Vec<EventResult> {
+    logs.into_iter()
+        .map(|log| {
+            EventResult::new(Arc::clone(&config.network_contract()), log, from_block, to_block)
+        })
+        .collect()
+}
+
+/// Hand the removed logs to the callback, every result carries `log.removed` so the handler can
+/// undo what it did for them.
+async fn retract_event(
+    config: &EventProcessingConfig,
+    removed_data: Vec<EventResult>,
+) -> CallbackOutcome {
+    if removed_data.is_empty() {
+        return CallbackOutcome::Processed;
+    }
+
+    indexing_event_processing();
+    let outcome = config.retract_event(removed_data).await;
+    indexing_event_processed();
+
+    outcome
 }
 
 async fn handle_logs_result(
@@ -575,30 +817,75 @@ async fn handle_logs_result(
 ) -> Result<JoinHandle<()>, Box<dyn std::error::Error + Send>> {
     match result {
         Ok(result) => {
+            if let Some(reorg) = result.reorg {
+                // Wait for every in-flight callback of this event to finish, so nothing from the
+                // orphaned branch can be written after the rollback has happened.
+                let permits = callback_permits
+                    .acquire_many_owned(callback_concurrency(&config) as u32)
+                    .await
+                    .ok();
+                rollback_event_to_block(&config, reorg.fork_block).await;
+                if let Some(backfill) = &result.backfill {
+                    backfill.rewind(&config, reorg.fork_block).await;
+                }
+
+                let removed_data = to_event_results(
+                    &config,
+                    reorg.removed_logs,
+                    result.from_block,
+                    result.to_block,
+                );
+                retract_event(&config, removed_data).await;
+                drop(permits);
+
+                return Ok(tokio::spawn(async {}));
+            }
+
             debug!("Processing logs {} - length {}", config.event_name(), result.logs.len());
 
-            let fn_data = result
-                .logs
-                .into_iter()
-                .map(|log| {
-                    EventResult::new(
-                        Arc::clone(&config.network_contract()),
-                        log,
-                        result.from_block,
-                        result.to_block,
-                    )
-                })
-                .collect::<Vec<_>>();
+            // logs flagged as removed by the node are no longer canonical, they are handed to the
+            // callback in their own batch so they can be retracted rather than indexed
+            let (removed_logs, logs): (Vec<_>, Vec<_>) =
+                result.logs.into_iter().partition(|log| log.removed);
+            let removed_data =
+                to_event_results(&config, removed_logs, result.from_block, result.to_block);
+            let logs = with_block_timestamps(&config, logs).await;
+            let fn_data = with_tx_contexts(
+                &config,
+                to_event_results(&config, logs, result.from_block, result.to_block),
+            )
+            .await;
 
             if let Ok(permit) = callback_permits.clone().acquire_owned().await {
                 let task = tokio::spawn(async move {
-                    trigger_event(config, fn_data, result.to_block).await;
+                    if retract_event(&config, removed_data).await == CallbackOutcome::Stopped {
+                        // the batch is indexed again on the next start
+                        drop(permit);
+                        return;
+                    }
+                    trigger_event(
+                        config,
+                        fn_data,
+                        result.from_block,
+                        result.to_block,
+                        result.backfill,
+                    )
+                    .await;
                     drop(permit)
                 });
 
                 Ok(task)
             } else {
-                trigger_event(config, fn_data, result.to_block).await;
+                if retract_event(&config, removed_data).await != CallbackOutcome::Stopped {
+                    trigger_event(
+                        config,
+                        fn_data,
+                        result.from_block,
+                        result.to_block,
+                        result.backfill,
+                    )
+                    .await;
+                }
                 Ok(tokio::spawn(async {}))
             }
         }
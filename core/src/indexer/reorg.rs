@@ -1,14 +1,338 @@
-use alloy::primitives::{U256, U64};
-use tracing::{debug, warn};
+use std::{collections::BTreeMap, path::Path};
 
-use crate::notifications::ChainStateNotification;
+use alloy::{
+    eips::BlockNumberOrTag,
+    network::AnyRpcBlock,
+    primitives::{B256, U256, U64},
+    rpc::types::Log,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    database::postgres::{
+        blocks::{delete_block_headers_above, load_block_hashes},
+        client::PostgresClient,
+        generate::generate_event_table_full_name,
+    },
+    event::config::EventProcessingConfig,
+    helpers::get_full_path,
+    indexer::last_synced::rewind_last_synced_block,
+    manifest::network::{ChainProfile, ReorgSafeDistance},
+    notifications::ChainStateNotification,
+    provider::{JsonRpcCachedProvider, ProviderError},
+    simple_file_formatters::csv::remove_records_after_block,
+    EthereumSqlTypeWrapper,
+};
+
+/// How many recent block hashes the live indexer remembers to detect reorgs on RPC networks.
+pub const REORG_TRACKING_DEPTH: u64 = 256;
+
+/// A rollback request emitted by the live indexer when the blocks it already indexed are no
+/// longer part of the canonical chain.
+#[derive(Debug, Clone)]
+pub struct ReorgRollback {
+    /// The highest block which is still canonical, anything above it must be rolled back.
+    pub fork_block: U64,
+    /// The logs already handed out above the fork block, flagged as `removed` so they can be
+    /// retracted downstream.
+    pub removed_logs: Vec<Log>,
+}
+
+/// Keeps the logs the live indexer handed out for the tracked reorg depth, so they can be
+/// retracted when their blocks are reorged out.
+#[derive(Debug, Default)]
+pub struct RecentLogs {
+    logs: Vec<Log>,
+}
+
+impl RecentLogs {
+    /// Remember the logs sent for a block range and forget anything deeper than
+    /// [`REORG_TRACKING_DEPTH`] below `head`.
+    pub fn record(&mut self, logs: &[Log], head: U64) {
+        let head = head.to::<u64>();
+        self.logs.extend(logs.iter().filter(|log| !log.removed).cloned());
+        self.logs.retain(|log| {
+            log.block_number.is_some_and(|number| number + REORG_TRACKING_DEPTH > head)
+        });
+    }
+
+    /// Take every remembered log above the fork block, flagged as removed.
+    pub fn take_removed(&mut self, fork_block: U64) -> Vec<Log> {
+        let fork_block = fork_block.to::<u64>();
+        let (removed, kept): (Vec<Log>, Vec<Log>) = std::mem::take(&mut self.logs)
+            .into_iter()
+            .partition(|log| log.block_number.is_some_and(|number| number > fork_block));
+        self.logs = kept;
+
+        removed.into_iter().map(|log| Log { removed: true, ..log }).collect()
+    }
+}
+
+/// Remembers the hashes of recently seen canonical blocks so a parent hash mismatch can be
+/// noticed when the chain reorganises.
+#[derive(Debug)]
+pub struct BlockHashTracker {
+    hashes: BTreeMap<u64, B256>,
+    depth: u64,
+}
+
+impl BlockHashTracker {
+    pub fn new(depth: u64) -> Self {
+        Self { hashes: BTreeMap::new(), depth }
+    }
+
+    pub fn get(&self, number: u64) -> Option<B256> {
+        self.hashes.get(&number).copied()
+    }
+
+    pub fn highest(&self) -> Option<u64> {
+        self.hashes.keys().next_back().copied()
+    }
+
+    pub fn lowest(&self) -> Option<u64> {
+        self.hashes.keys().next().copied()
+    }
+
+    /// Record a block, returns `false` without recording it if the block does not link to the
+    /// chain we have seen so far.
+    pub fn insert(&mut self, number: u64, hash: B256, parent_hash: B256) -> bool {
+        if let Some(known) = self.get(number) {
+            if known != hash {
+                return false;
+            }
+        }
+
+        if let Some(parent) = number.checked_sub(1).and_then(|n| self.get(n)) {
+            if parent != parent_hash {
+                return false;
+            }
+        }
+
+        self.hashes.insert(number, hash);
+
+        if let Some(lowest_to_keep) = number.checked_sub(self.depth) {
+            self.hashes = self.hashes.split_off(&lowest_to_keep);
+        }
+
+        true
+    }
+
+    /// Forget every block above `number`.
+    pub fn truncate_above(&mut self, number: u64) {
+        self.hashes.retain(|n, _| *n <= number);
+    }
+
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+}
+
+/// Fills the tracker with the last blocks of `rindexer_internal.blocks` up to `to_block`, blocks
+/// which do not link to the ones before them are left out.
+pub async fn seed_block_hash_tracker(
+    tracker: &mut BlockHashTracker,
+    database: &PostgresClient,
+    network: &str,
+    to_block: U64,
+) {
+    match load_block_hashes(database, network, to_block, tracker.depth).await {
+        Ok(blocks) => {
+            let count = blocks.len();
+            for (number, hash, parent_hash) in blocks {
+                tracker.insert(number, hash, parent_hash);
+            }
+            debug!("{} - Loaded {} stored block hashes for reorg detection", network, count);
+        }
+        Err(e) => {
+            error!("{} - Error loading stored block hashes for reorg detection: {}", network, e)
+        }
+    }
+}
+
+/// Checks the latest block against the tracked chain and returns the fork block if the chain
+/// we have seen has been reorganised.
+///
+/// Any gap between the highest tracked block and the latest block is filled with a batched header
+/// fetch so a reorg can not slip through between two polls.
+pub async fn detect_reorg(
+    cached_provider: &JsonRpcCachedProvider,
+    tracker: &mut BlockHashTracker,
+    latest_block: &AnyRpcBlock,
+) -> Result<Option<U64>, ProviderError> {
+    let number = latest_block.header.number;
+    let hash = latest_block.header.hash;
+
+    if tracker.get(number) == Some(hash) {
+        return Ok(None);
+    }
+
+    let mut blocks: Vec<(u64, B256, B256)> = Vec::new();
+
+    if let Some(highest) = tracker.highest() {
+        if number > highest + 1 {
+            let gap_start = std::cmp::max(highest + 1, number.saturating_sub(tracker.depth));
+            let missing = (gap_start..number).map(U64::from).collect::<Vec<_>>();
+            let mut gap = cached_provider.get_block_by_number_batch(&missing, false).await?;
+            gap.sort_by_key(|b| b.header.number);
+            blocks
+                .extend(gap.iter().map(|b| (b.header.number, b.header.hash, b.header.parent_hash)));
+        }
+    }
+
+    blocks.push((number, hash, latest_block.header.parent_hash));
+
+    for (number, hash, parent_hash) in blocks {
+        if !tracker.insert(number, hash, parent_hash) {
+            return find_fork_block(cached_provider, tracker).await.map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Walks the tracked blocks from the highest down and compares them with the canonical chain,
+/// the first block which still matches is the fork point.
+async fn find_fork_block(
+    cached_provider: &JsonRpcCachedProvider,
+    tracker: &mut BlockHashTracker,
+) -> Result<U64, ProviderError> {
+    let (Some(lowest), Some(highest)) = (tracker.lowest(), tracker.highest()) else {
+        return Ok(U64::ZERO);
+    };
+
+    let numbers = (lowest..=highest).map(U64::from).collect::<Vec<_>>();
+    let mut canonical = cached_provider.get_block_by_number_batch(&numbers, false).await?;
+    canonical.sort_by_key(|b| b.header.number);
+
+    let fork_block = canonical
+        .iter()
+        .rev()
+        .find(|b| tracker.get(b.header.number) == Some(b.header.hash))
+        .map(|b| b.header.number);
+
+    match fork_block {
+        Some(fork_block) => {
+            tracker.truncate_above(fork_block);
+            for block in canonical.iter().filter(|b| b.header.number > fork_block) {
+                tracker.insert(block.header.number, block.header.hash, block.header.parent_hash);
+            }
+
+            Ok(U64::from(fork_block))
+        }
+        None => {
+            warn!(
+                "Reorg is deeper than the {} tracked blocks, rolling back to block {}",
+                tracker.depth,
+                lowest.saturating_sub(1)
+            );
+            tracker.clear();
+            for block in canonical.iter() {
+                tracker.insert(block.header.number, block.header.hash, block.header.parent_hash);
+            }
+
+            Ok(U64::from(lowest.saturating_sub(1)))
+        }
+    }
+}
+
+/// Removes everything indexed above `fork_block` for an event so the canonical branch can be
+/// indexed again. This covers the generated postgres table, the csv file, the stored block
+/// headers and the last synced checkpoints.
+pub async fn rollback_event_to_block(config: &EventProcessingConfig, fork_block: U64) {
+    // factory events only feed the known deployed addresses, they have no rows to roll back
+    if config.is_factory_event() {
+        return;
+    }
+
+    let network = config.network_contract().network.clone();
+
+    warn!(
+        "{}::{} - Rolling back indexed data above block {}",
+        config.info_log_name(),
+        network,
+        fork_block
+    );
+
+    if let Some(database) = config.database() {
+        let table_name = generate_event_table_full_name(
+            &config.indexer_name(),
+            &config.contract_name(),
+            &config.event_name(),
+        );
+        let query = format!("DELETE FROM {table_name} WHERE network = $1 AND block_number > $2");
+
+        match database.execute(&query, &[&network, &EthereumSqlTypeWrapper::U64(fork_block)]).await
+        {
+            Ok(removed) => info!(
+                "{}::{} - Removed {} rows above block {} from {}",
+                config.info_log_name(),
+                network,
+                removed,
+                fork_block,
+                table_name
+            ),
+            Err(e) => error!(
+                "{}::{} - Error removing reorged rows from {}: {:?}",
+                config.info_log_name(),
+                network,
+                table_name,
+                e
+            ),
+        }
+
+        if config.network_contract().cached_provider.stores_block_headers() {
+            if let Err(e) = delete_block_headers_above(&database, &network, fork_block).await {
+                error!(
+                    "{}::{} - Error removing reorged block headers: {:?}",
+                    config.info_log_name(),
+                    network,
+                    e
+                );
+            }
+        }
+    }
+
+    if let Some(csv_details) = config.csv_details() {
+        let project_path = config.project_path();
+        match get_full_path(&project_path, &csv_details.path) {
+            Ok(full_path) => {
+                let file_path = build_event_csv_file_path(
+                    &full_path,
+                    &config.contract_name(),
+                    &config.event_name(),
+                );
+
+                if let Err(e) = remove_records_after_block(&file_path, &network, fork_block).await {
+                    error!(
+                        "{}::{} - Error removing reorged rows from csv {}: {:?}",
+                        config.info_log_name(),
+                        network,
+                        file_path,
+                        e
+                    );
+                }
+            }
+            Err(e) => error!("Failed to get full csv path {}: {:?}", csv_details.path, e),
+        }
+    }
+
+    rewind_last_synced_block(config, fork_block).await;
+}
+
+fn build_event_csv_file_path(full_path: &Path, contract_name: &str, event_name: &str) -> String {
+    let file_name = format!("{contract_name}-{event_name}.csv").to_lowercase();
+    full_path.join(contract_name).join(file_name).display().to_string()
+}
 
 /// Handles chain state notifications (reorgs, reverts, commits)
+///
+/// Returns the fork block when indexed blocks above it are no longer canonical and have to be
+/// rolled back.
 pub fn handle_chain_notification(
     notification: ChainStateNotification,
     info_log_name: &str,
     network: &str,
-) {
+) -> Option<U64> {
     match notification {
         ChainStateNotification::Reorged {
             revert_from_block,
@@ -25,38 +349,156 @@ pub fn handle_chain_notification(
                 new_from_block, new_to_block,
                 new_tip_hash
             );
-            // TODO: In future PR, actually handle the reorg by reverting and re-indexing
+
+            Some(U64::from(revert_from_block.saturating_sub(1)))
         }
         ChainStateNotification::Reverted { from_block, to_block } => {
             warn!(
                 "{}::{} - CHAIN REVERTED! Blocks {} to {} have been reverted",
                 info_log_name, network, from_block, to_block
             );
-            // TODO: In future PR, mark affected logs as removed in the database
+
+            Some(U64::from(from_block.saturating_sub(1)))
         }
         ChainStateNotification::Committed { from_block, to_block, tip_hash } => {
             debug!(
                 "{}::{} - Chain committed: blocks {} to {} (tip: {})",
                 info_log_name, network, from_block, to_block, tip_hash
             );
+
+            None
         }
     }
 }
 
+/// The reorg depth of the bundled profile of the chain, networks with a `chain_profile` can
+/// override it so prefer [`JsonRpcCachedProvider::chain_profile`] when there is a provider.
+#[allow(dead_code)]
 pub fn reorg_safe_distance_for_chain(chain_id: &U256) -> U64 {
-    if chain_id == &U256::from(1) {
-        U64::from(12)
-    } else {
-        U64::from(64)
+    ChainProfile::for_chain(chain_id.to::<u64>(), None).reorg_depth()
+}
+
+/// The highest block which is safe to index on a network. This follows the `reorg_safe_distance`
+/// configured on the network, or falls back to the reorg depth of the network's chain profile.
+/// Nodes which have no `safe` or `finalized` block yet also fall back to the reorg depth.
+pub async fn safe_block_number(
+    cached_provider: &JsonRpcCachedProvider,
+    latest_block: U64,
+) -> Result<U64, ProviderError> {
+    let profile_safe_block_number =
+        || latest_block.saturating_sub(cached_provider.chain_profile().reorg_depth());
+    let tag = match cached_provider.reorg_safe_distance() {
+        Some(ReorgSafeDistance::Safe) => BlockNumberOrTag::Safe,
+        Some(ReorgSafeDistance::Finalized) => BlockNumberOrTag::Finalized,
+        Some(ReorgSafeDistance::Blocks(blocks)) => {
+            return Ok(latest_block.saturating_sub(U64::from(blocks)));
+        }
+        None => return Ok(profile_safe_block_number()),
+    };
+
+    let safe_block_number = match cached_provider.get_block_number_by_tag(tag).await? {
+        Some(block_number) => block_number,
+        None => {
+            let fallback = profile_safe_block_number();
+            warn!(
+                "Node has no {} block yet, using block {} ({} behind the head) instead",
+                tag,
+                fallback,
+                cached_provider.chain_profile().reorg_depth()
+            );
+            fallback
+        }
+    };
+
+    Ok(safe_block_number.min(latest_block))
+}
+
+/// The highest block live indexing can process. The `safe` and `finalized` tags do not move in
+/// step with the head so they are resolved on every poll, fixed distances are worked out once on
+/// startup and passed in as `indexing_distance_from_head`.
+pub async fn live_safe_block_number(
+    cached_provider: &JsonRpcCachedProvider,
+    latest_block: U64,
+    reorg_safe_distance: bool,
+    indexing_distance_from_head: U64,
+) -> Result<U64, ProviderError> {
+    match cached_provider.reorg_safe_distance() {
+        Some(ReorgSafeDistance::Safe | ReorgSafeDistance::Finalized) if reorg_safe_distance => {
+            safe_block_number(cached_provider, latest_block).await
+        }
+        _ => Ok(latest_block.saturating_sub(indexing_distance_from_head)),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use alloy::primitives::U256;
-
     use super::*;
 
+    #[test]
+    fn test_block_hash_tracker_detects_parent_mismatch() {
+        let mut tracker = BlockHashTracker::new(3);
+
+        assert!(tracker.insert(10, B256::from([10u8; 32]), B256::from([9u8; 32])));
+        assert!(tracker.insert(11, B256::from([11u8; 32]), B256::from([10u8; 32])));
+
+        // block 12 builds on a different block 11
+        assert!(!tracker.insert(12, B256::from([12u8; 32]), B256::from([1u8; 32])));
+        // a different block at an already seen height
+        assert!(!tracker.insert(11, B256::from([2u8; 32]), B256::from([10u8; 32])));
+
+        assert!(tracker.insert(12, B256::from([12u8; 32]), B256::from([11u8; 32])));
+        assert!(tracker.insert(13, B256::from([13u8; 32]), B256::from([12u8; 32])));
+        assert_eq!(tracker.lowest(), Some(10));
+        assert!(tracker.insert(14, B256::from([14u8; 32]), B256::from([13u8; 32])));
+        assert_eq!(tracker.lowest(), Some(11));
+
+        tracker.truncate_above(12);
+        assert_eq!(tracker.highest(), Some(12));
+    }
+
+    #[test]
+    fn test_recent_logs_take_removed_above_fork_block() {
+        let log_at = |block_number: u64| Log { block_number: Some(block_number), ..Log::default() };
+
+        let mut recent_logs = RecentLogs::default();
+        recent_logs.record(&[log_at(10), log_at(11)], U64::from(11));
+        recent_logs.record(&[log_at(12), log_at(14)], U64::from(14));
+
+        let removed = recent_logs.take_removed(U64::from(11));
+        assert_eq!(
+            removed.iter().map(|log| log.block_number.unwrap()).collect::<Vec<_>>(),
+            vec![12, 14]
+        );
+        assert!(removed.iter().all(|log| log.removed));
+
+        // logs outside of the tracked depth are forgotten
+        recent_logs.record(&[], U64::from(10 + REORG_TRACKING_DEPTH));
+        let remaining = recent_logs.take_removed(U64::ZERO);
+        assert_eq!(
+            remaining.iter().map(|log| log.block_number).collect::<Vec<_>>(),
+            vec![Some(11)]
+        );
+    }
+
+    #[test]
+    fn test_handle_chain_notification_returns_fork_block() {
+        let reorged = ChainStateNotification::Reorged {
+            revert_from_block: 100,
+            revert_to_block: 105,
+            new_from_block: 100,
+            new_to_block: 106,
+            new_tip_hash: B256::ZERO,
+        };
+        assert_eq!(handle_chain_notification(reorged, "test", "mainnet"), Some(U64::from(99)));
+
+        let reverted = ChainStateNotification::Reverted { from_block: 50, to_block: 60 };
+        assert_eq!(handle_chain_notification(reverted, "test", "mainnet"), Some(U64::from(49)));
+
+        let committed =
+            ChainStateNotification::Committed { from_block: 1, to_block: 2, tip_hash: B256::ZERO };
+        assert_eq!(handle_chain_notification(committed, "test", "mainnet"), None);
+    }
+
     #[test]
     fn test_reorg_safe_distance_for_chain() {
         let mainnet_chain_id = U256::from(1);
@@ -1,4 +1,4 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use alloy::primitives::U64;
 use futures::future::try_join_all;
@@ -6,6 +6,7 @@ use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use tokio::{
     join,
+    sync::mpsc,
     task::{JoinError, JoinHandle},
     time::Instant,
 };
@@ -14,7 +15,7 @@ use tracing::{error, info};
 use crate::event::config::{ContractEventProcessingConfig, FactoryEventProcessingConfig};
 use crate::indexer::native_transfer::native_transfer_block_processor;
 use crate::{
-    database::postgres::client::PostgresConnectionError,
+    database::postgres::{blocks::spawn_block_headers_writer, client::PostgresConnectionError},
     event::{
         callback_registry::{EventCallbackRegistry, TraceCallbackRegistry},
         config::{EventProcessingConfig, TraceProcessingConfig},
@@ -22,16 +23,21 @@ use crate::{
     indexer::{
         dependency::ContractEventsDependenciesConfig,
         last_synced::{get_last_synced_block_number, SyncConfig},
-        native_transfer::{native_transfer_block_fetch, EVENT_NAME, NATIVE_TRANSFER_CONTRACT_NAME},
+        native_transfer::native_transfer_block_fetch,
         process::{
-            process_contracts_events_with_dependencies, process_event,
+            process_contract_events, process_contracts_events_with_dependencies, process_event,
             ProcessContractsEventsWithDependenciesError, ProcessEventError,
         },
         progress::IndexingEventsProgressState,
-        reorg::reorg_safe_distance_for_chain,
+        reorg::safe_block_number,
         ContractEventDependencies,
     },
-    manifest::core::Manifest,
+    learned_block_ranges::LearnedBlockRanges,
+    log_source::{FileLogSource, FileLogSourceError},
+    manifest::{
+        contract::{LogsFetchMode, ParseAbiError},
+        core::Manifest,
+    },
     provider::{JsonRpcCachedProvider, ProviderError},
     PostgresClient,
 };
@@ -75,6 +81,12 @@ pub enum StartIndexingError {
     #[error("The end block set for {0} is higher than the latest block: {1} - end block: {2}")]
     EndBlockIsHigherThanLatestBlockError(String, U64, U64),
 
+    #[error("{0}")]
+    LogsArchive(#[from] FileLogSourceError),
+
+    #[error("Could not read the function of a function call event: {0}")]
+    FunctionCall(#[from] ParseAbiError),
+
     #[error("Encountered unknown error: {0}")]
     UnknownError(String),
 }
@@ -124,22 +136,17 @@ async fn get_start_end_block(
         }
     }
 
-    let last_known_start_block = if manifest_start_block.is_some() {
-        let last_synced_block = get_last_synced_block_number(config).await;
-
-        if let Some(value) = last_synced_block {
-            let start_from = value + U64::from(1);
+    // the stored checkpoint always wins, even for live only contracts without a `start_block`,
+    // so any blocks missed while the indexer was down are caught up before going live
+    let last_known_start_block =
+        get_last_synced_block_number(config).await.map(|last_synced_block| {
+            let start_from = last_synced_block + U64::from(1);
             info!(
                 "{} Found last synced block number - {:?} rindexer will start up from {:?}",
-                event_name, value, start_from
+                event_name, last_synced_block, start_from
             );
-            Some(start_from)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+            start_from
+        });
 
     let start_block =
         last_known_start_block.unwrap_or(manifest_start_block.unwrap_or(latest_block));
@@ -166,8 +173,8 @@ pub async fn start_indexing_traces(
     database: Option<Arc<PostgresClient>>,
     trace_registry: Arc<TraceCallbackRegistry>,
 ) -> Result<Vec<JoinHandle<Result<(), ProcessEventError>>>, StartIndexingError> {
-    if !manifest.native_transfers.enabled {
-        info!("Native transfer indexing disabled!");
+    if !manifest.native_transfers.enabled && !manifest.contract_deployments.enabled {
+        info!("Native transfer and contract deployment indexing disabled!");
         return Ok(vec![]);
     }
 
@@ -214,14 +221,19 @@ pub async fn start_indexing_traces(
                 start_block,
                 end_block,
                 indexer_name: event.indexer_name.clone(),
-                contract_name: NATIVE_TRANSFER_CONTRACT_NAME.to_string(),
-                event_name: EVENT_NAME.to_string(),
+                contract_name: event.contract_name.clone(),
+                event_name: event.event_name.clone(),
+                kind: event.trace_information.kind,
                 network: network_name.to_string(),
                 progress: trace_progress_state.clone(),
                 database: database.clone(),
                 csv_details: None,
                 registry: trace_registry.clone(),
                 method: network.method,
+                deployers: network
+                    .deployers
+                    .as_ref()
+                    .map(|deployers| deployers.iter().copied().collect()),
                 stream_last_synced_block_file_path: None,
             });
 
@@ -231,6 +243,7 @@ pub async fn start_indexing_traces(
                 start_block,
                 network.end_block,
                 indexing_distance_from_head,
+                event.trace_information.reorg_safe_distance,
                 network_name.clone(),
             ));
 
@@ -276,6 +289,12 @@ pub async fn start_indexing_contract_events(
     let mut processed_network_contracts: Vec<ProcessedNetworkContract> = Vec::new();
     let mut dependency_event_processing_configs: Vec<ContractEventsDependenciesConfig> = Vec::new();
 
+    // events of `logs_fetch_mode: per_contract` contracts, keyed by indexer, contract and network
+    let mut per_contract_events: HashMap<
+        (String, String, String),
+        Vec<Arc<EventProcessingConfig>>,
+    > = HashMap::new();
+
     let mut block_tasks = FuturesUnordered::new();
 
     for event in registry.events.iter() {
@@ -358,6 +377,14 @@ pub async fn start_indexing_contract_events(
             processed_up_to: end_block,
         });
 
+        let function_call = match manifest.contracts.iter().find(|c| c.name == event.contract.name)
+        {
+            Some(contract) => {
+                contract.function_call_filter(&project_path, &event.event_name)?.map(Arc::new)
+            }
+            None => None,
+        };
+
         let event_processing_config = ContractEventProcessingConfig {
             id: event.id.clone(),
             project_path: project_path.clone(),
@@ -383,10 +410,14 @@ pub async fn start_indexing_contract_events(
                 network_contract.is_live_indexing()
             },
             index_event_in_order: event.index_event_in_order,
+            reorg_safe_distance: event.contract.reorg_safe_distance,
             indexing_distance_from_head,
+            function_call,
         };
 
-        if let Some(factory_details) = network_contract.indexing_contract_setup.factory_details() {
+        let factory_details = network_contract.indexing_contract_setup.factory_details();
+        // every factory of a nested factory keeps its own known deployed addresses
+        for factory_details in factory_details.iter().flat_map(|details| details.levels()) {
             let factory_event_processing_config = FactoryEventProcessingConfig {
                 address: factory_details.address.clone(),
                 input_name: factory_details.input_name.clone(),
@@ -406,6 +437,7 @@ pub async fn start_indexing_contract_events(
                     .map(|s| s.get_streams_last_synced_block_path()),
                 live_indexing: event_processing_config.live_indexing,
                 index_event_in_order: event.index_event_in_order,
+                reorg_safe_distance: event.contract.reorg_safe_distance,
                 indexing_distance_from_head,
             };
 
@@ -440,12 +472,28 @@ pub async fn start_indexing_contract_events(
                 Arc::new(event_processing_config.into()),
                 &dependencies,
             );
+        } else if event.contract.logs_fetch_mode == LogsFetchMode::PerContract
+            // calls come from call traces, they can not share the logs request of the contract
+            && event_processing_config.function_call.is_none()
+        {
+            per_contract_events
+                .entry((
+                    event.indexer_name.clone(),
+                    event.contract.name.clone(),
+                    network_contract.network.clone(),
+                ))
+                .or_default()
+                .push(Arc::new(event_processing_config.into()));
         } else {
             let process_event = tokio::spawn(process_event(event_processing_config.into(), false));
             non_blocking_process_events.push(process_event);
         }
     }
 
+    for (_, configs) in per_contract_events {
+        non_blocking_process_events.push(tokio::spawn(process_contract_events(configs)));
+    }
+
     Ok((
         non_blocking_process_events,
         processed_network_contracts,
@@ -465,6 +513,13 @@ pub async fn start_indexing(
     let start = Instant::now();
     let database = initialize_database(manifest).await?;
 
+    if let Some(database) = &database {
+        start_block_headers_writers(manifest, database, &registry, &trace_registry);
+    }
+
+    use_learned_block_ranges(project_path, &registry, &trace_registry);
+    use_logs_archives(manifest, project_path, &registry)?;
+
     // any events which are non-blocking and can be fired in parallel
     let mut non_blocking_process_events = Vec::new();
 
@@ -540,6 +595,101 @@ pub async fn start_indexing(
     Ok(processed_network_contracts)
 }
 
+/// Start the rpc endpoints of every network from the block ranges they learned before a restart,
+/// kept in `.rindexer/learned-block-ranges.json` in the project.
+fn use_learned_block_ranges(
+    project_path: &Path,
+    registry: &EventCallbackRegistry,
+    trace_registry: &TraceCallbackRegistry,
+) {
+    let learned_block_ranges = Arc::new(LearnedBlockRanges::load(project_path));
+
+    let providers = registry
+        .events
+        .iter()
+        .flat_map(|event| event.contract.details.iter())
+        .map(|details| (&details.network, &details.cached_provider))
+        .chain(
+            trace_registry
+                .events
+                .iter()
+                .flat_map(|event| event.trace_information.details.iter())
+                .map(|details| (&details.network, &details.cached_provider)),
+        )
+        .collect::<HashMap<_, _>>();
+
+    for (network, provider) in providers {
+        provider.use_learned_block_ranges(&learned_block_ranges, network);
+    }
+}
+
+/// Backfill the networks with a `logs_archive` from the log files in it, the blocks after the
+/// archive are still fetched from rpc.
+fn use_logs_archives(
+    manifest: &Manifest,
+    project_path: &Path,
+    registry: &EventCallbackRegistry,
+) -> Result<(), StartIndexingError> {
+    for network in &manifest.networks {
+        let Some(logs_archive) = &network.logs_archive else {
+            continue;
+        };
+
+        let providers = registry
+            .events
+            .iter()
+            .flat_map(|event| event.contract.details.iter())
+            .filter(|details| details.network == network.name)
+            .map(|details| &details.cached_provider)
+            .collect::<Vec<_>>();
+        if providers.is_empty() {
+            continue;
+        }
+
+        let archive = Arc::new(FileLogSource::open(&project_path.join(logs_archive))?);
+        info!("{} - Backfilling logs from archive {}", network.name, logs_archive);
+
+        for provider in providers {
+            provider.set_logs_archive(Arc::clone(&archive));
+        }
+    }
+
+    Ok(())
+}
+
+/// Hook the providers of networks with `store_block_headers` enabled up to a writer which keeps
+/// `rindexer_internal.blocks` filled with every header they fetch.
+fn start_block_headers_writers(
+    manifest: &Manifest,
+    database: &Arc<PostgresClient>,
+    registry: &EventCallbackRegistry,
+    trace_registry: &TraceCallbackRegistry,
+) {
+    let providers = registry
+        .events
+        .iter()
+        .flat_map(|event| event.contract.details.iter())
+        .map(|details| (&details.network, &details.cached_provider))
+        .chain(
+            trace_registry
+                .events
+                .iter()
+                .flat_map(|event| event.trace_information.details.iter())
+                .map(|details| (&details.network, &details.cached_provider)),
+        )
+        .collect::<Vec<_>>();
+
+    for network in manifest.networks.iter().filter(|n| n.store_block_headers.unwrap_or_default()) {
+        let Some((_, provider)) = providers.iter().find(|(name, _)| **name == network.name) else {
+            continue;
+        };
+
+        let (headers_tx, headers_rx) = mpsc::unbounded_channel();
+        provider.set_block_header_sink(headers_tx);
+        spawn_block_headers_writer(Arc::clone(database), network.name.clone(), headers_rx);
+    }
+}
+
 pub async fn initialize_database(
     manifest: &Manifest,
 ) -> Result<Option<Arc<PostgresClient>>, StartIndexingError> {
@@ -564,14 +714,11 @@ pub async fn calculate_safe_block_number(
 ) -> Result<(U64, U64), StartIndexingError> {
     let mut indexing_distance_from_head = U64::ZERO;
     if reorg_safe_distance {
-        let chain_id =
-            provider.get_chain_id().await.map_err(StartIndexingError::GetChainIdError)?;
-        let reorg_safe_distance = reorg_safe_distance_for_chain(&chain_id);
-        let safe_block_number = latest_block - reorg_safe_distance;
+        let safe_block_number = safe_block_number(provider, latest_block).await?;
         if end_block > safe_block_number {
             end_block = safe_block_number;
         }
-        indexing_distance_from_head = reorg_safe_distance;
+        indexing_distance_from_head = latest_block - safe_block_number;
     }
     Ok((end_block, indexing_distance_from_head))
 }
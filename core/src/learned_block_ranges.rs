@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use alloy::primitives::U64;
+use tracing::{debug, warn};
+
+/// The `eth_getLogs` block range each rpc endpoint told rindexer it allows, keyed by network and
+/// then by endpoint name (its host and a hash of its url), as written to
+/// `.rindexer/learned-block-ranges.json`.
+type BlockRanges = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// The block ranges learned from the errors of the rpc endpoints, kept on disk so a restart
+/// starts from them instead of relearning them with oversized requests.
+#[derive(Debug)]
+pub struct LearnedBlockRanges {
+    path: PathBuf,
+    ranges: Mutex<BlockRanges>,
+}
+
+impl LearnedBlockRanges {
+    pub fn load(project_path: &Path) -> Self {
+        let path = project_path.join(".rindexer").join("learned-block-ranges.json");
+
+        let ranges = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring learned block ranges in {}: {}", path.display(), e);
+                BlockRanges::default()
+            }),
+            Err(_) => BlockRanges::default(),
+        };
+
+        Self { path, ranges: Mutex::new(ranges) }
+    }
+
+    pub fn get(&self, network: &str, endpoint: &str) -> Option<U64> {
+        let ranges = self.ranges.lock().unwrap();
+        ranges.get(network)?.get(endpoint).map(|range| U64::from(*range))
+    }
+
+    /// Stores a block range an endpoint was limited to, the file is only rewritten when the range
+    /// changed which is rare once the endpoints are learned.
+    pub fn record(&self, network: &str, endpoint: &str, max_block_range: U64) {
+        let mut ranges = self.ranges.lock().unwrap();
+        let range = max_block_range.to::<u64>();
+
+        let previous =
+            ranges.entry(network.to_string()).or_default().insert(endpoint.to_string(), range);
+        if previous == Some(range) {
+            return;
+        }
+
+        debug!("Learned max block range {} for {} on {}", range, endpoint, network);
+
+        if let Err(e) = write_block_ranges(&self.path, &ranges) {
+            warn!("Failed to write learned block ranges to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn write_block_ranges(path: &Path, ranges: &BlockRanges) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(ranges)?)?;
+    fs::rename(temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learned_block_ranges_survive_a_restart() {
+        let project_path = std::env::temp_dir()
+            .join(format!("rindexer-learned-block-ranges-{}", std::process::id()));
+
+        let learned = LearnedBlockRanges::load(&project_path);
+        assert_eq!(learned.get("ethereum", "eth.example.com"), None);
+
+        learned.record("ethereum", "eth.example.com", U64::from(2000));
+        learned.record("base", "base.example.com", U64::from(500));
+
+        let restarted = LearnedBlockRanges::load(&project_path);
+        assert_eq!(restarted.get("ethereum", "eth.example.com"), Some(U64::from(2000)));
+        assert_eq!(restarted.get("base", "base.example.com"), Some(U64::from(500)));
+        assert_eq!(restarted.get("base", "eth.example.com"), None);
+
+        fs::remove_dir_all(project_path).unwrap();
+    }
+}
@@ -12,7 +12,10 @@ pub use database::postgres::{
     client::{PostgresClient, ToSql},
     generate::drop_tables_for_indexer_sql,
     setup::setup_postgres,
-    sql_type_wrapper::EthereumSqlTypeWrapper,
+    sql_type_wrapper::{
+        map_block_timestamp_to_ethereum_wrapper, map_tx_context_to_ethereum_wrapper,
+        EthereumSqlTypeWrapper,
+    },
 };
 
 mod simple_file_formatters;
@@ -21,7 +24,7 @@ pub use simple_file_formatters::csv::AsyncCsvAppender;
 mod helpers;
 pub use helpers::{
     format_all_files_for_project, generate_random_id, load_env_from_project_path,
-    public_read_env_value, write_file, WriteFileError,
+    public_read_env_value, public_substitute_env_variables, write_file, WriteFileError,
 };
 mod api;
 pub use api::{generate_graphql_queries, GraphqlOverrideSettings};
@@ -31,14 +34,21 @@ pub use logger::setup_info_logger;
 mod abi;
 pub use abi::ABIItem;
 mod chat;
+mod compute_units;
 pub mod event;
+mod learned_block_ranges;
+pub mod log_source;
 pub mod notifications;
 pub use notifications::ChainStateNotification;
 pub mod phantom;
 pub mod provider;
+mod rpc_cassette;
+pub use rpc_cassette::{RPC_RECORD_ENV, RPC_REPLAY_ENV};
+mod rpc_endpoints;
 mod start;
 mod streams;
 mod types;
+mod ws_subscriptions;
 
 // export 3rd party dependencies
 pub use async_trait::async_trait;
@@ -0,0 +1,572 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::{
+    hex,
+    primitives::{Address, Bytes, LogData, B256, U64},
+    rpc::types::Log,
+};
+use async_trait::async_trait;
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::{Field, Row},
+};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncSeekExt, BufReader},
+    sync::OnceCell,
+};
+
+use crate::{
+    event::RindexerEventFilter,
+    provider::{JsonRpcCachedProvider, ProviderError, RpcEndpointClient},
+};
+
+/// Where the logs of an event are fetched from. Backfills pick the source per request, so a run
+/// can move from rpc to an archive and back, the live tail always reads from rpc.
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    /// The logs matching `filter` between its from and to block, in block and log order.
+    async fn get_logs(&self, filter: &RindexerEventFilter) -> Result<Vec<Log>, ProviderError>;
+
+    /// The last block a single request starting at `from_block` should ask for, sources which
+    /// store logs in chunks keep a request inside one of them.
+    fn limit_to_block(&self, _from_block: U64, to_block: U64) -> U64 {
+        to_block
+    }
+
+    /// Whether requests are kept to the max block range learned for the rpc endpoint.
+    fn follows_endpoint_block_range(&self) -> bool {
+        true
+    }
+}
+
+/// Fetches logs with `eth_getLogs` (or the network's `logs_source`) from a single rpc endpoint,
+/// so the block range it learns from an error is kept against that endpoint. Reth networks are
+/// served by this source too, through the ipc endpoint of their node.
+pub struct RpcLogSource {
+    provider: Arc<JsonRpcCachedProvider>,
+    endpoint: RpcEndpointClient,
+    /// The first block of the network's logs archive, requests before it stop short of it so the
+    /// archived blocks are read from disk.
+    archive_from_block: Option<U64>,
+}
+
+impl RpcLogSource {
+    pub fn new(provider: Arc<JsonRpcCachedProvider>, endpoint: RpcEndpointClient) -> Self {
+        let archive_from_block = provider.logs_archive().map(|archive| archive.from_block());
+        Self { provider, endpoint, archive_from_block }
+    }
+}
+
+#[async_trait]
+impl LogSource for RpcLogSource {
+    async fn get_logs(&self, filter: &RindexerEventFilter) -> Result<Vec<Log>, ProviderError> {
+        self.provider.get_logs_from_endpoint(&self.endpoint, filter).await
+    }
+
+    fn limit_to_block(&self, from_block: U64, to_block: U64) -> U64 {
+        stop_before_archive(self.archive_from_block, from_block, to_block)
+    }
+}
+
+fn stop_before_archive(archive_from_block: Option<U64>, from_block: U64, to_block: U64) -> U64 {
+    match archive_from_block {
+        Some(archive_from_block) if from_block < archive_from_block => {
+            to_block.min(archive_from_block - U64::from(1))
+        }
+        _ => to_block,
+    }
+}
+
+/// Every block of a request is traced on its own, requests for function call events are kept to
+/// this many blocks.
+const CALL_TRACES_MAX_BLOCK_RANGE: u64 = 100;
+
+/// Builds the logs of a function call event out of the call traces of every block, the filter has
+/// to have a [`RindexerEventFilter::function_call`].
+pub struct CallTraceLogSource {
+    provider: Arc<JsonRpcCachedProvider>,
+}
+
+impl CallTraceLogSource {
+    pub fn new(provider: Arc<JsonRpcCachedProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl LogSource for CallTraceLogSource {
+    async fn get_logs(&self, filter: &RindexerEventFilter) -> Result<Vec<Log>, ProviderError> {
+        match filter.function_call() {
+            Some(function_call) => {
+                self.provider.get_logs_from_call_traces(filter, function_call).await
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    fn limit_to_block(&self, from_block: U64, to_block: U64) -> U64 {
+        to_block.min(from_block + U64::from(CALL_TRACES_MAX_BLOCK_RANGE - 1))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FileLogSourceError {
+    #[error("Could not read logs archive {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Logs archive {0} has no `<from_block>-<to_block>.jsonl` or `.parquet` files")]
+    Empty(String),
+
+    #[error("Logs archive file {0} overlaps or leaves a gap after the previous file")]
+    NotContiguous(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Jsonl,
+    Parquet,
+}
+
+#[derive(Debug)]
+struct ArchiveFile {
+    from_block: U64,
+    to_block: U64,
+    format: ArchiveFormat,
+    path: PathBuf,
+    /// The first block of every run of lines for the same block and the byte offset it starts
+    /// at, read once the file is first asked for.
+    block_offsets: OnceCell<Vec<(U64, u64)>>,
+}
+
+impl ArchiveFile {
+    fn new(from_block: U64, to_block: U64, format: ArchiveFormat, path: PathBuf) -> Self {
+        Self { from_block, to_block, format, path, block_offsets: OnceCell::new() }
+    }
+
+    fn error(&self, error: impl std::fmt::Display) -> ProviderError {
+        ProviderError::CustomError(format!("{}: {}", self.path.display(), error))
+    }
+
+    async fn block_offsets(&self) -> Result<&[(U64, u64)], ProviderError> {
+        self.block_offsets.get_or_try_init(|| self.index_block_offsets()).await.map(Vec::as_slice)
+    }
+
+    async fn index_block_offsets(&self) -> Result<Vec<(U64, u64)>, ProviderError> {
+        #[derive(Deserialize)]
+        struct LogBlockNumber {
+            #[serde(rename = "blockNumber")]
+            block_number: U64,
+        }
+
+        let file = tokio::fs::File::open(&self.path).await.map_err(|e| self.error(e))?;
+        let mut reader = BufReader::new(file);
+        let mut block_offsets: Vec<(U64, u64)> = Vec::new();
+        let mut offset = 0;
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).await.map_err(|e| self.error(e))?;
+            if read == 0 {
+                break;
+            }
+
+            if !line.trim().is_empty() {
+                let block_number = serde_json::from_str::<LogBlockNumber>(&line)?.block_number;
+                match block_offsets.last() {
+                    Some((last_block, _)) if *last_block > block_number => {
+                        return Err(self.error(format!(
+                            "logs are not in block order, block {block_number} after {last_block}"
+                        )));
+                    }
+                    Some((last_block, _)) if *last_block == block_number => {}
+                    _ => block_offsets.push((block_number, offset)),
+                }
+            }
+            offset += read as u64;
+        }
+
+        Ok(block_offsets)
+    }
+
+    async fn get_jsonl_logs(
+        &self,
+        filter: &RindexerEventFilter,
+        addresses: &Option<HashSet<Address>>,
+    ) -> Result<Vec<Log>, ProviderError> {
+        let block_offsets = self.block_offsets().await?;
+        let start = block_offsets.partition_point(|(block, _)| *block < filter.from_block());
+        let Some((_, offset)) = block_offsets.get(start) else {
+            return Ok(vec![]);
+        };
+
+        let mut reader = tokio::fs::File::open(&self.path).await.map_err(|e| self.error(e))?;
+        reader.seek(SeekFrom::Start(*offset)).await.map_err(|e| self.error(e))?;
+        let mut lines = BufReader::new(reader).lines();
+
+        let mut logs = Vec::new();
+        while let Some(line) = lines.next_line().await.map_err(|e| self.error(e))? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let log: Log = serde_json::from_str(&line)?;
+            if log
+                .block_number
+                .is_some_and(|block_number| U64::from(block_number) > filter.to_block())
+            {
+                break;
+            }
+            if filter.matches_log(addresses, &log) {
+                logs.push(log);
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// Parquet files have no cheap way to seek to a block, the whole file is read on a blocking
+    /// thread and the rows outside the request dropped.
+    async fn get_parquet_logs(
+        &self,
+        filter: &RindexerEventFilter,
+        addresses: &Option<HashSet<Address>>,
+    ) -> Result<Vec<Log>, ProviderError> {
+        let path = self.path.clone();
+        let from_block = filter.from_block().to::<u64>();
+        let to_block = filter.to_block().to::<u64>();
+
+        let logs =
+            tokio::task::spawn_blocking(move || read_parquet_logs(&path, from_block, to_block))
+                .await
+                .map_err(|e| self.error(e))?
+                .map_err(|e| self.error(e))?;
+
+        Ok(logs.into_iter().filter(|log| filter.matches_log(addresses, log)).collect())
+    }
+}
+
+fn read_parquet_logs(path: &Path, from_block: u64, to_block: u64) -> Result<Vec<Log>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let reader = SerializedFileReader::new(file).map_err(|e| e.to_string())?;
+
+    let mut logs = Vec::new();
+    for row in reader.get_row_iter(None).map_err(|e| e.to_string())? {
+        let log = parquet_row_to_log(&row.map_err(|e| e.to_string())?)?;
+        if log.block_number.is_some_and(|block| block >= from_block && block <= to_block) {
+            logs.push(log);
+        }
+    }
+
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+    Ok(logs)
+}
+
+/// Reads a row laid out like the `logs` dataset of cryo, the hashes and addresses may be binary or
+/// hex strings and the numbers any integer type.
+fn parquet_row_to_log(row: &Row) -> Result<Log, String> {
+    let mut block_number = None;
+    let mut address = None;
+    let mut topics: [Option<B256>; 4] = [None; 4];
+    let mut data = Bytes::new();
+    let mut log = Log::default();
+
+    for (name, field) in row.get_column_iter() {
+        match name.as_str() {
+            "block_number" => block_number = parquet_u64(field),
+            "block_hash" => {
+                log.block_hash =
+                    parquet_bytes(field).and_then(|b| B256::try_from(b.as_slice()).ok())
+            }
+            "block_timestamp" | "timestamp" => log.block_timestamp = parquet_u64(field),
+            "transaction_hash" => {
+                log.transaction_hash =
+                    parquet_bytes(field).and_then(|b| B256::try_from(b.as_slice()).ok())
+            }
+            "transaction_index" => log.transaction_index = parquet_u64(field),
+            "log_index" => log.log_index = parquet_u64(field),
+            "address" => {
+                address = parquet_bytes(field).and_then(|b| Address::try_from(b.as_slice()).ok())
+            }
+            "topic0" | "topic1" | "topic2" | "topic3" => {
+                let index = name[5..].parse::<usize>().unwrap_or_default();
+                topics[index] =
+                    parquet_bytes(field).and_then(|b| B256::try_from(b.as_slice()).ok());
+            }
+            "data" => data = parquet_bytes(field).map(Bytes::from).unwrap_or_default(),
+            _ => {}
+        }
+    }
+
+    let block_number = block_number.ok_or("row has no block_number")?;
+    let address = address.ok_or("row has no address")?;
+    let topics = topics.into_iter().map_while(|topic| topic).collect();
+
+    log.block_number = Some(block_number);
+    log.inner = alloy::primitives::Log { address, data: LogData::new_unchecked(topics, data) };
+    Ok(log)
+}
+
+fn parquet_u64(field: &Field) -> Option<u64> {
+    match field {
+        Field::Byte(v) => u64::try_from(*v).ok(),
+        Field::Short(v) => u64::try_from(*v).ok(),
+        Field::Int(v) => u64::try_from(*v).ok(),
+        Field::Long(v) => u64::try_from(*v).ok(),
+        Field::UByte(v) => Some(u64::from(*v)),
+        Field::UShort(v) => Some(u64::from(*v)),
+        Field::UInt(v) => Some(u64::from(*v)),
+        Field::ULong(v) => Some(*v),
+        Field::Str(v) => match v.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => v.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn parquet_bytes(field: &Field) -> Option<Vec<u8>> {
+    match field {
+        Field::Bytes(v) => Some(v.data().to_vec()),
+        Field::Str(v) => hex::decode(v).ok(),
+        _ => None,
+    }
+}
+
+/// Serves logs from a local archive, a directory of `<from_block>-<to_block>.jsonl` files holding
+/// one `eth_getLogs` log object per line in block order, or `<from_block>-<to_block>.parquet`
+/// files laid out like the `logs` dataset of cryo. The files have to cover one contiguous block
+/// range, events are backfilled from it at disk speed and the blocks around it come from rpc.
+///
+/// Each jsonl file is indexed by block the first time it is read, so a request only parses the
+/// lines of its own blocks.
+#[derive(Debug)]
+pub struct FileLogSource {
+    files: Vec<ArchiveFile>,
+}
+
+impl FileLogSource {
+    pub fn open(path: &Path) -> Result<Self, FileLogSourceError> {
+        let display_path = path.display().to_string();
+        let entries =
+            fs::read_dir(path).map_err(|e| FileLogSourceError::Io(display_path.clone(), e))?;
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| FileLogSourceError::Io(display_path.clone(), e))?.path();
+            if let Some((from_block, to_block, format)) = archive_file_range(&path) {
+                files.push(ArchiveFile::new(from_block, to_block, format, path));
+            }
+        }
+
+        files.sort_by_key(|file| file.from_block);
+
+        if files.is_empty() {
+            return Err(FileLogSourceError::Empty(display_path));
+        }
+        for pair in files.windows(2) {
+            if pair[1].from_block != pair[0].to_block + U64::from(1) {
+                return Err(FileLogSourceError::NotContiguous(pair[1].path.display().to_string()));
+            }
+        }
+
+        Ok(Self { files })
+    }
+
+    /// The first block the archive holds the logs of.
+    pub fn from_block(&self) -> U64 {
+        self.files.first().map_or(U64::ZERO, |file| file.from_block)
+    }
+
+    /// Whether the archive holds the logs of the block.
+    pub fn contains(&self, block: U64) -> bool {
+        self.files.first().is_some_and(|file| file.from_block <= block)
+            && self.files.last().is_some_and(|file| file.to_block >= block)
+    }
+}
+
+#[async_trait]
+impl LogSource for FileLogSource {
+    async fn get_logs(&self, filter: &RindexerEventFilter) -> Result<Vec<Log>, ProviderError> {
+        let from_block = filter.from_block();
+        let to_block = filter.to_block();
+        let addresses = filter.contract_addresses().await;
+
+        let mut logs = Vec::new();
+        for file in self
+            .files
+            .iter()
+            .filter(|file| file.to_block >= from_block && file.from_block <= to_block)
+        {
+            match file.format {
+                ArchiveFormat::Jsonl => logs.extend(file.get_jsonl_logs(filter, &addresses).await?),
+                ArchiveFormat::Parquet => {
+                    logs.extend(file.get_parquet_logs(filter, &addresses).await?)
+                }
+            }
+        }
+
+        Ok(logs)
+    }
+
+    fn limit_to_block(&self, from_block: U64, to_block: U64) -> U64 {
+        self.files
+            .iter()
+            .find(|file| file.from_block <= from_block && file.to_block >= from_block)
+            .map_or(to_block, |file| file.to_block.min(to_block))
+    }
+
+    fn follows_endpoint_block_range(&self) -> bool {
+        false
+    }
+}
+
+fn archive_file_range(path: &Path) -> Option<(U64, U64, ArchiveFormat)> {
+    let format = match path.extension()?.to_str()? {
+        "jsonl" => ArchiveFormat::Jsonl,
+        "parquet" => ArchiveFormat::Parquet,
+        _ => return None,
+    };
+
+    let (from_block, to_block) = path.file_stem()?.to_str()?.split_once('-')?;
+    let from_block = from_block.parse::<u64>().ok()?;
+    let to_block = to_block.parse::<u64>().ok()?;
+
+    (from_block <= to_block).then(|| (U64::from(from_block), U64::from(to_block), format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_file_range() {
+        assert_eq!(
+            archive_file_range(Path::new("archive/100-199.jsonl")),
+            Some((U64::from(100), U64::from(199), ArchiveFormat::Jsonl))
+        );
+        assert_eq!(
+            archive_file_range(Path::new("archive/100-199.parquet")),
+            Some((U64::from(100), U64::from(199), ArchiveFormat::Parquet))
+        );
+        assert_eq!(archive_file_range(Path::new("archive/100-199.csv")), None);
+        assert_eq!(archive_file_range(Path::new("archive/199-100.jsonl")), None);
+        assert_eq!(archive_file_range(Path::new("archive/latest.jsonl")), None);
+    }
+
+    #[test]
+    fn test_file_log_source_limits_requests_to_a_file() {
+        let source = archive();
+
+        assert_eq!(source.limit_to_block(U64::from(150), U64::from(1000)), U64::from(199));
+        assert_eq!(source.limit_to_block(U64::from(200), U64::from(250)), U64::from(250));
+        assert!(source.contains(U64::from(299)));
+        assert!(!source.contains(U64::from(300)));
+    }
+
+    #[test]
+    fn test_rpc_requests_stop_at_the_archive() {
+        let source = archive();
+        let archive_from_block = Some(source.from_block());
+
+        // a backfill starting before the archive pages through rpc up to its first block
+        let to_block = stop_before_archive(archive_from_block, U64::from(10), U64::from(1000));
+        assert_eq!(to_block, U64::from(99));
+        assert!(!source.contains(to_block));
+
+        // the next request starts inside the archive and is served from disk
+        assert!(source.contains(to_block + U64::from(1)));
+        assert_eq!(source.limit_to_block(to_block + U64::from(1), U64::from(1000)), U64::from(199));
+
+        // once past the archive rpc is no longer held back
+        assert_eq!(
+            stop_before_archive(archive_from_block, U64::from(300), U64::from(1000)),
+            U64::from(1000)
+        );
+        assert_eq!(stop_before_archive(None, U64::from(10), U64::from(1000)), U64::from(1000));
+    }
+
+    #[test]
+    fn test_parquet_row_to_log() {
+        let hash = |byte: u8| Field::Bytes(vec![byte; 32].into());
+        let row = Row::new(vec![
+            ("block_number".to_string(), Field::UInt(100)),
+            ("transaction_index".to_string(), Field::Int(2)),
+            ("log_index".to_string(), Field::Long(7)),
+            ("transaction_hash".to_string(), hash(1)),
+            ("address".to_string(), Field::Str(format!("0x{}", "22".repeat(20)))),
+            ("topic0".to_string(), hash(3)),
+            ("topic1".to_string(), hash(4)),
+            ("topic2".to_string(), Field::Null),
+            ("topic3".to_string(), Field::Null),
+            ("data".to_string(), Field::Bytes(vec![5, 6].into())),
+            ("chain_id".to_string(), Field::ULong(1)),
+        ]);
+
+        let log = parquet_row_to_log(&row).unwrap();
+        assert_eq!(log.block_number, Some(100));
+        assert_eq!(log.transaction_index, Some(2));
+        assert_eq!(log.log_index, Some(7));
+        assert_eq!(log.transaction_hash, Some(B256::repeat_byte(1)));
+        assert_eq!(log.address(), Address::repeat_byte(0x22));
+        assert_eq!(log.topics(), &[B256::repeat_byte(3), B256::repeat_byte(4)]);
+        assert_eq!(log.data().data, Bytes::from(vec![5, 6]));
+        assert_eq!(log.block_hash, None);
+
+        let row = Row::new(vec![("block_number".to_string(), Field::UInt(100))]);
+        assert!(parquet_row_to_log(&row).is_err());
+    }
+
+    fn archive() -> FileLogSource {
+        FileLogSource {
+            files: vec![
+                ArchiveFile::new(
+                    U64::from(100),
+                    U64::from(199),
+                    ArchiveFormat::Jsonl,
+                    PathBuf::from("100-199.jsonl"),
+                ),
+                ArchiveFile::new(
+                    U64::from(200),
+                    U64::from(299),
+                    ArchiveFormat::Parquet,
+                    PathBuf::from("200-299.parquet"),
+                ),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_file_indexes_block_offsets() {
+        let path = std::env::temp_dir()
+            .join(format!("rindexer-logs-archive-{}-100-199.jsonl", std::process::id()));
+        let lines = [
+            r#"{"blockNumber":"0x64"}"#,
+            r#"{"blockNumber":"0x64"}"#,
+            "",
+            r#"{"blockNumber":"0x66"}"#,
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let file =
+            ArchiveFile::new(U64::from(100), U64::from(199), ArchiveFormat::Jsonl, path.clone());
+        assert_eq!(
+            file.block_offsets().await.unwrap(),
+            &[(U64::from(100), 0), (U64::from(102), 47)]
+        );
+
+        fs::write(&path, [lines[3], lines[0]].join("\n")).unwrap();
+        let file =
+            ArchiveFile::new(U64::from(100), U64::from(199), ArchiveFormat::Jsonl, path.clone());
+        assert!(file.block_offsets().await.is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use alloy::primitives::U64;
+use alloy_chains::Chain;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use super::core::serialize_option_u64_as_string;
+use super::native_transfer::{deserialize_option_u64_from_string_or_num, TraceProcessingMethod};
+
+/// How far behind the head is safe to index when neither the network nor the bundled profile of
+/// its chain know the reorg depth.
+const DEFAULT_REORG_DEPTH: u64 = 64;
+
+lazy_static! {
+    static ref BUNDLED_CHAIN_PROFILES: BTreeMap<u64, ChainProfile> =
+        serde_yaml::from_str(include_str!("chain_profiles.yaml"))
+            .expect("Bundled chain_profiles.yaml is invalid");
+}
+
+/// Which zk rollup stack a chain runs on, zk chains trace calls differently.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZkFlavour {
+    /// Not a zk rollup.
+    NotZk,
+    /// zkSync and the chains built with the zk stack, they answer `zks_L1ChainId`.
+    ZkStack,
+    Scroll,
+    PolygonZkEvm,
+    Linea,
+}
+
+/// What rindexer has to know about a chain to index it well. Every field is optional, a network
+/// only sets the fields it wants to change from the profile bundled for its chain id.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChainProfile {
+    /// The average time between blocks in milliseconds, used by `block_poll_frequency`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time_ms: Option<u64>,
+
+    /// Without one rindexer asks the node with `zks_L1ChainId` on startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zk: Option<ZkFlavour>,
+
+    /// How many blocks can be reorged, indexing stays this far behind the head when the network
+    /// has no `reorg_safe_distance`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_depth: Option<u64>,
+
+    /// The trace methods the nodes of the chain serve, every method is assumed to work when not
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_methods: Option<Vec<TraceProcessingMethod>>,
+
+    /// The largest `eth_getLogs` block range the chain's nodes allow, used when the network has
+    /// no `max_block_range`.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string_or_num",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub max_logs_range: Option<U64>,
+}
+
+impl ChainProfile {
+    /// The profile of a chain, the fields set in `overrides` win over the bundled ones.
+    pub fn for_chain(chain_id: u64, overrides: Option<&ChainProfile>) -> ChainProfile {
+        let bundled = bundled_chain_profile(chain_id);
+        let Some(overrides) = overrides else {
+            return bundled;
+        };
+
+        ChainProfile {
+            block_time_ms: overrides.block_time_ms.or(bundled.block_time_ms),
+            zk: overrides.zk.or(bundled.zk),
+            reorg_depth: overrides.reorg_depth.or(bundled.reorg_depth),
+            trace_methods: overrides.trace_methods.clone().or(bundled.trace_methods),
+            max_logs_range: overrides.max_logs_range.or(bundled.max_logs_range),
+        }
+    }
+
+    pub fn block_time(&self) -> Option<Duration> {
+        self.block_time_ms.map(Duration::from_millis)
+    }
+
+    /// Whether the chain is a zk rollup, `None` when the profile does not say.
+    pub fn is_zk(&self) -> Option<bool> {
+        self.zk.map(|zk| zk != ZkFlavour::NotZk)
+    }
+
+    pub fn reorg_depth(&self) -> U64 {
+        U64::from(self.reorg_depth.unwrap_or(DEFAULT_REORG_DEPTH))
+    }
+
+    pub fn supports_trace_method(&self, method: TraceProcessingMethod) -> bool {
+        // every node serves blocks, only the trace methods are optional
+        method == TraceProcessingMethod::EthGetBlockByNumber
+            || self.trace_methods.as_ref().is_none_or(|methods| methods.contains(&method))
+    }
+}
+
+/// The profile rindexer ships for a chain out of `chain_profiles.yaml`, chains it does not list
+/// get an empty profile apart from the block time `alloy_chains` has for them.
+fn bundled_chain_profile(chain_id: u64) -> ChainProfile {
+    let mut profile = BUNDLED_CHAIN_PROFILES.get(&chain_id).cloned().unwrap_or_default();
+    if profile.block_time_ms.is_none() {
+        profile.block_time_ms = Chain::from(chain_id)
+            .average_blocktime_hint()
+            .map(|block_time| block_time.as_millis() as u64);
+    }
+
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_profile_overrides_bundled_profile() {
+        let mainnet = ChainProfile::for_chain(1, None);
+        assert_eq!(mainnet.is_zk(), Some(false));
+        assert_eq!(mainnet.reorg_depth(), U64::from(12));
+        assert!(mainnet.block_time().is_some());
+
+        let overrides: ChainProfile = serde_yaml::from_str(
+            r#"
+            block_time_ms: 250
+            zk: zk_stack
+            trace_methods:
+              - debug_traceBlockByNumber
+            max_logs_range: 10000
+            "#,
+        )
+        .unwrap();
+        let unknown = ChainProfile::for_chain(987_654_321, Some(&overrides));
+        assert_eq!(unknown.block_time(), Some(Duration::from_millis(250)));
+        assert_eq!(unknown.is_zk(), Some(true));
+        assert_eq!(unknown.reorg_depth(), U64::from(64));
+        assert_eq!(unknown.max_logs_range, Some(U64::from(10000)));
+        assert!(unknown.supports_trace_method(TraceProcessingMethod::DebugTraceBlockByNumber));
+        assert!(unknown.supports_trace_method(TraceProcessingMethod::EthGetBlockByNumber));
+        assert!(!unknown.supports_trace_method(TraceProcessingMethod::TraceBlock));
+
+        assert_eq!(ChainProfile::for_chain(987_654_321, None).is_zk(), None);
+    }
+
+    #[test]
+    fn test_bundled_chain_profiles() {
+        assert!(!BUNDLED_CHAIN_PROFILES.is_empty());
+
+        let zksync = ChainProfile::for_chain(324, None);
+        assert_eq!(zksync.zk, Some(ZkFlavour::ZkStack));
+        assert_eq!(zksync.reorg_depth(), U64::from(64));
+
+        assert_eq!(ChainProfile::for_chain(534352, None).zk, Some(ZkFlavour::Scroll));
+        assert_eq!(ChainProfile::for_chain(8453, None).is_zk(), Some(false));
+    }
+}
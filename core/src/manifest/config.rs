@@ -14,6 +14,15 @@ pub struct Config {
     /// If `index_event_in_order` is used, this option will always be set as `1` (sequential).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub callback_concurrency: Option<usize>,
+
+    /// Splits the historic sync of every "network-event" into this many block ranges which are
+    /// fetched concurrently, each learning its own max block range. Ranges which finished are
+    /// checkpointed so a restart does not fetch them again.
+    ///
+    /// If `index_event_in_order` is used, the results are still handed to the handler in block
+    /// order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backfill_segments: Option<usize>,
 }
 
 #[cfg(test)]
@@ -45,5 +54,17 @@ mod tests {
 
         assert_eq!(transfer.buffer, Some(4));
         assert_eq!(transfer.callback_concurrency, None);
+        assert_eq!(transfer.backfill_segments, None);
+    }
+
+    #[test]
+    fn test_config_backfill_segments() {
+        let yaml = r#"
+          backfill_segments: 8
+        "#;
+
+        let transfer: Config = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(transfer.backfill_segments, Some(8));
     }
 }
@@ -1,7 +1,13 @@
-use std::{borrow::Cow, collections::HashSet, fs, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
 
 use alloy::rpc::types::Topic;
 use alloy::{
+    json_abi::{Function, JsonAbi},
     primitives::{Address, U64},
     rpc::types::ValueOrArray,
 };
@@ -9,7 +15,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
-use crate::event::contract_setup::FactoryDetails;
+use super::native_transfer::TraceProcessingMethod;
+use crate::event::contract_setup::{FactoryAddress, FactoryDetails, FactoryDetailsFromAbiError};
+use crate::event::function_call::{
+    function_call_event, function_call_event_name, FunctionCallFilter,
+};
 use crate::helpers::parse_topic;
 use crate::{
     event::contract_setup::{
@@ -61,15 +71,62 @@ pub struct FilterDetailsYaml {
 pub struct FactoryDetailsYaml {
     pub name: String,
 
-    pub address: ValueOrArray<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<ValueOrArray<Address>>,
+
+    /// The factory which deployed this factory, its addresses are then discovered from the
+    /// events of that factory instead of being set in `address`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Box<FactoryDetailsYaml>>,
 
     pub event_name: String,
 
+    /// The path to the deployed addresses in the event inputs, `.` goes into a tuple and `[]`
+    /// through every item of an array, e.g. `pools[].addr`.
     pub input_name: String,
 
     pub abi: String,
 }
 
+impl FactoryDetailsYaml {
+    /// Every factory of a nested factory, from the one with an `address` to this one.
+    pub fn levels(&self) -> Vec<&FactoryDetailsYaml> {
+        let mut levels = match &self.factory {
+            Some(factory) => factory.levels(),
+            None => vec![],
+        };
+        levels.push(self);
+
+        levels
+    }
+
+    pub fn factory_details(
+        &self,
+        project_path: &Path,
+        indexed_filters: Option<Vec<EventInputIndexedFilters>>,
+    ) -> Result<FactoryDetails, FactoryDetailsFromAbiError> {
+        let address = match (&self.address, &self.factory) {
+            (Some(address), None) => FactoryAddress::Address(address.clone()),
+            (None, Some(factory)) => {
+                FactoryAddress::Factory(Box::new(factory.factory_details(project_path, None)?))
+            }
+            _ => {
+                return Err(FactoryDetailsFromAbiError::AddressOrFactoryRequired(self.name.clone()))
+            }
+        };
+
+        FactoryDetails::from_abi(
+            project_path,
+            self.abi.clone(),
+            self.name.clone(),
+            address,
+            self.event_name.clone(),
+            self.input_name.clone(),
+            indexed_filters,
+        )
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ContractDetails {
     pub network: String,
@@ -112,28 +169,21 @@ impl ContractDetails {
             })
         } else if let Some(factory) = &self.factory {
             IndexingContractSetup::Factory(
-                FactoryDetails::from_abi(
-                    project_path,
-                    factory.abi.clone(),
-                    factory.name.clone(),
-                    factory.address.clone(),
-                    factory.event_name.clone(),
-                    factory.input_name.clone(),
-                    self.indexed_filters.clone(),
-                )
-                .unwrap_or_else(|_| panic!("Could not parse ABI from path: {}", factory.abi)),
+                factory
+                    .factory_details(project_path, self.indexed_filters.clone())
+                    .unwrap_or_else(|e| panic!("Could not build factory {}: {}", factory.name, e)),
             )
         } else if let Some(filter) = &self.filter {
             match filter {
                 ValueOrArray::Value(filter) => IndexingContractSetup::Filter(FilterDetails {
                     events: ValueOrArray::Value(filter.event_name.clone()),
-                    indexed_filters: self.indexed_filters.as_ref().and_then(|f| f.first().cloned()),
+                    indexed_filters: self.indexed_filters.clone(),
                 }),
                 ValueOrArray::Array(filters) => IndexingContractSetup::Filter(FilterDetails {
                     events: ValueOrArray::Array(
                         filters.iter().map(|f| f.event_name.clone()).collect(),
                     ),
-                    indexed_filters: self.indexed_filters.as_ref().and_then(|f| f.first().cloned()),
+                    indexed_filters: self.indexed_filters.clone(),
                 }),
             }
         } else {
@@ -204,6 +254,101 @@ impl DependencyEventTree {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogsFetchMode {
+    /// Every event follows the chain with its own `eth_getLogs` requests.
+    #[default]
+    PerEvent,
+    /// One `eth_getLogs` request matches every event of the contract, the logs are then handed to
+    /// the event they belong to. Events with dependencies are still fetched on their own.
+    PerContract,
+}
+
+/// How many times a handler is called before `skip` or `halt` apply when no `max_attempts` is set.
+const DEFAULT_ON_ERROR_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnErrorPolicy {
+    /// Keep calling the handler with the batch, indexing halts once `max_attempts` is used up.
+    #[default]
+    Retry,
+    /// Record the batch as a failed batch to replay later and carry on with the next one.
+    Skip,
+    /// Shut indexing down cleanly and exit with a non-zero code.
+    Halt,
+}
+
+/// What happens to a batch of events when its handler keeps failing.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OnError {
+    #[serde(default)]
+    pub policy: OnErrorPolicy,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+impl OnError {
+    /// How many times the handler is called before the policy applies, `None` retries forever.
+    pub fn max_attempts(&self) -> Option<u32> {
+        match self.policy {
+            OnErrorPolicy::Retry => self.max_attempts,
+            OnErrorPolicy::Skip | OnErrorPolicy::Halt => {
+                Some(self.max_attempts.unwrap_or(DEFAULT_ON_ERROR_MAX_ATTEMPTS))
+            }
+        }
+    }
+}
+
+/// The `on_error` of a contract, `events` overrides it for single events.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ContractOnError {
+    #[serde(flatten)]
+    pub default: OnError,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<BTreeMap<String, OnError>>,
+}
+
+/// A function of the contract whose calls are indexed as an event, the calls are found in the
+/// call traces of every block.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContractFunction {
+    /// The name of the function, or its full signature like `safeTransferFrom(address,address,uint256)`
+    /// to pick one of its overloads.
+    pub name: String,
+
+    /// Index the calls which reverted too, the event then gets a `succeeded` input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_reverted: Option<bool>,
+
+    /// How the blocks are traced, `trace_block` when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<TraceProcessingMethod>,
+}
+
+impl ContractFunction {
+    /// The name of the event the calls are indexed as, `transfer` is indexed as `TransferCall`.
+    pub fn event_name(&self) -> String {
+        function_call_event_name(self.function_name())
+    }
+
+    /// The name of the function without the inputs of its signature.
+    pub fn function_name(&self) -> &str {
+        self.name.split_once('(').map_or(self.name.as_str(), |(name, _)| name).trim()
+    }
+
+    pub fn include_reverted(&self) -> bool {
+        self.include_reverted.unwrap_or_default()
+    }
+
+    pub fn method(&self) -> TraceProcessingMethod {
+        self.method.unwrap_or(TraceProcessingMethod::TraceBlock)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Contract {
     pub name: String,
@@ -224,6 +369,27 @@ pub struct Contract {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reorg_safe_distance: Option<bool>,
 
+    /// Attach the block timestamp to every indexed event, overrides the network setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_block_timestamp: Option<bool>,
+
+    /// Attach the sender, recipient, value, gas and status of its transaction to every indexed
+    /// event, fetched with the receipts of the transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_tx_context: Option<bool>,
+
+    /// Fetch the logs of every event with its own `eth_getLogs` or once for the whole contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_fetch_mode: Option<LogsFetchMode>,
+
+    /// What to do when a handler of the contract keeps failing, retries forever when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<ContractOnError>,
+
+    /// Functions whose successful calls are indexed like events, including internal calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<ContractFunction>>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub generate_csv: Option<bool>,
 
@@ -247,6 +413,36 @@ pub enum ParseAbiError {
 
     #[error("Could not merge ABI: {0}")]
     CouldNotMergeAbis(#[from] serde_json::Error),
+
+    #[error("Function {0} not found in ABI")]
+    FunctionNotFoundInAbi(String),
+
+    #[error("Function {0} is overloaded in the ABI, use one of its signatures: {1}")]
+    FunctionOverloadedInAbi(String, String),
+}
+
+/// The function of the ABI with the name or full signature. A bare name has to be unique, an
+/// overloaded function is picked by its signature like `safeTransferFrom(address,address,uint256)`.
+fn abi_function<'a>(abi: &'a JsonAbi, name: &str) -> Result<&'a Function, ParseAbiError> {
+    let signature = name.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    let function_name = signature.split_once('(').map_or(signature.as_str(), |(name, _)| name);
+    let functions = abi.function(function_name).map(Vec::as_slice).unwrap_or_default();
+
+    if signature.contains('(') {
+        return functions
+            .iter()
+            .find(|function| function.signature() == signature)
+            .ok_or_else(|| ParseAbiError::FunctionNotFoundInAbi(name.to_string()));
+    }
+
+    match functions {
+        [function] => Ok(function),
+        [] => Err(ParseAbiError::FunctionNotFoundInAbi(name.to_string())),
+        overloads => Err(ParseAbiError::FunctionOverloadedInAbi(
+            name.to_string(),
+            overloads.iter().map(Function::signature).collect::<Vec<_>>().join(", "),
+        )),
+    }
 }
 
 impl Contract {
@@ -254,7 +450,70 @@ impl Contract {
         self.name = name;
     }
 
+    pub fn on_error_for_event(&self, event_name: &str) -> OnError {
+        self.on_error
+            .as_ref()
+            .map(|on_error| {
+                on_error
+                    .events
+                    .as_ref()
+                    .and_then(|events| events.get(event_name))
+                    .copied()
+                    .unwrap_or(on_error.default)
+            })
+            .unwrap_or_default()
+    }
+
+    /// The configured function the event indexes the calls of, if it is a function call event.
+    pub fn function_for_event(&self, event_name: &str) -> Option<&ContractFunction> {
+        self.functions
+            .as_ref()
+            .and_then(|functions| functions.iter().find(|f| f.event_name() == event_name))
+    }
+
+    /// What is needed to build the logs of a function call event from call traces, `None` for
+    /// any other event.
+    pub fn function_call_filter(
+        &self,
+        project_path: &Path,
+        event_name: &str,
+    ) -> Result<Option<FunctionCallFilter>, ParseAbiError> {
+        let Some(function) = self.function_for_event(event_name) else {
+            return Ok(None);
+        };
+
+        let abi: JsonAbi = serde_json::from_str(&self.read_abi(project_path)?)?;
+
+        Ok(Some(FunctionCallFilter {
+            function: abi_function(&abi, &function.name)?.clone(),
+            include_reverted: function.include_reverted(),
+            method: function.method(),
+        }))
+    }
+
+    /// The ABI of the contract with an event added for every function in `functions`, the calls
+    /// of the function are indexed as that event.
     pub fn parse_abi(&self, project_path: &Path) -> Result<String, ParseAbiError> {
+        let abi_str = self.read_abi(project_path)?;
+        let Some(functions) = self.functions.as_ref().filter(|functions| !functions.is_empty())
+        else {
+            return Ok(abi_str);
+        };
+
+        let abi: JsonAbi = serde_json::from_str(&abi_str)?;
+        let mut abi_value: Vec<Value> = serde_json::from_str(&abi_str)?;
+        for function in functions {
+            let event = function_call_event(
+                abi_function(&abi, &function.name)?,
+                function.include_reverted(),
+            );
+            abi_value.push(serde_json::to_value(event)?);
+        }
+
+        Ok(serde_json::to_string(&abi_value)?)
+    }
+
+    fn read_abi(&self, project_path: &Path) -> Result<String, ParseAbiError> {
         match &self.abi {
             StringOrArray::Single(abi_path) => {
                 let full_path = get_full_path(project_path, abi_path)?;
@@ -351,3 +610,255 @@ impl Contract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::*;
+    use crate::event::RindexerEventFilter;
+
+    #[test]
+    fn test_filter_mode_applies_indexed_filters_per_event() {
+        let details: ContractDetails = serde_yaml::from_str(
+            r#"
+            network: ethereum
+            filter:
+              - event_name: Transfer
+              - event_name: Approval
+            indexed_filters:
+              - event_name: Transfer
+                indexed_1:
+                  - "0x0000000000000000000000000000000000000001"
+              - event_name: Approval
+                indexed_1:
+                  - "0x0000000000000000000000000000000000000002"
+            "#,
+        )
+        .unwrap();
+
+        let IndexingContractSetup::Filter(filter_details) =
+            details.indexing_contract_setup(Path::new("."))
+        else {
+            panic!("expected filter setup");
+        };
+
+        let topic1 = |event_name: &str| {
+            RindexerEventFilter::new_filter(
+                &B256::ZERO,
+                event_name,
+                &filter_details,
+                U64::ZERO,
+                U64::from(100),
+            )
+            .unwrap()
+            .topic1()
+        };
+        let owner = |address: &str| parse_topic(address);
+
+        assert!(topic1("Transfer").matches(&owner("0x0000000000000000000000000000000000000001")));
+        assert!(!topic1("Transfer").matches(&owner("0x0000000000000000000000000000000000000002")));
+        assert!(topic1("Approval").matches(&owner("0x0000000000000000000000000000000000000002")));
+    }
+
+    #[test]
+    fn test_on_error_for_event() {
+        let on_error: ContractOnError = serde_yaml::from_str(
+            r#"
+            policy: skip
+            events:
+              Transfer:
+                policy: halt
+                max_attempts: 2
+              Approval:
+                policy: retry
+            "#,
+        )
+        .unwrap();
+        let contract = Contract {
+            name: "Token".to_string(),
+            details: vec![],
+            abi: StringOrArray::Single("./abis/token.abi.json".to_string()),
+            include_events: None,
+            index_event_in_order: None,
+            dependency_events: None,
+            reorg_safe_distance: None,
+            include_block_timestamp: None,
+            include_tx_context: None,
+            logs_fetch_mode: None,
+            on_error: Some(on_error),
+            functions: None,
+            generate_csv: None,
+            streams: None,
+            chat: None,
+        };
+
+        let transfer = contract.on_error_for_event("Transfer");
+        assert_eq!(transfer.policy, OnErrorPolicy::Halt);
+        assert_eq!(transfer.max_attempts(), Some(2));
+
+        let approval = contract.on_error_for_event("Approval");
+        assert_eq!(approval.policy, OnErrorPolicy::Retry);
+        assert_eq!(approval.max_attempts(), None);
+
+        let other = contract.on_error_for_event("OwnershipTransferred");
+        assert_eq!(other.policy, OnErrorPolicy::Skip);
+        assert_eq!(other.max_attempts(), Some(DEFAULT_ON_ERROR_MAX_ATTEMPTS));
+
+        let contract = Contract { on_error: None, ..contract };
+        assert_eq!(contract.on_error_for_event("Transfer"), OnError::default());
+    }
+
+    #[test]
+    fn test_parse_abi_adds_function_call_events() {
+        let project_path =
+            std::env::temp_dir().join(format!("rindexer-function-calls-{}", std::process::id()));
+        fs::create_dir_all(&project_path).unwrap();
+        fs::write(
+            project_path.join("token.abi.json"),
+            r#"[{"type":"function","name":"transfer","stateMutability":"nonpayable","inputs":[{"name":"to","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[{"name":"","type":"bool"}]}]"#,
+        )
+        .unwrap();
+
+        let functions: Vec<ContractFunction> = serde_yaml::from_str(
+            r#"
+            - name: transfer
+              include_reverted: true
+              method: debug_traceBlockByNumber
+            "#,
+        )
+        .unwrap();
+        let contract: Contract = serde_yaml::from_str(
+            r#"
+            name: Token
+            details: []
+            abi: ./token.abi.json
+            include_events:
+              - Transfer
+            "#,
+        )
+        .unwrap();
+        let contract = Contract { functions: Some(functions), ..contract };
+
+        let abi: JsonAbi =
+            serde_json::from_str(&contract.parse_abi(&project_path).unwrap()).unwrap();
+        let event = abi.event("TransferCall").and_then(|events| events.first()).unwrap();
+        assert_eq!(event.signature(), "TransferCall(address,uint256,bool)");
+        assert!(abi.function("transfer").is_some());
+
+        let filter = contract.function_call_filter(&project_path, "TransferCall").unwrap().unwrap();
+        assert_eq!(filter.function.signature(), "transfer(address,uint256)");
+        assert!(filter.include_reverted);
+        assert_eq!(filter.method, TraceProcessingMethod::DebugTraceBlockByNumber);
+        assert!(contract.function_call_filter(&project_path, "Transfer").unwrap().is_none());
+
+        let missing = Contract {
+            functions: Some(vec![ContractFunction {
+                name: "approve".to_string(),
+                include_reverted: None,
+                method: None,
+            }]),
+            ..contract.clone()
+        };
+        assert!(matches!(
+            missing.parse_abi(&project_path),
+            Err(ParseAbiError::FunctionNotFoundInAbi(name)) if name == "approve"
+        ));
+
+        fs::write(
+            project_path.join("token.abi.json"),
+            r#"[{"type":"function","name":"transfer","stateMutability":"nonpayable","inputs":[{"name":"to","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[]},{"type":"function","name":"transfer","stateMutability":"nonpayable","inputs":[{"name":"to","type":"address"}],"outputs":[]}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            contract.parse_abi(&project_path),
+            Err(ParseAbiError::FunctionOverloadedInAbi(name, signatures))
+                if name == "transfer" && signatures == "transfer(address,uint256), transfer(address)"
+        ));
+
+        let overload = Contract {
+            functions: Some(vec![ContractFunction {
+                name: "transfer(address, uint256)".to_string(),
+                include_reverted: None,
+                method: None,
+            }]),
+            ..contract.clone()
+        };
+        assert_eq!(overload.functions.as_ref().unwrap()[0].event_name(), "TransferCall");
+        let filter = overload.function_call_filter(&project_path, "TransferCall").unwrap().unwrap();
+        assert_eq!(filter.function.signature(), "transfer(address,uint256)");
+        assert!(overload.parse_abi(&project_path).is_ok());
+
+        let missing_overload = Contract {
+            functions: Some(vec![ContractFunction {
+                name: "transfer(uint256)".to_string(),
+                include_reverted: None,
+                method: None,
+            }]),
+            ..contract.clone()
+        };
+        assert!(matches!(
+            missing_overload.parse_abi(&project_path),
+            Err(ParseAbiError::FunctionNotFoundInAbi(name)) if name == "transfer(uint256)"
+        ));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn test_nested_factory_details() {
+        let project_path =
+            std::env::temp_dir().join(format!("rindexer-nested-factory-{}", std::process::id()));
+        fs::create_dir_all(&project_path).unwrap();
+        fs::write(
+            project_path.join("registry.abi.json"),
+            r#"[{"type":"event","name":"FactoryCreated","anonymous":false,"inputs":[{"name":"factory","type":"address","indexed":true}]}]"#,
+        )
+        .unwrap();
+        fs::write(
+            project_path.join("factory.abi.json"),
+            r#"[{"type":"event","name":"PoolsCreated","anonymous":false,"inputs":[{"name":"pools","type":"tuple[]","indexed":false,"components":[{"name":"fee","type":"uint24"},{"name":"addr","type":"address"}]}]}]"#,
+        )
+        .unwrap();
+
+        let factory: FactoryDetailsYaml = serde_yaml::from_str(
+            r#"
+            name: Factory
+            event_name: PoolsCreated
+            input_name: pools[].addr
+            abi: ./factory.abi.json
+            factory:
+              name: Registry
+              address: "0x0000000000000000000000000000000000000001"
+              event_name: FactoryCreated
+              input_name: factory
+              abi: ./registry.abi.json
+            "#,
+        )
+        .unwrap();
+
+        let names = factory.levels().iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Registry", "Factory"]);
+
+        let details = factory.factory_details(&project_path, None).unwrap();
+        let levels = details.levels();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].contract_name, "Registry");
+        assert!(matches!(details.address, FactoryAddress::Factory(_)));
+        assert!(matches!(details.root_address(), ValueOrArray::Value(_)));
+
+        let not_an_address =
+            FactoryDetailsYaml { input_name: "pools[].fee".to_string(), ..factory };
+        assert!(matches!(
+            not_an_address.factory_details(&project_path, None),
+            Err(FactoryDetailsFromAbiError::InputNotAnAddress(_, input)) if input == "pools[].fee"
+        ));
+        let no_address = FactoryDetailsYaml { factory: None, ..not_an_address };
+        assert!(matches!(
+            no_address.factory_details(&project_path, None),
+            Err(FactoryDetailsFromAbiError::AddressOrFactoryRequired(_))
+        ));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+}
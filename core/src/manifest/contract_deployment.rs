@@ -0,0 +1,148 @@
+use alloy::primitives::{Address, U64};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{
+    core::serialize_option_u64_as_string,
+    native_transfer::{deserialize_option_u64_from_string_or_num, TraceProcessingMethod},
+};
+use crate::manifest::{chat::ChatConfig, stream::StreamsConfig};
+
+/// Deployments made by contracts only show up in the traces of a block, so unlike native
+/// transfers they can not be read from `eth_getBlockByNumber`.
+fn default_method() -> TraceProcessingMethod {
+    TraceProcessingMethod::TraceBlock
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ContractDeploymentDetails {
+    pub network: String,
+
+    #[serde(default = "default_method")]
+    pub method: TraceProcessingMethod,
+
+    /// Only index the contracts deployed by these accounts or factories, every deployment on the
+    /// network is indexed when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployers: Option<Vec<Address>>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string_or_num",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub start_block: Option<U64>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string_or_num",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub end_block: Option<U64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// The normalized 'Contract Deployments' config, every `CREATE` and `CREATE2` found in the traces
+/// is indexed as a `ContractCreated` event of the `EvmTraces` contract.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ContractDeployments {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// None means "All" networks, the same as for native transfers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub networks: Option<Vec<ContractDeploymentDetails>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streams: Option<StreamsConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat: Option<ChatConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generate_csv: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_safe_distance: Option<bool>,
+}
+
+impl ContractDeployments {
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled
+    }
+}
+
+/// The config to enable contract deployments, either `true` for every deployment on all networks
+/// or the "full" option configuration.
+///
+/// # Example
+///
+/// ```yaml
+/// contract_deployments:
+///   networks:
+///     - network: ethereum
+///       method: trace_block
+///       deployers:
+///         - "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+/// ```
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ContractDeploymentsFullOrSimple {
+    Simple(bool),
+    Full(ContractDeployments),
+}
+
+pub fn deserialize_contract_deployments<'de, D>(
+    deserializer: D,
+) -> Result<ContractDeployments, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = ContractDeploymentsFullOrSimple::deserialize(deserializer)?;
+    let deployments = match value {
+        ContractDeploymentsFullOrSimple::Simple(enabled) => {
+            ContractDeployments { enabled, ..Default::default() }
+        }
+        ContractDeploymentsFullOrSimple::Full(deployments) => deployments,
+    };
+
+    Ok(deployments)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_contract_deployments_full() {
+        let yaml = r#"
+          networks:
+            - network: ethereum
+              start_block: "100"
+              deployers:
+                - "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+            - network: base
+              method: debug_traceBlockByNumber
+        "#;
+
+        let deployments: ContractDeployments = serde_yaml::from_str(yaml).unwrap();
+        let networks = deployments.networks.unwrap();
+
+        assert!(deployments.enabled);
+        assert_eq!(networks[0].method, TraceProcessingMethod::TraceBlock);
+        assert_eq!(networks[0].start_block, Some(U64::from(100)));
+        assert_eq!(
+            networks[0].deployers,
+            Some(vec![address!("0x1F98431c8aD98523631AE4a59f267346ea31F984")])
+        );
+        assert_eq!(networks[1].method, TraceProcessingMethod::DebugTraceBlockByNumber);
+        assert_eq!(networks[1].deployers, None);
+    }
+}
@@ -9,9 +9,15 @@ use crate::{
     indexer::Indexer,
     manifest::{
         contract::Contract,
+        contract_deployment::{
+            deserialize_contract_deployments, ContractDeploymentDetails, ContractDeployments,
+        },
         global::Global,
         graphql::GraphQLSettings,
-        native_transfer::{deserialize_native_transfers, NativeTransferDetails, NativeTransfers},
+        native_transfer::{
+            deserialize_native_transfers, NativeTransferDetails, NativeTransfers,
+            TraceProcessingMethod,
+        },
         network::Network,
         phantom::Phantom,
         storage::Storage,
@@ -81,6 +87,13 @@ pub struct Manifest {
     #[serde(deserialize_with = "deserialize_native_transfers")]
     pub native_transfers: NativeTransfers,
 
+    #[serde(
+        default,
+        deserialize_with = "deserialize_contract_deployments",
+        skip_serializing_if = "ContractDeployments::is_disabled"
+    )]
+    pub contract_deployments: ContractDeployments,
+
     pub contracts: Vec<Contract>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -99,6 +112,7 @@ impl Manifest {
             name: self.name.clone(),
             contracts: self.contracts.clone(),
             native_transfers: self.native_transfers.clone(),
+            contract_deployments: self.contract_deployments.clone(),
         }
     }
 
@@ -148,6 +162,31 @@ impl Manifest {
         }
     }
 
+    /// Check if the manifest has opted-in to indexing contract deployments. It is off by default.
+    pub fn has_enabled_contract_deployments(&self) -> bool {
+        self.contract_deployments.enabled
+    }
+
+    /// Like [`Manifest::set_native_transfer_networks`], `contract_deployments: true` indexes every
+    /// deployment on all the root `networks`.
+    pub fn set_contract_deployment_networks(&mut self) {
+        if self.contract_deployments.networks.is_none() {
+            let root_networks = self
+                .networks
+                .iter()
+                .map(|n| ContractDeploymentDetails {
+                    network: n.name.clone(),
+                    method: TraceProcessingMethod::TraceBlock,
+                    deployers: None,
+                    start_block: None,
+                    end_block: None,
+                })
+                .collect::<Vec<_>>();
+
+            self.contract_deployments.networks = Some(root_networks);
+        }
+    }
+
     pub fn contract_csv_enabled(&self, contract_name: &str) -> bool {
         let contract_csv_enabled = self
             .contracts
@@ -286,6 +325,33 @@ mod tests {
         assert!(!manifest.native_transfers.enabled);
     }
 
+    #[test]
+    fn test_contract_deployments_simple() {
+        let yaml = r#"
+        name: test
+        project_type: no-code
+        networks: []
+        contracts: []
+        contract_deployments: true
+        "#;
+
+        let mut manifest: Manifest = serde_yaml::from_str(yaml).unwrap();
+        assert!(manifest.has_enabled_contract_deployments());
+        manifest.set_contract_deployment_networks();
+        assert_eq!(manifest.contract_deployments.networks, Some(vec![]));
+
+        let yaml = r#"
+        name: test
+        project_type: no-code
+        networks: []
+        contracts: []
+        "#;
+
+        let manifest: Manifest = serde_yaml::from_str(yaml).unwrap();
+        assert!(!manifest.has_enabled_contract_deployments());
+        assert!(!serde_yaml::to_string(&manifest).unwrap().contains("contract_deployments"));
+    }
+
     #[test]
     fn test_config_simple() {
         let yaml = r#"
@@ -1,6 +1,8 @@
+pub mod chain_profile;
 pub mod chat;
 pub mod config;
 pub mod contract;
+pub mod contract_deployment;
 pub mod core;
 pub mod global;
 pub mod graphql;
@@ -25,8 +25,8 @@ pub enum TraceProcessingMethod {
 }
 
 /// Deserialize a number or string into a U64. This is required for the untagged deserialize of
-/// native transfers to succeed.
-fn deserialize_option_u64_from_string_or_num<'de, D>(
+/// native transfers and network rpc endpoints to succeed.
+pub(super) fn deserialize_option_u64_from_string_or_num<'de, D>(
     deserializer: D,
 ) -> Result<Option<U64>, D::Error>
 where
@@ -1,13 +1,22 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::time::Duration;
 
-use alloy::primitives::U64;
+use alloy::{
+    primitives::U64,
+    transports::{
+        http::reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION},
+        Authorization,
+    },
+};
 use serde::de::Visitor;
-use serde::{de, Deserialize, Deserializer, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tokio::sync::broadcast::Sender;
 use tokio::time::sleep;
 
+pub use super::chain_profile::ChainProfile;
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
+use super::native_transfer::deserialize_option_u64_from_string_or_num;
 pub use super::reth::RethConfig;
 use crate::notifications::ChainStateNotification;
 use crate::reth::node::start_reth_node_with_exex;
@@ -18,7 +27,24 @@ pub struct Network {
 
     pub chain_id: u64,
 
-    pub rpc: String,
+    /// A single rpc url or a list of endpoints requests are routed across.
+    pub rpc: NetworkRpc,
+
+    /// Overrides what rindexer knows about the chain, like its block time or reorg depth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_profile: Option<ChainProfile>,
+
+    /// Extra http headers sent with every rpc request of the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+
+    /// Authenticates every rpc request of the network, also used when opening websockets.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "serde_yaml::with::singleton_map"
+    )]
+    pub auth: Option<RpcAuth>,
 
     /// Poll the latest block at a defined frequency. It is recommended that this frequency be a
     /// multiple faster than the networks block time to ensure fast indexing.
@@ -27,9 +53,20 @@ pub struct Network {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub block_poll_frequency: Option<BlockPollFrequency>,
 
+    /// How live indexing is woken up when the network has a `ws://` or `wss://` rpc endpoint.
+    /// Defaults to `new_heads`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_subscription: Option<LiveSubscription>,
+
+    /// The compute units the rpc provider allows per second, requests across every event of the
+    /// network wait for their share of it.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub compute_units_per_second: Option<u64>,
 
+    /// The compute units of rpc methods, overriding the default costs for the methods listed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_unit_costs: Option<BTreeMap<String, u64>>,
+
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
@@ -41,15 +78,45 @@ pub struct Network {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub get_logs_settings: Option<GetLogsSettings>,
 
+    /// Where the logs of the network's events come from, `eth_getLogs` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_source: Option<LogsSource>,
+
+    /// A directory of `<from_block>-<to_block>.jsonl` log files, relative to the project, the
+    /// blocks it covers are backfilled from it instead of rpc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_archive: Option<String>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_logs_bloom_checks: Option<bool>,
 
+    /// Attach the block timestamp to every indexed event on this network, contracts can override
+    /// this with their own `include_block_timestamp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_block_timestamp: Option<bool>,
+
+    /// Keep the header of every block rindexer fetches for this network in
+    /// `rindexer_internal.blocks`, needs postgres storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_block_headers: Option<bool>,
+
+    /// How far behind the head contracts with `reorg_safe_distance` enabled index on this network.
+    /// Either the node's `safe` or `finalized` block, or a number of blocks. Defaults to a chain
+    /// based distance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_safe_distance: Option<ReorgSafeDistance>,
+
     /// Reth configuration for this network
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reth: Option<RethConfig>,
 }
 
 impl Network {
+    /// `headers` with the network's own headers and auth added.
+    pub fn rpc_headers(&self, headers: HeaderMap) -> Result<HeaderMap, RpcHeaderError> {
+        rpc_headers(headers, self.headers.clone().unwrap_or_default(), self.auth.as_ref())
+    }
+
     /// Get the IPC path for the Reth node
     pub fn get_reth_ipc_path(&self) -> Option<String> {
         use reth::cli::Commands;
@@ -95,6 +162,169 @@ impl Network {
     }
 }
 
+/// The rpc a network is indexed through. Either a single url or a list of endpoints, requests are
+/// spread over the healthy endpoints with the lowest priority and unhealthy ones are routed around.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum NetworkRpc {
+    Url(String),
+    Endpoints(Vec<RpcEndpoint>),
+}
+
+impl NetworkRpc {
+    /// The endpoints of the network, a single url is a single endpoint.
+    pub fn endpoints(&self) -> Vec<RpcEndpoint> {
+        match self {
+            NetworkRpc::Url(url) => vec![RpcEndpoint::new(url.clone())],
+            NetworkRpc::Endpoints(endpoints) => endpoints.clone(),
+        }
+    }
+
+    /// The url of the first endpoint, for the places which only care about one url.
+    pub fn primary_url(&self) -> &str {
+        match self {
+            NetworkRpc::Url(url) => url,
+            NetworkRpc::Endpoints(endpoints) => {
+                endpoints.first().map(|endpoint| endpoint.url.as_str()).unwrap_or_default()
+            }
+        }
+    }
+
+    /// Rewrites every url, keeping the weights and priorities of the endpoints.
+    pub fn map_urls(&self, f: impl Fn(&str) -> String) -> NetworkRpc {
+        match self {
+            NetworkRpc::Url(url) => NetworkRpc::Url(f(url)),
+            NetworkRpc::Endpoints(endpoints) => NetworkRpc::Endpoints(
+                endpoints
+                    .iter()
+                    .map(|endpoint| RpcEndpoint { url: f(&endpoint.url), ..endpoint.clone() })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<String> for NetworkRpc {
+    fn from(url: String) -> Self {
+        NetworkRpc::Url(url)
+    }
+}
+
+impl From<&str> for NetworkRpc {
+    fn from(url: &str) -> Self {
+        NetworkRpc::Url(url.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RpcEndpoint {
+    pub url: String,
+
+    /// Share of the requests this endpoint gets compared to the other endpoints with the same
+    /// priority. Defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+
+    /// Endpoints with a lower priority are used first, higher ones only take requests when every
+    /// endpoint before them is unhealthy. Defaults to 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+
+    /// Overrides the network `max_block_range` for this endpoint.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string_or_num",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub max_block_range: Option<U64>,
+}
+
+impl RpcEndpoint {
+    pub fn new(url: String) -> Self {
+        Self { url, weight: None, priority: None, max_block_range: None }
+    }
+}
+
+/// The `Authorization` header of a network's rpc requests.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcAuth {
+    /// `Authorization: Bearer <token>`.
+    Bearer(String),
+    /// `Authorization: Basic <base64 of username:password>`.
+    Basic { username: String, password: String },
+}
+
+impl RpcAuth {
+    pub fn authorization(&self) -> Authorization {
+        match self {
+            RpcAuth::Bearer(token) => Authorization::bearer(token),
+            RpcAuth::Basic { username, password } => Authorization::basic(username, password),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RpcHeaderError {
+    #[error("Invalid rpc header name {0}")]
+    InvalidName(String),
+
+    #[error("Invalid value for rpc header {0}")]
+    InvalidValue(String),
+}
+
+/// Adds the custom headers and the `Authorization` header of `auth` to `headers`, replacing any
+/// header of the same name.
+pub fn rpc_headers(
+    mut headers: HeaderMap,
+    custom_headers: impl IntoIterator<Item = (String, String)>,
+    auth: Option<&RpcAuth>,
+) -> Result<HeaderMap, RpcHeaderError> {
+    for (name, value) in custom_headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| RpcHeaderError::InvalidName(name.clone()))?;
+        let header_value =
+            HeaderValue::from_str(&value).map_err(|_| RpcHeaderError::InvalidValue(name))?;
+        headers.insert(header_name, header_value);
+    }
+
+    if let Some(auth) = auth {
+        let mut header_value = HeaderValue::from_str(&auth.authorization().to_string())
+            .map_err(|_| RpcHeaderError::InvalidValue(AUTHORIZATION.to_string()))?;
+        header_value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, header_value);
+    }
+
+    Ok(headers)
+}
+
+/// What live indexing subscribes to over a websocket rpc endpoint instead of polling for blocks.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveSubscription {
+    /// `eth_subscribe("newHeads")`, live indexing checks for logs on every new block.
+    NewHeads,
+    /// `eth_subscribe("logs")` with the event filter, live indexing only checks for logs once
+    /// the node has seen a matching one.
+    Logs,
+    /// Keep polling for new blocks.
+    Disabled,
+}
+
+/// How the logs of a network's events are fetched.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogsSource {
+    /// `eth_getLogs` over block ranges.
+    #[default]
+    GetLogs,
+    /// The receipts of every block in the range through `eth_getBlockReceipts`, for chains where
+    /// `eth_getLogs` is capped to tiny ranges or returns incomplete results. Blocks whose logs
+    /// bloom can not hold a matching log are skipped.
+    BlockReceipts,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub enum AddressFiltering {
     InMemory,
@@ -188,6 +418,69 @@ impl<'de> Deserialize<'de> for BlockPollFrequency {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReorgSafeDistance {
+    /// Index up to the block the node reports with the `safe` tag.
+    Safe,
+    /// Index up to the block the node reports with the `finalized` tag.
+    Finalized,
+    /// Stay a fixed number of blocks behind the latest block.
+    Blocks(u64),
+}
+
+impl Serialize for ReorgSafeDistance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ReorgSafeDistance::Safe => serializer.serialize_str("safe"),
+            ReorgSafeDistance::Finalized => serializer.serialize_str("finalized"),
+            ReorgSafeDistance::Blocks(blocks) => serializer.serialize_u64(*blocks),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ReorgSafeDistance {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ReorgSafeDistanceVisitor;
+
+        impl<'de> Visitor<'de> for ReorgSafeDistanceVisitor {
+            type Value = ReorgSafeDistance;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("`safe`, `finalized` or a number of blocks")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<ReorgSafeDistance, E>
+            where
+                E: de::Error,
+            {
+                Ok(ReorgSafeDistance::Blocks(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ReorgSafeDistance, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "safe" => Ok(ReorgSafeDistance::Safe),
+                    "finalized" => Ok(ReorgSafeDistance::Finalized),
+                    _ => {
+                        let blocks = value.parse::<u64>().map_err(E::custom)?;
+                        Ok(ReorgSafeDistance::Blocks(blocks))
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_str(ReorgSafeDistanceVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use reth::cli::Commands;
@@ -209,12 +502,122 @@ mod tests {
 
         assert_eq!(network.name, "ethereum");
         assert_eq!(network.chain_id, 1);
-        assert_eq!(network.rpc, "https://mainnet.gateway.tenderly.co");
+        assert_eq!(network.rpc, NetworkRpc::Url("https://mainnet.gateway.tenderly.co".to_string()));
         assert_eq!(network.max_block_range, None);
         assert_eq!(network.compute_units_per_second, None);
         assert_eq!(network.block_poll_frequency, None);
     }
 
+    #[test]
+    fn test_network_rpc_endpoints() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc:
+              - url: https://mainnet.gateway.tenderly.co
+                weight: 3
+                max_block_range: 10000
+              - url: https://eth.llamarpc.com
+                priority: 1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.rpc.primary_url(), "https://mainnet.gateway.tenderly.co");
+        assert_eq!(
+            network.rpc.endpoints(),
+            vec![
+                RpcEndpoint {
+                    url: "https://mainnet.gateway.tenderly.co".to_string(),
+                    weight: Some(3),
+                    priority: None,
+                    max_block_range: Some(U64::from(10000)),
+                },
+                RpcEndpoint {
+                    url: "https://eth.llamarpc.com".to_string(),
+                    weight: None,
+                    priority: Some(1),
+                    max_block_range: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_network_live_subscription() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: wss://mainnet.gateway.tenderly.co
+            live_subscription: logs
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.live_subscription, Some(LiveSubscription::Logs));
+    }
+
+    #[test]
+    fn test_network_logs_source() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: https://mainnet.gateway.tenderly.co
+            logs_source: block_receipts
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.logs_source, Some(LogsSource::BlockReceipts));
+    }
+
+    #[test]
+    fn test_network_rpc_headers() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: https://mainnet.gateway.tenderly.co
+            headers:
+              X-Api-Key: secret
+            auth:
+              basic:
+                username: user
+                password: pass
+            "#,
+        )
+        .unwrap();
+
+        let headers = network.rpc_headers(HeaderMap::new()).unwrap();
+        assert_eq!(headers.get("x-api-key").unwrap(), "secret");
+        assert_eq!(headers.get(AUTHORIZATION).unwrap(), "Basic dXNlcjpwYXNz");
+
+        let bearer = RpcAuth::Bearer("token".to_string());
+        let headers = rpc_headers(HeaderMap::new(), [], Some(&bearer)).unwrap();
+        assert_eq!(headers.get(AUTHORIZATION).unwrap(), "Bearer token");
+
+        assert!(rpc_headers(HeaderMap::new(), [("bad header".to_string(), "1".to_string())], None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_network_logs_archive() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: https://mainnet.gateway.tenderly.co
+            logs_archive: ./archive/ethereum
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.logs_archive.as_deref(), Some("./archive/ethereum"));
+    }
+
     #[test]
     fn test_network_block_poll_frequency() {
         let network: Network = serde_yaml::from_str(
@@ -272,6 +675,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_network_reorg_safe_distance() {
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: https://mainnet.gateway.tenderly.co
+            reorg_safe_distance: finalized
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.reorg_safe_distance, Some(ReorgSafeDistance::Finalized));
+
+        let network: Network = serde_yaml::from_str(
+            r#"
+            name: base
+            chain_id: 8453
+            rpc: https://mainnet.base.org
+            reorg_safe_distance: 20
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(network.reorg_safe_distance, Some(ReorgSafeDistance::Blocks(20)));
+        assert_eq!(serde_yaml::to_string(&ReorgSafeDistance::Blocks(20)).unwrap(), "20\n");
+        assert_eq!(serde_yaml::to_string(&ReorgSafeDistance::Safe).unwrap(), "safe\n");
+
+        let network: Result<Network, _> = serde_yaml::from_str(
+            r#"
+            name: ethereum
+            chain_id: 1
+            rpc: https://mainnet.gateway.tenderly.co
+            reorg_safe_distance: latest
+            "#,
+        );
+
+        assert!(network.is_err());
+    }
+
     #[test]
     fn test_network_with_reth_config() {
         let network: Network = serde_yaml::from_str(
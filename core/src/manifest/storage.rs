@@ -87,6 +87,10 @@ pub struct PostgresDetails {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_create_tables: Option<bool>,
+
+    /// Commit the event rows and the `last_synced_block` checkpoint in the same transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atomic_checkpoint: Option<bool>,
 }
 
 fn default_csv_path() -> String {
@@ -156,6 +160,15 @@ impl Storage {
         self.postgres.as_ref().is_some_and(|details| details.drop_each_run.unwrap_or_default())
     }
 
+    pub fn postgres_atomic_checkpoint(&self) -> bool {
+        let enabled = self.postgres_enabled();
+        if !enabled {
+            return false;
+        }
+
+        self.postgres.as_ref().is_some_and(|details| details.atomic_checkpoint.unwrap_or_default())
+    }
+
     pub fn csv_enabled(&self) -> bool {
         match &self.csv {
             Some(details) => details.enabled,
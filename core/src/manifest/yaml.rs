@@ -1,43 +1,30 @@
 use std::{
     collections::HashSet,
-    env,
     fs::File,
     io::{Read, Write},
     path::{Path, PathBuf},
 };
 
-use alloy::rpc::types::ValueOrArray;
-use regex::{Captures, Regex};
+use alloy::{rpc::types::ValueOrArray, transports::http::reqwest::header::HeaderMap};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use tracing::error;
 
 use crate::{
-    abi::ABIItem,
-    helpers::{load_env_from_full_path, replace_env_variable_to_raw_name},
+    abi::{ABIItem, ReadAbiError},
+    helpers::{
+        load_env_from_full_path, replace_env_variable_to_raw_name, substitute_env_variables,
+    },
     manifest::{
+        contract::ParseAbiError,
         core::{Manifest, ProjectType},
-        network::Network,
+        native_transfer::TraceProcessingMethod,
+        network::{ChainProfile, LogsSource, Network, RpcHeaderError},
     },
     StringOrArray,
 };
 
 pub const YAML_CONFIG_NAME: &str = "rindexer.yaml";
 
-fn substitute_env_variables(contents: &str) -> Result<String, regex::Error> {
-    let re = Regex::new(r"\$\{([^}]+)\}")?;
-    let result = re.replace_all(contents, |caps: &Captures| {
-        let var_name = &caps[1];
-        match env::var(var_name) {
-            Ok(val) => val,
-            Err(_) => {
-                error!("Environment variable {} not found", var_name);
-                panic!("Environment variable {var_name} not found")
-            }
-        }
-    });
-    Ok(result.into_owned())
-}
-
 #[derive(thiserror::Error, Debug)]
 pub enum ValidateManifestError {
     #[error("Contract names {0} must be unique")]
@@ -64,6 +51,12 @@ pub enum ValidateManifestError {
     #[error("Indexed filter defined more than allowed for event {0} for contract {1} - indexed expected: {2} defined: {3}")]
     IndexedFilterDefinedMoreThanAllowed(String, String, usize, usize),
 
+    #[error("Indexed filter defined more than once for event {0} for contract {1} - only one indexed filter per event is applied")]
+    IndexedFilterDefinedMoreThanOnce(String, String),
+
+    #[error("Indexed filter for event {0} for contract {1} would never be applied as the event is not indexed on network {2}")]
+    IndexedFilterEventNotIndexed(String, String, String),
+
     #[error("Relationship contract {0} not found")]
     RelationshipContractNotFound(String),
 
@@ -75,6 +68,48 @@ pub enum ValidateManifestError {
 
     #[error("Global ABI can only be a single string")]
     GlobalAbiCanOnlyBeASingleString(String),
+
+    #[error("Network {0} must have at least one rpc endpoint")]
+    NetworkRpcEndpointsEmpty(String),
+
+    #[error("Network {0} can not use logs_source block_receipts with disable_logs_bloom_checks, the blocks to fetch receipts for are picked by their logs bloom")]
+    BlockReceiptsNeedLogsBloom(String),
+
+    #[error("Network {0} has store_block_headers enabled which needs postgres storage enabled")]
+    StoreBlockHeadersNeedPostgres(String),
+
+    #[error("Network {0} has invalid rpc headers: {1}")]
+    InvalidRpcHeaders(String, RpcHeaderError),
+
+    #[error("Native transfers on network {0} use {1:?} which the chain_profile of the network does not list in its trace_methods")]
+    TraceMethodNotSupported(String, TraceProcessingMethod),
+
+    #[error("Event {0} in on_error for contract {1} not found in ABI")]
+    OnErrorEventNotFoundInABI(String, String),
+
+    #[error("on_error for contract {0} must allow at least one attempt")]
+    OnErrorMaxAttemptsZero(String),
+
+    #[error("Function {0} in functions for contract {1} not found in ABI")]
+    FunctionNotFoundInABI(String, String),
+
+    #[error("Function {0} in functions for contract {1} is overloaded in the ABI, use the signature of the one to index: {2}")]
+    FunctionOverloadedInABI(String, String, String),
+
+    #[error("Function {0} for contract {1} is indexed as event {2} which the ABI already has")]
+    FunctionCallEventNameConflict(String, String, String),
+
+    #[error("Function {0} for contract {1} can not be traced with {3:?} on network {2} - functions need trace_block or debug_traceBlockByNumber and the chain_profile of the network has to list it in its trace_methods")]
+    FunctionTraceMethodNotSupported(String, String, String, TraceProcessingMethod),
+
+    #[error("Contract deployments on network {0} can not be found with {1:?} - they need trace_block or debug_traceBlockByNumber and the chain_profile of the network has to list it in its trace_methods")]
+    ContractDeploymentsTraceMethodNotSupported(String, TraceProcessingMethod),
+
+    #[error("Contract deployments are only indexed in no-code projects")]
+    ContractDeploymentsNotSupportedInRustProjects,
+
+    #[error("Factory for contract {0} is invalid: {1}")]
+    InvalidFactory(String, String),
 }
 
 fn validate_manifest(
@@ -94,6 +129,66 @@ fn validate_manifest(
         ));
     }
 
+    for network in &manifest.networks {
+        if network.rpc.endpoints().is_empty() {
+            return Err(ValidateManifestError::NetworkRpcEndpointsEmpty(network.name.clone()));
+        }
+
+        if network.logs_source == Some(LogsSource::BlockReceipts)
+            && network.disable_logs_bloom_checks.unwrap_or_default()
+        {
+            return Err(ValidateManifestError::BlockReceiptsNeedLogsBloom(network.name.clone()));
+        }
+
+        if network.store_block_headers.unwrap_or_default() && !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::StoreBlockHeadersNeedPostgres(network.name.clone()));
+        }
+
+        network
+            .rpc_headers(HeaderMap::new())
+            .map_err(|e| ValidateManifestError::InvalidRpcHeaders(network.name.clone(), e))?;
+    }
+
+    for native_transfer in manifest.native_transfers.networks.iter().flatten() {
+        let Some(network) = manifest.networks.iter().find(|n| n.name == native_transfer.network)
+        else {
+            continue;
+        };
+
+        let chain_profile =
+            ChainProfile::for_chain(network.chain_id, network.chain_profile.as_ref());
+        if !chain_profile.supports_trace_method(native_transfer.method) {
+            return Err(ValidateManifestError::TraceMethodNotSupported(
+                network.name.clone(),
+                native_transfer.method,
+            ));
+        }
+    }
+
+    if manifest.has_enabled_contract_deployments() {
+        if manifest.project_type == ProjectType::Rust {
+            return Err(ValidateManifestError::ContractDeploymentsNotSupportedInRustProjects);
+        }
+
+        for deployment in manifest.contract_deployments.networks.iter().flatten() {
+            let Some(network) = manifest.networks.iter().find(|n| n.name == deployment.network)
+            else {
+                continue;
+            };
+
+            let chain_profile =
+                ChainProfile::for_chain(network.chain_id, network.chain_profile.as_ref());
+            if deployment.method == TraceProcessingMethod::EthGetBlockByNumber
+                || !chain_profile.supports_trace_method(deployment.method)
+            {
+                return Err(ValidateManifestError::ContractDeploymentsTraceMethodNotSupported(
+                    network.name.clone(),
+                    deployment.method,
+                ));
+            }
+        }
+    }
+
     for contract in &manifest.contracts {
         if contract.name.to_lowercase().contains("filter") {
             return Err(ValidateManifestError::ContractNameCanNotIncludeFilter(
@@ -101,8 +196,52 @@ fn validate_manifest(
             ));
         }
 
-        let events = ABIItem::read_abi_items(project_path, contract)
-            .map_err(|e| ValidateManifestError::InvalidABI(contract.name.clone(), e.to_string()))?;
+        let events = ABIItem::read_abi_items(project_path, contract).map_err(|e| match e {
+            ReadAbiError::ParseAbiError(ParseAbiError::FunctionNotFoundInAbi(function)) => {
+                ValidateManifestError::FunctionNotFoundInABI(function, contract.name.clone())
+            }
+            ReadAbiError::ParseAbiError(ParseAbiError::FunctionOverloadedInAbi(
+                function,
+                signatures,
+            )) => ValidateManifestError::FunctionOverloadedInABI(
+                function,
+                contract.name.clone(),
+                signatures,
+            ),
+            e => ValidateManifestError::InvalidABI(contract.name.clone(), e.to_string()),
+        })?;
+
+        for function in contract.functions.iter().flatten() {
+            let event_name = function.event_name();
+            if events.iter().filter(|e| e.name == event_name && e.type_ == "event").count() > 1 {
+                return Err(ValidateManifestError::FunctionCallEventNameConflict(
+                    function.name.clone(),
+                    contract.name.clone(),
+                    event_name,
+                ));
+            }
+
+            for detail in &contract.details {
+                let Some(network) = manifest.networks.iter().find(|n| n.name == detail.network)
+                else {
+                    continue;
+                };
+
+                let method = function.method();
+                let chain_profile =
+                    ChainProfile::for_chain(network.chain_id, network.chain_profile.as_ref());
+                if method == TraceProcessingMethod::EthGetBlockByNumber
+                    || !chain_profile.supports_trace_method(method)
+                {
+                    return Err(ValidateManifestError::FunctionTraceMethodNotSupported(
+                        function.name.clone(),
+                        contract.name.clone(),
+                        network.name.clone(),
+                        method,
+                    ));
+                }
+            }
+        }
 
         for detail in &contract.details {
             let has_network = manifest.networks.iter().any(|n| n.name == detail.network);
@@ -140,8 +279,42 @@ fn validate_manifest(
                 }
             }
 
+            if let Some(factory) = &detail.factory {
+                factory.factory_details(project_path, None).map_err(|e| {
+                    ValidateManifestError::InvalidFactory(contract.name.clone(), e.to_string())
+                })?;
+            }
+
             if let Some(indexed_filters) = &detail.indexed_filters {
+                // filter mode only indexes its filter events, the other modes every included one
+                let indexed_events: Option<Vec<&String>> = match &detail.filter {
+                    Some(ValueOrArray::Value(filter)) => Some(vec![&filter.event_name]),
+                    Some(ValueOrArray::Array(filters)) => {
+                        Some(filters.iter().map(|filter| &filter.event_name).collect())
+                    }
+                    None => contract.include_events.as_ref().map(|events| events.iter().collect()),
+                };
+
+                let mut seen_events = HashSet::new();
                 for indexed_filter in indexed_filters.iter() {
+                    if !seen_events.insert(&indexed_filter.event_name) {
+                        return Err(ValidateManifestError::IndexedFilterDefinedMoreThanOnce(
+                            indexed_filter.event_name.clone(),
+                            contract.name.clone(),
+                        ));
+                    }
+
+                    if indexed_events
+                        .as_ref()
+                        .is_some_and(|events| !events.contains(&&indexed_filter.event_name))
+                    {
+                        return Err(ValidateManifestError::IndexedFilterEventNotIndexed(
+                            indexed_filter.event_name.clone(),
+                            contract.name.clone(),
+                            detail.network.clone(),
+                        ));
+                    }
+
                     let event = events.iter().find(|e| e.name == indexed_filter.event_name);
                     if let Some(event) = event {
                         let indexed_allowed_length =
@@ -182,6 +355,25 @@ fn validate_manifest(
             }
         }
 
+        if let Some(on_error) = &contract.on_error {
+            let events_on_error = on_error.events.iter().flatten();
+            if std::iter::once(&on_error.default)
+                .chain(events_on_error.clone().map(|(_, event_on_error)| event_on_error))
+                .any(|on_error| on_error.max_attempts == Some(0))
+            {
+                return Err(ValidateManifestError::OnErrorMaxAttemptsZero(contract.name.clone()));
+            }
+
+            for (event, _) in events_on_error {
+                if !events.iter().any(|e| e.name == *event && e.type_ == "event") {
+                    return Err(ValidateManifestError::OnErrorEventNotFoundInABI(
+                        event.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+            }
+        }
+
         if let Some(_dependency_events) = &contract.dependency_events {
             // TODO - validate the events all exist in the contract ABIs
         }
@@ -303,25 +495,31 @@ pub fn read_manifest(file_path: &PathBuf) -> Result<Manifest, ReadManifestError>
 
     let mut manifest_after_transform: Manifest = serde_yaml::from_str(&contents)?;
 
-    // Assign networks to the Native Transfer if opted into without defining networks.
-    // We treat None as "All available".
+    // Assign networks to the Native Transfer and Contract Deployments if opted into without
+    // defining networks. We treat None as "All available".
     manifest_after_transform.set_native_transfer_networks();
+    manifest_after_transform.set_contract_deployment_networks();
 
     // as we don't want to inject the RPC URL in rust projects in clear text we should change
-    // the networks.rpc back to what it was before and the generated code will handle it
+    // the networks.rpc back to what it was before and the generated code will handle it, the
+    // same goes for the rpc headers and auth
     if manifest_after_transform.project_type == ProjectType::Rust {
         let manifest_networks_only: ManifestNetworksOnly =
             serde_yaml::from_str(&contents_before_transform)?;
 
         for network in &mut manifest_after_transform.networks {
-            network.rpc = manifest_networks_only
+            let raw_network = manifest_networks_only
                 .networks
                 .iter()
                 .find(|n| n.name == network.name)
-                .map_or_else(
-                    || replace_env_variable_to_raw_name(&network.rpc),
-                    |n| replace_env_variable_to_raw_name(&n.rpc),
-                );
+                .unwrap_or(&*network)
+                .clone();
+
+            // headers and auth can embed env variables anywhere in their values, they are kept as
+            // written and substituted when the generated code runs
+            network.rpc = raw_network.rpc.map_urls(replace_env_variable_to_raw_name);
+            network.headers = raw_network.headers;
+            network.auth = raw_network.auth;
         }
     }
 
@@ -1,51 +1,80 @@
 use crate::notifications::ChainStateNotification;
-use alloy::network::{AnyNetwork, AnyRpcBlock, AnyTransactionReceipt};
+use alloy::consensus::Transaction;
+use alloy::network::{
+    AnyNetwork, AnyRpcBlock, AnyRpcTransaction, AnyTransactionReceipt, TransactionResponse,
+};
+use alloy::rpc::json_rpc::RpcRecv;
 use alloy::rpc::types::{Filter, ValueOrArray};
 use alloy::{
     eips::{BlockId, BlockNumberOrTag},
-    primitives::{Address, Bytes, TxHash, U256, U64},
+    primitives::{Address, BlockHash, Bytes, TxHash, U256, U64},
     providers::{
         ext::TraceApi,
         fillers::{BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller},
-        Identity, IpcConnect, Provider, ProviderBuilder, RootProvider,
+        Identity, IpcConnect, Provider, ProviderBuilder, RootProvider, WsConnect,
     },
+    pubsub::PubSubConnect,
     rpc::{
         client::RpcClient,
         types::{
             trace::parity::{
-                Action, CallAction, CallType, LocalizedTransactionTrace, TransactionTrace,
+                Action, CallAction, CallType, CreateAction, CreateOutput, CreationMethod,
+                LocalizedTransactionTrace, TraceOutput, TransactionTrace,
             },
             Log,
         },
     },
     transports::{
         http::{
-            reqwest::{header::HeaderMap, Client, Error as ReqwestError},
+            reqwest::{
+                header::{HeaderMap, AUTHORIZATION},
+                Client, Error as ReqwestError,
+            },
             Http,
         },
         layers::RetryBackoffLayer,
-        RpcError, TransportErrorKind,
+        Authorization, BoxTransport, RpcError, TransportErrorKind,
     },
 };
-use alloy_chains::{Chain, NamedChain};
-use futures::future::try_join_all;
+use alloy_chains::Chain;
+use futures::{
+    future::try_join_all,
+    stream::{self, StreamExt, TryStreamExt},
+};
+use mini_moka::sync::Cache;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::future::IntoFuture;
 use std::{
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
     time::{Duration, Instant},
 };
 use thiserror::Error;
-use tokio::sync::{broadcast::Sender, Mutex};
+use tokio::sync::{broadcast::Sender, mpsc::UnboundedSender, Mutex};
 use tokio::task::JoinError;
-use tracing::{debug, debug_span, error, Instrument};
+use tracing::{debug, debug_span, error, warn, Instrument};
 use url::Url;
 
-use crate::helpers::chunk_hashset;
-use crate::manifest::network::{AddressFiltering, BlockPollFrequency};
-use crate::{event::RindexerEventFilter, manifest::core::Manifest};
+use crate::compute_units::{ComputeUnitLayer, ComputeUnitLimiter};
+use crate::helpers::{chunk_hashset, is_relevant_block};
+use crate::learned_block_ranges::LearnedBlockRanges;
+use crate::log_source::FileLogSource;
+use crate::manifest::native_transfer::TraceProcessingMethod;
+use crate::manifest::network::{
+    AddressFiltering, BlockPollFrequency, ChainProfile, LiveSubscription, LogsSource,
+    ReorgSafeDistance, RpcEndpoint, RpcHeaderError,
+};
+use crate::rpc_cassette::{rpc_cassette, RpcCassetteError, RpcCassetteTransport};
+use crate::rpc_endpoints::{route, HealthTrackedTransport, RpcEndpointState, RpcRouter};
+use crate::ws_subscriptions::WsSubscriptions;
+use crate::{
+    event::{callback_registry::TxContext, function_call::FunctionCallFilter, RindexerEventFilter},
+    manifest::core::Manifest,
+};
 
 /// An alias type for a complex alloy Provider
 pub type RindexerProvider = FillProvider<
@@ -69,21 +98,68 @@ pub const RPC_CHUNK_SIZE: usize = 1000;
 /// See: https://www.alchemy.com/docs/best-practices-when-using-alchemy#2-avoid-high-batch-cardinality
 pub const RECOMMENDED_RPC_CHUNK_SIZE: usize = 50;
 
+/// How many block timestamps are kept in memory, logs for the same block are usually spread over
+/// a few consecutive batches so this only has to cover the recent blocks.
+const BLOCK_TIMESTAMP_CACHE_CAPACITY: u64 = 10_000;
+
+/// The block range of a request when logs come from block receipts and the network has no
+/// `max_block_range`, every block in it is at least a header to fetch.
+const BLOCK_RECEIPTS_MAX_BLOCK_RANGE: u64 = 500;
+
 #[derive(Debug)]
 pub struct JsonRpcCachedProvider {
     provider: Arc<RindexerProvider>,
     client: RpcClient,
+    endpoints: Vec<RpcEndpointClient>,
+    ws_subscriptions: Option<Arc<WsSubscriptions>>,
     cache: Mutex<Option<(Instant, Arc<AnyRpcBlock>)>>,
+    block_timestamp_cache: Cache<BlockHash, u64>,
+    block_header_sink: OnceLock<UnboundedSender<BlockHeader>>,
+    logs_archive: OnceLock<Arc<FileLogSource>>,
+    logs_source: LogsSource,
+    // set once the node answered that it has no `eth_getBlockReceipts`
+    block_receipts_unsupported: AtomicBool,
     is_zk_chain: bool,
+    chain_profile: ChainProfile,
     #[allow(unused)]
     chain_id: u64,
     chain: Chain,
     block_poll_frequency: Option<BlockPollFrequency>,
     address_filtering: Option<AddressFiltering>,
+    reorg_safe_distance: Option<ReorgSafeDistance>,
     pub max_block_range: Option<U64>,
     pub chain_state_notification: Option<Sender<ChainStateNotification>>,
 }
 
+/// One of the rpc endpoints of a network, with a provider which only sends requests to it.
+#[derive(Debug, Clone)]
+pub struct RpcEndpointClient {
+    pub(crate) state: Arc<RpcEndpointState>,
+    provider: Arc<RindexerProvider>,
+}
+
+/// The parts of a block header rindexer keeps in `rindexer_internal.blocks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: U64,
+    pub hash: BlockHash,
+    pub parent_hash: BlockHash,
+    pub timestamp: u64,
+    pub base_fee: Option<u64>,
+}
+
+impl From<&AnyRpcBlock> for BlockHeader {
+    fn from(block: &AnyRpcBlock) -> Self {
+        Self {
+            number: U64::from(block.header.number),
+            hash: block.header.hash,
+            parent_hash: block.header.parent_hash,
+            timestamp: block.header.timestamp,
+            base_fee: block.header.base_fee_per_gas,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ProviderError {
     #[error("Failed to make rpc request: {0}")]
@@ -124,6 +200,9 @@ pub struct TraceCall {
     pub value: U256,
     #[serde(rename = "type")]
     pub typ: String,
+    /// The returned data, for `CREATE` and `CREATE2` frames the deployed code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
     #[serde(default)]
     pub calls: Vec<TraceCall>,
 }
@@ -137,58 +216,21 @@ pub struct TraceCallFrame {
     pub result: TraceCall,
 }
 
-/// A faster than network-call method for determining if a chain is Zk-Rollup.
-fn is_known_zk_evm_compatible_chain(chain: Chain) -> Option<bool> {
-    if let Some(name) = chain.named() {
-        match name {
-            // Known zkEVM-compatible chains
-            NamedChain::Lens
-            | NamedChain::ZkSync
-            | NamedChain::Sophon
-            | NamedChain::Abstract
-            | NamedChain::Scroll
-            | NamedChain::PolygonZkEvm
-            | NamedChain::Linea => Some(true),
-
-            // Known non-zkEVM chains
-            NamedChain::Mainnet
-            | NamedChain::Sepolia
-            | NamedChain::Arbitrum
-            | NamedChain::Soneium
-            | NamedChain::Avalanche
-            | NamedChain::Polygon
-            | NamedChain::Hyperliquid
-            | NamedChain::Blast
-            | NamedChain::World
-            | NamedChain::Unichain
-            | NamedChain::Base
-            | NamedChain::Optimism
-            | NamedChain::ApeChain
-            | NamedChain::BinanceSmartChain
-            | NamedChain::Fantom
-            | NamedChain::Cronos
-            | NamedChain::Gnosis => Some(false),
-
-            // Fallback for unknown chains
-            _ => None,
-        }
-    } else {
-        None
-    }
-}
-
 impl JsonRpcCachedProvider {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         provider: RindexerProvider,
         chain_id: u64,
+        chain_profile: ChainProfile,
         client: RpcClient,
         block_poll_frequency: Option<BlockPollFrequency>,
         max_block_range: Option<U64>,
         address_filtering: Option<AddressFiltering>,
+        reorg_safe_distance: Option<ReorgSafeDistance>,
         chain_state_notification: Option<Sender<ChainStateNotification>>,
     ) -> Self {
         let chain = Chain::from(chain_id);
-        let is_zk_chain = match is_known_zk_evm_compatible_chain(chain) {
+        let is_zk_chain = match chain_profile.is_zk() {
             Some(zk) => zk,
             None => {
                 let response: Result<String, _> =
@@ -202,20 +244,83 @@ impl JsonRpcCachedProvider {
             }
         };
 
+        let provider = Arc::new(provider);
+        let endpoint = RpcEndpointClient {
+            state: Arc::new(RpcEndpointState::new(
+                &RpcEndpoint::new(String::new()),
+                max_block_range,
+                false,
+            )),
+            provider: Arc::clone(&provider),
+        };
+
         JsonRpcCachedProvider {
-            provider: Arc::new(provider),
+            provider,
+            endpoints: vec![endpoint],
+            ws_subscriptions: None,
             cache: Mutex::new(None),
+            block_timestamp_cache: Cache::builder()
+                .max_capacity(BLOCK_TIMESTAMP_CACHE_CAPACITY)
+                .build(),
+            block_header_sink: OnceLock::new(),
+            logs_archive: OnceLock::new(),
+            logs_source: LogsSource::default(),
+            block_receipts_unsupported: AtomicBool::new(false),
             max_block_range,
             client,
             chain,
             chain_id,
             is_zk_chain,
+            chain_profile,
             block_poll_frequency,
             address_filtering,
+            reorg_safe_distance,
             chain_state_notification,
         }
     }
 
+    /// Replaces the endpoints `eth_getLogs` requests are spread over, by default that is only the
+    /// provider the cache was created with.
+    pub(crate) fn with_endpoints(mut self, endpoints: Vec<RpcEndpointClient>) -> Self {
+        if !endpoints.is_empty() {
+            self.endpoints = endpoints;
+        }
+        self
+    }
+
+    /// What rindexer knows about the chain, the bundled profile with the network's overrides.
+    pub fn chain_profile(&self) -> &ChainProfile {
+        &self.chain_profile
+    }
+
+    pub(crate) fn with_logs_source(mut self, logs_source: LogsSource) -> Self {
+        self.logs_source = logs_source;
+        self
+    }
+
+    pub(crate) fn with_ws_subscriptions(
+        mut self,
+        ws_subscriptions: Option<Arc<WsSubscriptions>>,
+    ) -> Self {
+        self.ws_subscriptions = ws_subscriptions;
+        self
+    }
+
+    /// The websocket subscriptions live indexing can wait on instead of polling, if the network
+    /// has a websocket endpoint.
+    pub(crate) fn ws_subscriptions(&self) -> Option<Arc<WsSubscriptions>> {
+        self.ws_subscriptions.clone()
+    }
+
+    /// The endpoint the next `eth_getLogs` request should be sent to, so the range it learns from
+    /// any error is kept against the endpoint which returned it.
+    pub(crate) fn logs_endpoint(&self) -> RpcEndpointClient {
+        let states =
+            self.endpoints.iter().map(|endpoint| Arc::clone(&endpoint.state)).collect::<Vec<_>>();
+        let index = route(&states).first().copied().unwrap_or_default();
+        self.endpoints[index].clone()
+    }
+
     /// Return a duration for block poll caching based on user configuration.
     fn block_poll_frequency(&self) -> Duration {
         let Some(block_poll_frequency) = self.block_poll_frequency else {
@@ -226,13 +331,13 @@ impl JsonRpcCachedProvider {
             BlockPollFrequency::Rapid => Duration::from_millis(50),
             BlockPollFrequency::PollRateMs { millis } => Duration::from_millis(millis),
             BlockPollFrequency::Division { divisor } => self
-                .chain
-                .average_blocktime_hint()
+                .chain_profile
+                .block_time()
                 .and_then(|t| t.checked_div(divisor))
                 .unwrap_or(Duration::from_millis(50)),
             BlockPollFrequency::RpcOptimized => self
-                .chain
-                .average_blocktime_hint()
+                .chain_profile
+                .block_time()
                 .and_then(|t| t.checked_div(3))
                 .map(|t| t.max(Duration::from_millis(500)))
                 .unwrap_or(Duration::from_millis(1000)),
@@ -249,8 +354,15 @@ impl JsonRpcCachedProvider {
         //
         // If we want to reduce RPC calls further at the cost of we could consider indexing delay we
         // could set this to block-time directly.
+        //
+        // A websocket subscription tells us about new blocks directly, which skips the cache.
         if let Some((timestamp, block)) = &*cache_guard {
-            if timestamp.elapsed() < cache_time {
+            let newer_head = self
+                .ws_subscriptions
+                .as_ref()
+                .and_then(|subscriptions| subscriptions.latest_head())
+                .is_some_and(|head| head > block.header.number);
+            if timestamp.elapsed() < cache_time && !newer_head {
                 return Ok(Some(Arc::clone(block)));
             }
         }
@@ -263,6 +375,13 @@ impl JsonRpcCachedProvider {
             .await?;
 
         if let Some(block) = latest_block {
+            let is_new_block = cache_guard
+                .as_ref()
+                .is_none_or(|(_, cached)| cached.header.hash != block.header.hash);
+            if is_new_block {
+                self.publish_block_header(&block);
+            }
+
             let arc_block = Arc::new(block);
             *cache_guard = Some((Instant::now(), Arc::clone(&arc_block)));
             return Ok(Some(arc_block));
@@ -279,6 +398,20 @@ impl JsonRpcCachedProvider {
         Ok(U64::from(number))
     }
 
+    /// Get the block number the node reports for a tag such as `safe` or `finalized`, this is
+    /// `None` if the node has no block for the tag yet.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_block_number_by_tag(
+        &self,
+        tag: BlockNumberOrTag,
+    ) -> Result<Option<U64>, ProviderError> {
+        let block = self.provider.get_block(BlockId::Number(tag)).await?;
+        if let Some(block) = &block {
+            self.publish_block_header(block);
+        }
+        Ok(block.map(|block| U64::from(block.header.number)))
+    }
+
     /// Prefer using `trace_block` where possible as it returns more information.
     ///
     /// The current ethers version does not allow batching, we should upgrade to alloy.
@@ -377,6 +510,38 @@ impl JsonRpcCachedProvider {
         Ok(traces)
     }
 
+    /// Every call made in the block with `debug_traceBlockByNumber`, internal calls included. Calls
+    /// carry the position and hash of their transaction and their trace address, and the error of
+    /// the call they were made from when that call reverted.
+    #[tracing::instrument(skip_all)]
+    pub async fn debug_trace_block_calls(
+        &self,
+        block_number: U64,
+    ) -> Result<Vec<LocalizedTransactionTrace>, ProviderError> {
+        let block = json!(serde_json::to_string_pretty(&block_number)?.replace("\"", ""));
+        let options = json!({ "tracer": "callTracer" });
+
+        let frames: Vec<TraceCallFrame> =
+            self.provider.raw_request("debug_traceBlockByNumber".into(), [block, options]).await?;
+
+        let mut traces = Vec::new();
+        for (position, frame) in frames.into_iter().enumerate() {
+            flatten_call_frame(
+                frame.result,
+                &CallFrameContext {
+                    tx_hash: frame.tx_hash,
+                    position: position as u64,
+                    block_number,
+                    parent_error: None,
+                },
+                vec![],
+                &mut traces,
+            );
+        }
+
+        Ok(traces)
+    }
+
     /// Request `trace_block` information. This currently does not support batched multi-calls.
     #[tracing::instrument(skip_all)]
     pub async fn trace_block(
@@ -439,22 +604,164 @@ impl JsonRpcCachedProvider {
             .collect::<Vec<_>>();
 
         let chunk_results: Vec<Result<Vec<AnyRpcBlock>, _>> = try_join_all(futures).await?;
-        let results = chunk_results
+        let results: Vec<AnyRpcBlock> = chunk_results
             .into_iter()
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
             .flatten()
             .collect();
+        for block in &results {
+            self.publish_block_header(block);
+        }
 
         Ok(results)
     }
 
+    /// Starts every endpoint of the network from the block range it learned before a restart and
+    /// persists what the endpoints learn from here on.
+    pub(crate) fn use_learned_block_ranges(
+        &self,
+        learned_block_ranges: &Arc<LearnedBlockRanges>,
+        network: &str,
+    ) {
+        for endpoint in &self.endpoints {
+            endpoint.state.use_learned_block_ranges(Arc::clone(learned_block_ranges), network);
+        }
+    }
+
+    /// Serve the historic logs of the blocks `archive` holds from it instead of rpc. Only the
+    /// first archive registered is kept.
+    pub fn set_logs_archive(&self, archive: Arc<FileLogSource>) {
+        if self.logs_archive.set(archive).is_err() {
+            debug!("Logs archive already registered for chain {}", self.chain_id);
+        }
+    }
+
+    pub(crate) fn logs_archive(&self) -> Option<Arc<FileLogSource>> {
+        self.logs_archive.get().cloned()
+    }
+
+    /// Send every block header this provider fetches to `sink`, used to fill the internal
+    /// blocks table. Only the first sink registered is kept.
+    pub fn set_block_header_sink(&self, sink: UnboundedSender<BlockHeader>) {
+        if self.block_header_sink.set(sink).is_err() {
+            debug!("Block header sink already registered for chain {}", self.chain_id);
+        }
+    }
+
+    /// Whether the headers this provider fetches are kept in the internal blocks table.
+    pub fn stores_block_headers(&self) -> bool {
+        self.block_header_sink.get().is_some()
+    }
+
+    fn publish_block_header(&self, block: &AnyRpcBlock) {
+        if let Some(sink) = self.block_header_sink.get() {
+            let _ = sink.send(BlockHeader::from(block));
+        }
+    }
+
+    /// Resolves the timestamp of each `(block_number, block_hash)` pair. Blocks seen before are
+    /// served from memory and the rest are fetched in one `eth_getBlockByNumber` batch.
+    #[tracing::instrument(skip_all, fields(len = blocks.len()))]
+    pub async fn get_block_timestamps(
+        &self,
+        blocks: &[(U64, BlockHash)],
+    ) -> Result<HashMap<BlockHash, u64>, ProviderError> {
+        let mut timestamps = HashMap::with_capacity(blocks.len());
+        let mut missing = Vec::new();
+
+        for (block_number, block_hash) in blocks {
+            if timestamps.contains_key(block_hash) {
+                continue;
+            }
+
+            match self.block_timestamp_cache.get(block_hash) {
+                Some(timestamp) => {
+                    timestamps.insert(*block_hash, timestamp);
+                }
+                None => missing.push((*block_number, *block_hash)),
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(timestamps);
+        }
+
+        let mut block_numbers = missing.iter().map(|(number, _)| *number).collect::<Vec<_>>();
+        block_numbers.sort();
+        block_numbers.dedup();
+
+        let fetched = self.get_block_by_number_batch(&block_numbers, false).await?;
+        let fetched_by_number = fetched
+            .into_iter()
+            .map(|block| {
+                self.block_timestamp_cache.insert(block.header.hash, block.header.timestamp);
+                (block.header.number, block.header.timestamp)
+            })
+            .collect::<HashMap<_, _>>();
+
+        // keyed by the hash we were asked for, if the block was reorged in the meantime the
+        // canonical block at the same height is as close as we can get
+        for (block_number, block_hash) in missing {
+            if let Some(timestamp) = fetched_by_number.get(&block_number.to::<u64>()) {
+                timestamps.insert(block_hash, *timestamp);
+            }
+        }
+
+        Ok(timestamps)
+    }
+
     /// Fetch tx receipts in a batch rpc call
     #[tracing::instrument(skip_all)]
     pub async fn get_tx_receipts_batch(
         &self,
         hashes: &[TxHash],
     ) -> Result<Vec<AnyTransactionReceipt>, ProviderError> {
+        self.get_by_tx_hash_batch("eth_getTransactionReceipt", hashes).await
+    }
+
+    /// Fetch txs in a batch rpc call, a tx the node does not know is `None`
+    #[tracing::instrument(skip_all)]
+    pub async fn get_transactions_batch(
+        &self,
+        hashes: &[TxHash],
+    ) -> Result<Vec<Option<AnyRpcTransaction>>, ProviderError> {
+        self.get_by_tx_hash_batch("eth_getTransactionByHash", hashes).await
+    }
+
+    /// Fetch the [`TxContext`] of every tx, both the receipts and the txs are fetched in batches
+    /// so every tx costs two requests however often it shows up in the logs.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_tx_contexts(
+        &self,
+        hashes: &[TxHash],
+    ) -> Result<HashMap<TxHash, TxContext>, ProviderError> {
+        let (receipts, transactions) = tokio::try_join!(
+            self.get_tx_receipts_batch(hashes),
+            self.get_transactions_batch(hashes)
+        )?;
+
+        let values = transactions
+            .into_iter()
+            .flatten()
+            .map(|tx| (tx.tx_hash(), tx.value()))
+            .collect::<HashMap<_, _>>();
+
+        Ok(receipts
+            .into_iter()
+            .map(|receipt| {
+                let hash = receipt.transaction_hash;
+                let value = values.get(&hash).copied();
+                (hash, TxContext::new(&receipt, value))
+            })
+            .collect())
+    }
+
+    async fn get_by_tx_hash_batch<T: RpcRecv>(
+        &self,
+        method: &'static str,
+        hashes: &[TxHash],
+    ) -> Result<Vec<T>, ProviderError> {
         if hashes.is_empty() {
             return Ok(Vec::new());
         }
@@ -471,7 +778,7 @@ impl JsonRpcCachedProvider {
 
                     for hash in owned_chunk {
                         let call = batch.add_call(
-                            "eth_getTransactionReceipt",
+                            method,
                             &(
                                 hash,
                                 /* one element tuple from dangling comma */
@@ -481,7 +788,7 @@ impl JsonRpcCachedProvider {
                     }
 
                     if let Err(e) = batch.send().await {
-                        error!("Failed to send batch tx receipt request: {:?}", e);
+                        error!("Failed to send batch {} request: {:?}", method, e);
                         return Err(e);
                     }
 
@@ -490,8 +797,7 @@ impl JsonRpcCachedProvider {
             })
             .collect::<Vec<_>>();
 
-        let chunk_results: Vec<Result<Vec<AnyTransactionReceipt>, _>> =
-            try_join_all(futures).await?;
+        let chunk_results: Vec<Result<Vec<T>, _>> = try_join_all(futures).await?;
         let results = chunk_results
             .into_iter()
             .collect::<Result<Vec<_>, _>>()?
@@ -507,10 +813,36 @@ impl JsonRpcCachedProvider {
         &self,
         event_filter: &RindexerEventFilter,
     ) -> Result<Vec<Log>, ProviderError> {
+        self.get_logs_with_provider(&self.provider, event_filter).await
+    }
+
+    /// Get logs from a single endpoint rather than routing the request over all of them.
+    #[tracing::instrument(skip_all)]
+    pub(crate) async fn get_logs_from_endpoint(
+        &self,
+        endpoint: &RpcEndpointClient,
+        event_filter: &RindexerEventFilter,
+    ) -> Result<Vec<Log>, ProviderError> {
+        self.get_logs_with_provider(&endpoint.provider, event_filter).await
+    }
+
+    async fn get_logs_with_provider(
+        &self,
+        provider: &RindexerProvider,
+        event_filter: &RindexerEventFilter,
+    ) -> Result<Vec<Log>, ProviderError> {
+        if let Some(function_call) = event_filter.function_call() {
+            return self.get_logs_from_call_traces(event_filter, function_call).await;
+        }
+
+        if self.logs_source == LogsSource::BlockReceipts {
+            return self.get_logs_from_block_receipts(event_filter).await;
+        }
+
         let addresses = event_filter.contract_addresses().await;
 
         let base_filter = Filter::new()
-            .event_signature(event_filter.event_signature())
+            .event_signature(event_filter.event_signatures())
             .topic1(event_filter.topic1())
             .topic2(event_filter.topic2())
             .topic3(event_filter.topic3())
@@ -524,12 +856,13 @@ impl JsonRpcCachedProvider {
             Some(addresses) if addresses.is_empty() => Ok(vec![]),
             Some(addresses) => match self.address_filtering {
                 Some(AddressFiltering::InMemory) => {
-                    self.get_logs_for_address_in_memory(&base_filter, addresses).await
+                    self.get_logs_for_address_in_memory(provider, &base_filter, addresses).await
                 }
                 Some(AddressFiltering::MaxAddressPerGetLogsRequest(
                     max_address_per_get_logs_request,
                 )) => {
                     self.get_logs_for_address_in_batches(
+                        provider,
                         &base_filter,
                         addresses,
                         max_address_per_get_logs_request,
@@ -538,6 +871,7 @@ impl JsonRpcCachedProvider {
                 }
                 None => {
                     self.get_logs_for_address_in_batches(
+                        provider,
                         &base_filter,
                         addresses,
                         DEFAULT_RPC_SUPPORTED_ACCOUNT_FILTERS,
@@ -545,7 +879,7 @@ impl JsonRpcCachedProvider {
                     .await
                 }
             },
-            None => Ok(self.provider.get_logs(&base_filter).await?),
+            None => Ok(provider.get_logs(&base_filter).await?),
         }
 
         // rindexer_info!("get_logs DEBUG [{:?}]", filter.raw_filter());
@@ -560,10 +894,244 @@ impl JsonRpcCachedProvider {
         // Ok(result)
     }
 
+    /// Get the logs matching the filter out of the receipts of the blocks in its range. Blocks
+    /// whose logs bloom can not hold a matching log are skipped without fetching their receipts.
+    #[tracing::instrument(skip_all)]
+    async fn get_logs_from_block_receipts(
+        &self,
+        event_filter: &RindexerEventFilter,
+    ) -> Result<Vec<Log>, ProviderError> {
+        let addresses = event_filter.contract_addresses().await;
+        if addresses.as_ref().is_some_and(|addresses| addresses.is_empty()) {
+            return Ok(vec![]);
+        }
+
+        let event_signatures = event_filter.event_signatures();
+        let block_numbers = (event_filter.from_block().to::<u64>()
+            ..=event_filter.to_block().to::<u64>())
+            .map(U64::from)
+            .collect::<Vec<_>>();
+
+        let blocks = self
+            .get_block_by_number_batch(&block_numbers, false)
+            .await?
+            .into_iter()
+            .filter(|block| is_relevant_block(&addresses, &event_signatures, block))
+            .collect::<Vec<_>>();
+
+        let receipts = self.get_block_receipts_batch(&blocks).await?;
+
+        let logs = receipts
+            .iter()
+            .flat_map(|receipt| receipt.inner.inner.logs())
+            .filter(|log| event_filter.matches_log(&addresses, log))
+            .cloned()
+            .collect();
+
+        Ok(logs)
+    }
+
+    /// Get the calls of a function as logs of the event they are indexed as, out of the call
+    /// traces of every block in the range of the filter.
+    #[tracing::instrument(skip_all)]
+    pub(crate) async fn get_logs_from_call_traces(
+        &self,
+        event_filter: &RindexerEventFilter,
+        function_call: &FunctionCallFilter,
+    ) -> Result<Vec<Log>, ProviderError> {
+        let addresses = event_filter.contract_addresses().await;
+        if addresses.as_ref().is_some_and(|addresses| addresses.is_empty()) {
+            return Ok(vec![]);
+        }
+
+        let event_signature = event_filter.event_signature();
+        let block_numbers =
+            event_filter.from_block().to::<u64>()..=event_filter.to_block().to::<u64>();
+
+        let logs: Vec<Vec<Log>> = stream::iter(block_numbers)
+            .map(|block_number| {
+                let addresses = &addresses;
+                async move {
+                    let block_number = U64::from(block_number);
+                    let traces = match function_call.method {
+                        TraceProcessingMethod::TraceBlock => self.trace_block(block_number).await?,
+                        TraceProcessingMethod::DebugTraceBlockByNumber => {
+                            self.debug_trace_block_calls(block_number).await?
+                        }
+                        TraceProcessingMethod::EthGetBlockByNumber => {
+                            return Err(ProviderError::CustomError(
+                                "Function calls can only be indexed with trace_block or debug_traceBlockByNumber".to_string(),
+                            ));
+                        }
+                    };
+
+                    let mut logs = function_call.call_logs(event_signature, addresses, &traces);
+                    self.fill_call_logs_block_details(block_number, &mut logs).await?;
+
+                    Ok(logs)
+                }
+            })
+            .buffered(RECOMMENDED_RPC_CHUNK_SIZE / 5)
+            .try_collect()
+            .await?;
+
+        Ok(logs.into_iter().flatten().collect())
+    }
+
+    /// `debug_traceBlockByNumber` leaves out the block hash, and the hashes of the transactions on
+    /// zk chains, they are taken from the block when it has any logs.
+    async fn fill_call_logs_block_details(
+        &self,
+        block_number: U64,
+        logs: &mut [Log],
+    ) -> Result<(), ProviderError> {
+        if logs.iter().all(|log| log.block_hash.is_some() && log.transaction_hash.is_some()) {
+            return Ok(());
+        }
+
+        let block = self
+            .get_block_by_number_batch(&[block_number], false)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ProviderError::CustomError(format!("Block {block_number} not found")))?;
+
+        for log in logs {
+            log.block_hash.get_or_insert(block.header.hash);
+            if log.transaction_hash.is_none() {
+                log.transaction_hash = log
+                    .transaction_index
+                    .and_then(|index| block.transactions.hashes().nth(index as usize));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The traces of `debug_traceBlockByNumber` have the same gaps as the call logs, they are taken
+    /// from the block and a trace whose transaction still can not be found is an error.
+    pub async fn fill_traces_block_details(
+        &self,
+        block_number: U64,
+        traces: &mut [LocalizedTransactionTrace],
+    ) -> Result<(), ProviderError> {
+        if traces.iter().all(|trace| trace.block_hash.is_some() && trace.transaction_hash.is_some())
+        {
+            return Ok(());
+        }
+
+        let block = self
+            .get_block_by_number_batch(&[block_number], false)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ProviderError::CustomError(format!("Block {block_number} not found")))?;
+
+        for trace in traces {
+            trace.block_hash.get_or_insert(block.header.hash);
+            if trace.transaction_hash.is_none() {
+                trace.transaction_hash = trace
+                    .transaction_position
+                    .and_then(|position| block.transactions.hashes().nth(position as usize));
+            }
+            if trace.transaction_hash.is_none() {
+                return Err(ProviderError::CustomError(format!(
+                    "Transaction {:?} of block {block_number} not found for trace",
+                    trace.transaction_position
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the receipts of every block with `eth_getBlockReceipts`, nodes which do not serve
+    /// it get asked for the receipts of the block's transactions instead.
+    #[tracing::instrument(skip_all, fields(len = blocks.len()))]
+    async fn get_block_receipts_batch(
+        &self,
+        blocks: &[AnyRpcBlock],
+    ) -> Result<Vec<AnyTransactionReceipt>, ProviderError> {
+        if blocks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if !self.block_receipts_unsupported.load(Ordering::Relaxed) {
+            let block_numbers = blocks.iter().map(|block| block.header.number).collect::<Vec<_>>();
+
+            // block receipts responses are large, keep the batches small
+            let futures = block_numbers
+                .chunks(RECOMMENDED_RPC_CHUNK_SIZE / 5)
+                .map(|chunk| {
+                    let client = self.client.clone();
+                    let owned_chunk = chunk.to_vec();
+
+                    tokio::spawn(async move {
+                        let mut batch = client.new_batch();
+                        let mut request_futures = Vec::with_capacity(owned_chunk.len());
+
+                        for block_number in owned_chunk {
+                            let call = batch.add_call::<_, Option<Vec<AnyTransactionReceipt>>>(
+                                "eth_getBlockReceipts",
+                                &(BlockNumberOrTag::Number(block_number),),
+                            )?;
+                            request_futures.push(call)
+                        }
+
+                        if let Err(e) = batch.send().await {
+                            error!("Failed to send batch block receipts request: {:?}", e);
+                            return Err(e);
+                        }
+
+                        try_join_all(request_futures).await
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let chunk_results = try_join_all(futures).await?;
+            match chunk_results.into_iter().collect::<Result<Vec<_>, _>>() {
+                Ok(results) => {
+                    let mut receipts = Vec::new();
+                    for (block, block_receipts) in blocks.iter().zip(results.into_iter().flatten())
+                    {
+                        match block_receipts {
+                            Some(block_receipts) => receipts.extend(block_receipts),
+                            // the node does not have the receipts of the block yet, taking that as
+                            // no receipts would silently lose the events of the block
+                            None => {
+                                warn!(
+                                    "eth_getBlockReceipts returned no receipts for block {} on chain {}, fetching the receipts of its transactions instead",
+                                    block.header.number, self.chain_id
+                                );
+                                let hashes = block.transactions.hashes().collect::<Vec<_>>();
+                                receipts.extend(self.get_tx_receipts_batch(&hashes).await?);
+                            }
+                        }
+                    }
+                    return Ok(receipts);
+                }
+                // -32601 is the json-rpc "method not found" error
+                Err(e) if e.as_error_resp().is_some_and(|response| response.code == -32601) => {
+                    warn!(
+                        "Chain {} does not serve eth_getBlockReceipts, fetching the receipts of every transaction instead",
+                        self.chain_id
+                    );
+                    self.block_receipts_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let hashes =
+            blocks.iter().flat_map(|block| block.transactions.hashes()).collect::<Vec<_>>();
+        self.get_tx_receipts_batch(&hashes).await
+    }
+
     /// Get logs by chunking addresses and fetching asynchronously in batches
     #[tracing::instrument(skip_all)]
     async fn get_logs_for_address_in_batches(
         &self,
+        provider: &RindexerProvider,
         filter: &Filter,
         addresses: HashSet<Address>,
         chunk_size: usize,
@@ -573,7 +1141,7 @@ impl JsonRpcCachedProvider {
         let logs_futures = address_chunks.into_iter().map(|chunk| async move {
             let filter =
                 filter.clone().address(ValueOrArray::Array(chunk.into_iter().collect::<Vec<_>>()));
-            self.provider.get_logs(&filter).await
+            provider.get_logs(&filter).await
         });
 
         let chunked_logs = try_join_all(logs_futures).await?;
@@ -585,10 +1153,11 @@ impl JsonRpcCachedProvider {
     #[tracing::instrument(skip_all)]
     async fn get_logs_for_address_in_memory(
         &self,
+        provider: &RindexerProvider,
         filter: &Filter,
         addresses: HashSet<Address>,
     ) -> Result<Vec<Log>, ProviderError> {
-        let logs = self.provider.get_logs(filter).await?;
+        let logs = provider.get_logs(filter).await?;
 
         let filtered_logs =
             logs.into_iter().filter(|log| addresses.contains(&log.address())).collect::<Vec<_>>();
@@ -609,6 +1178,10 @@ impl JsonRpcCachedProvider {
     pub fn get_chain_state_notification(&self) -> Option<Sender<ChainStateNotification>> {
         self.chain_state_notification.clone()
     }
+
+    pub fn reorg_safe_distance(&self) -> Option<ReorgSafeDistance> {
+        self.reorg_safe_distance
+    }
 }
 #[derive(Error, Debug)]
 pub enum RetryClientError {
@@ -626,8 +1199,21 @@ pub enum RetryClientError {
 
     #[error("Could not start reth node for network {0}: {1}")]
     RethNodeStartError(String, String),
+
+    #[error("Websocket provider can't be created for {0}: {1}")]
+    WsProviderCantBeCreated(String, String),
+
+    #[error("No rpc endpoints configured")]
+    NoRpcEndpoints,
+
+    #[error("Invalid rpc headers for network {0}: {1}")]
+    InvalidRpcHeaders(String, RpcHeaderError),
+
+    #[error("{0}")]
+    RpcCassette(#[from] RpcCassetteError),
 }
 
+/// Create a client for a single rpc url, use `create_client_with_endpoints` for any other setting.
 #[allow(clippy::too_many_arguments)]
 pub async fn create_client(
     rpc_url: &str,
@@ -639,58 +1225,310 @@ pub async fn create_client(
     address_filtering: Option<AddressFiltering>,
     chain_state_notification: Option<Sender<ChainStateNotification>>,
 ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
-    let (rpc_client, provider) = if rpc_url.ends_with(".ipc") {
-        // IPC connection
-        let ipc = IpcConnect::new(rpc_url.to_string());
-        let retry_layer =
-            RetryBackoffLayer::new(5000, 1000, compute_units_per_second.unwrap_or(660));
-        let rpc_client = RpcClient::builder().layer(retry_layer).ipc(ipc.clone()).await?;
-
-        let provider =
-            ProviderBuilder::new().network::<AnyNetwork>().connect_ipc(ipc).await.map_err(|e| {
-                RetryClientError::HttpProviderCantBeCreated(
-                    rpc_url.to_string(),
-                    format!("IPC connection failed: {e}"),
-                )
-            })?;
+    create_client_with_endpoints(
+        &[RpcEndpoint::new(rpc_url.to_string())],
+        chain_id,
+        CreateClientOptions {
+            compute_units_per_second,
+            max_block_range,
+            block_poll_frequency,
+            custom_headers,
+            address_filtering,
+            chain_state_notification,
+            ..Default::default()
+        },
+    )
+    .await
+}
 
-        (rpc_client, provider)
-    } else {
-        // HTTP connection
-        let rpc_url = Url::parse(rpc_url).map_err(|e| {
-            RetryClientError::HttpProviderCantBeCreated(rpc_url.to_string(), e.to_string())
-        })?;
+/// How a client is set up besides its endpoints and chain id, every field is optional.
+#[derive(Default)]
+pub struct CreateClientOptions {
+    /// What live indexing subscribes to when an endpoint is a websocket.
+    pub live_subscription: Option<LiveSubscription>,
+    /// Where the logs of events are fetched from.
+    pub logs_source: Option<LogsSource>,
+    /// Overrides what rindexer knows about the chain.
+    pub chain_profile: Option<ChainProfile>,
+    pub compute_units_per_second: Option<u64>,
+    pub compute_unit_costs: Option<BTreeMap<String, u64>>,
+    pub max_block_range: Option<U64>,
+    pub block_poll_frequency: Option<BlockPollFrequency>,
+    pub custom_headers: HeaderMap,
+    pub address_filtering: Option<AddressFiltering>,
+    pub reorg_safe_distance: Option<ReorgSafeDistance>,
+    pub chain_state_notification: Option<Sender<ChainStateNotification>>,
+}
 
-        // Most log responses return in this timeout, any others retry on failure with smaller range
-        let client_with_auth = Client::builder()
-            .default_headers(custom_headers)
-            .timeout(Duration::from_secs(90))
-            .build()?;
+/// Create a client which routes requests across the endpoints, failing over to the next one when
+/// an endpoint does not answer. With a websocket endpoint live indexing subscribes to new blocks.
+pub async fn create_client_with_endpoints(
+    endpoints: &[RpcEndpoint],
+    chain_id: u64,
+    options: CreateClientOptions,
+) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
+    let CreateClientOptions {
+        live_subscription,
+        logs_source,
+        chain_profile,
+        compute_units_per_second,
+        compute_unit_costs,
+        max_block_range,
+        block_poll_frequency,
+        custom_headers,
+        address_filtering,
+        reorg_safe_distance,
+        chain_state_notification,
+    } = options;
+
+    if endpoints.is_empty() {
+        return Err(RetryClientError::NoRpcEndpoints);
+    }
 
-        let http = Http::with_client(client_with_auth, rpc_url);
-        let retry_layer =
-            RetryBackoffLayer::new(5000, 1000, compute_units_per_second.unwrap_or(660));
-        let rpc_client = RpcClient::builder().layer(retry_layer).transport(http, false);
-        let provider =
-            ProviderBuilder::new().network::<AnyNetwork>().connect_client(rpc_client.clone());
+    let chain_profile = ChainProfile::for_chain(chain_id, chain_profile.as_ref());
+    let max_block_range = max_block_range.or(chain_profile.max_logs_range);
+    let logs_source = logs_source.unwrap_or_default();
+    let max_block_range = match logs_source {
+        LogsSource::BlockReceipts => {
+            max_block_range.or(Some(U64::from(BLOCK_RECEIPTS_MAX_BLOCK_RANGE)))
+        }
+        LogsSource::GetLogs => max_block_range,
+    };
 
-        (rpc_client, provider)
+    let retry_layer = RetryBackoffLayer::new(5000, 1000, compute_units_per_second.unwrap_or(660));
+    // one budget for the whole network, whichever endpoint a request ends up on
+    let compute_unit_layer = ComputeUnitLayer::new(
+        compute_units_per_second
+            .filter(|compute_units_per_second| *compute_units_per_second > 0)
+            .map(|compute_units_per_second| {
+                Arc::new(ComputeUnitLimiter::new(
+                    compute_units_per_second,
+                    compute_unit_costs.as_ref(),
+                ))
+            }),
+    );
+    let cassette = rpc_cassette(chain_id)?;
+    let replaying = cassette.as_ref().is_some_and(|cassette| cassette.is_replay());
+    let has_fallback = endpoints.len() > 1;
+    let mut is_local = true;
+    let mut routed_endpoints = Vec::with_capacity(endpoints.len());
+    let mut endpoint_clients = Vec::with_capacity(endpoints.len());
+
+    for endpoint in endpoints {
+        let (transport, endpoint_is_local) = match &cassette {
+            Some(cassette) if replaying => {
+                (BoxTransport::new(RpcCassetteTransport::new(Arc::clone(cassette), None)), true)
+            }
+            Some(cassette) => {
+                let (transport, endpoint_is_local) =
+                    connect_endpoint_transport(&endpoint.url, &custom_headers).await?;
+                let transport = RpcCassetteTransport::new(Arc::clone(cassette), Some(transport));
+                (BoxTransport::new(transport), endpoint_is_local)
+            }
+            None => connect_endpoint_transport(&endpoint.url, &custom_headers).await?,
+        };
+        is_local &= endpoint_is_local;
+
+        let state = Arc::new(RpcEndpointState::new(endpoint, max_block_range, has_fallback));
+        let transport = HealthTrackedTransport::new(Arc::clone(&state), transport);
+
+        let endpoint_client = RpcClient::builder()
+            .layer(retry_layer.clone())
+            .layer(compute_unit_layer.clone())
+            .transport(transport.clone(), endpoint_is_local);
+        endpoint_clients.push(RpcEndpointClient {
+            state: Arc::clone(&state),
+            provider: Arc::new(
+                ProviderBuilder::new().network::<AnyNetwork>().connect_client(endpoint_client),
+            ),
+        });
+        routed_endpoints.push((state, transport));
+    }
+
+    // a replay has nothing to subscribe to, live indexing polls the cassette instead
+    let ws_endpoint = endpoints
+        .iter()
+        .filter(|endpoint| !replaying && is_ws_url(&endpoint.url))
+        .min_by_key(|endpoint| endpoint.priority.unwrap_or_default());
+    let ws_subscriptions = match (live_subscription, ws_endpoint) {
+        (Some(LiveSubscription::Disabled), _) => None,
+        (mode, Some(endpoint)) => Some(WsSubscriptions::start(
+            endpoint.url.clone(),
+            ws_auth(&custom_headers),
+            mode.unwrap_or(LiveSubscription::NewHeads),
+        )),
+        (Some(mode), None) => {
+            warn!(
+                "live_subscription {:?} needs a ws:// or wss:// rpc endpoint, polling for new blocks instead",
+                mode
+            );
+            None
+        }
+        (None, None) => None,
     };
 
+    let rpc_client = RpcClient::builder()
+        .layer(retry_layer)
+        .layer(compute_unit_layer)
+        .transport(RpcRouter::new(routed_endpoints), is_local);
+    let provider =
+        ProviderBuilder::new().network::<AnyNetwork>().connect_client(rpc_client.clone());
+
     Ok(Arc::new(
         JsonRpcCachedProvider::new(
             provider,
             chain_id,
+            chain_profile,
             rpc_client,
             block_poll_frequency,
             max_block_range,
             address_filtering,
+            reorg_safe_distance,
             chain_state_notification,
         )
-        .await,
+        .await
+        .with_endpoints(endpoint_clients)
+        .with_logs_source(logs_source)
+        .with_ws_subscriptions(ws_subscriptions),
     ))
 }
 
+struct CallFrameContext {
+    tx_hash: Option<TxHash>,
+    position: u64,
+    block_number: U64,
+    parent_error: Option<String>,
+}
+
+/// Flattens a `callTracer` frame and its subcalls in execution order. `CREATE` and `CREATE2`
+/// frames become create actions with the deployed code as their output, like `trace_block`.
+fn flatten_call_frame(
+    call: TraceCall,
+    context: &CallFrameContext,
+    trace_address: Vec<usize>,
+    traces: &mut Vec<LocalizedTransactionTrace>,
+) {
+    let error = call.error.or_else(|| context.parent_error.clone());
+    let gas = call
+        .gas
+        .and_then(|gas| u64::from_str_radix(gas.trim_start_matches("0x"), 16).ok())
+        .unwrap_or_default();
+    let creation_method = match call.typ.as_str() {
+        "CREATE" => Some(CreationMethod::Create),
+        "CREATE2" => Some(CreationMethod::Create2),
+        _ => None,
+    };
+
+    let action_and_result = match (creation_method, call.to) {
+        (Some(creation_method), to) => Some((
+            Action::Create(CreateAction {
+                from: call.from,
+                gas,
+                init: call.input,
+                value: call.value,
+                creation_method,
+            }),
+            to.map(|address| {
+                TraceOutput::Create(CreateOutput {
+                    address,
+                    code: call.output.unwrap_or_default(),
+                    gas_used: call.gas_used.saturating_to(),
+                })
+            }),
+        )),
+        (None, Some(to)) => Some((
+            Action::Call(CallAction {
+                from: call.from,
+                to,
+                value: call.value,
+                gas,
+                input: call.input,
+                call_type: match call.typ.as_str() {
+                    "CALL" => CallType::Call,
+                    "CALLCODE" => CallType::CallCode,
+                    "DELEGATECALL" => CallType::DelegateCall,
+                    "STATICCALL" => CallType::StaticCall,
+                    _ => CallType::None,
+                },
+            }),
+            None,
+        )),
+        (None, None) => None,
+    };
+
+    if let Some((action, result)) = action_and_result {
+        traces.push(LocalizedTransactionTrace {
+            trace: TransactionTrace {
+                action,
+                result,
+                trace_address: trace_address.clone(),
+                subtraces: call.calls.len(),
+                error: error.clone(),
+            },
+            transaction_hash: context.tx_hash,
+            transaction_position: Some(context.position),
+            block_number: Some(context.block_number.to()),
+            block_hash: None,
+        });
+    }
+
+    let context = CallFrameContext { parent_error: error, ..*context };
+    for (index, subcall) in call.calls.into_iter().enumerate() {
+        let mut subcall_address = trace_address.clone();
+        subcall_address.push(index);
+        flatten_call_frame(subcall, &context, subcall_address, traces);
+    }
+}
+
+fn is_ws_url(rpc_url: &str) -> bool {
+    rpc_url.starts_with("ws://") || rpc_url.starts_with("wss://")
+}
+
+/// Connects the transport of a single endpoint, returning whether it is a local one.
+async fn connect_endpoint_transport(
+    rpc_url: &str,
+    custom_headers: &HeaderMap,
+) -> Result<(BoxTransport, bool), RetryClientError> {
+    if rpc_url.ends_with(".ipc") {
+        // IPC connection
+        let ipc = IpcConnect::new(rpc_url.to_string());
+        let is_local = ipc.is_local();
+        let frontend = ipc.into_service().await.map_err(|e| {
+            RetryClientError::IpcProviderCantBeCreated(rpc_url.to_string(), e.to_string())
+        })?;
+
+        Ok((BoxTransport::new(frontend), is_local))
+    } else if is_ws_url(rpc_url) {
+        // Websocket connection, used for requests like any other endpoint
+        let ws = WsConnect::new(rpc_url).with_auth_opt(ws_auth(custom_headers));
+        let is_local = ws.is_local();
+        let frontend = ws.into_service().await.map_err(|e| {
+            RetryClientError::WsProviderCantBeCreated(rpc_url.to_string(), e.to_string())
+        })?;
+
+        Ok((BoxTransport::new(frontend), is_local))
+    } else {
+        // HTTP connection
+        let url = Url::parse(rpc_url).map_err(|e| {
+            RetryClientError::HttpProviderCantBeCreated(rpc_url.to_string(), e.to_string())
+        })?;
+
+        // Most log responses return in this timeout, any others retry on failure with smaller range
+        let client_with_auth = Client::builder()
+            .default_headers(custom_headers.clone())
+            .timeout(Duration::from_secs(90))
+            .build()?;
+
+        Ok((BoxTransport::new(Http::with_client(client_with_auth, url)), false))
+    }
+}
+
+/// Websockets can only send the `Authorization` header, the other custom headers only go out with
+/// http requests.
+fn ws_auth(custom_headers: &HeaderMap) -> Option<Authorization> {
+    custom_headers.get(AUTHORIZATION)?.to_str().ok().map(Authorization::raw)
+}
+
 pub async fn get_chain_id(rpc_url: &str) -> Result<U256, RpcError<TransportErrorKind>> {
     let url = Url::parse(rpc_url).map_err(|e| RpcError::LocalUsageError(Box::new(e)))?;
     let provider = ProviderBuilder::new().connect_http(url);
@@ -703,6 +1541,7 @@ pub async fn get_chain_id(rpc_url: &str) -> Result<U256, RpcError<TransportError
 pub struct CreateNetworkProvider {
     pub network_name: String,
     pub disable_logs_bloom_checks: bool,
+    pub include_block_timestamp: bool,
     pub client: Arc<JsonRpcCachedProvider>,
 }
 
@@ -718,29 +1557,42 @@ impl CreateNetworkProvider {
             })?;
 
             // if reth is enabled and started successfully, we can use the reth ipc path to create a provider.
-            // else, we will use the rpc url provided in the manifest.
-            let provider_url = if reth_tx.is_some() {
-                network.get_reth_ipc_path().unwrap()
+            // else, we will use the rpc endpoints provided in the manifest.
+            let endpoints = if reth_tx.is_some() {
+                vec![RpcEndpoint::new(network.get_reth_ipc_path().unwrap())]
             } else {
-                network.rpc.clone()
+                network.rpc.endpoints()
             };
 
             // create the provider
-            let provider = create_client(
-                &provider_url,
+            let provider = create_client_with_endpoints(
+                &endpoints,
                 network.chain_id,
-                network.compute_units_per_second,
-                network.max_block_range,
-                network.block_poll_frequency,
-                manifest.get_custom_headers(),
-                network.get_logs_settings.clone().map(|settings| settings.address_filtering),
-                reth_tx.clone(),
+                CreateClientOptions {
+                    live_subscription: network.live_subscription,
+                    logs_source: network.logs_source,
+                    chain_profile: network.chain_profile.clone(),
+                    compute_units_per_second: network.compute_units_per_second,
+                    compute_unit_costs: network.compute_unit_costs.clone(),
+                    max_block_range: network.max_block_range,
+                    block_poll_frequency: network.block_poll_frequency,
+                    custom_headers: network.rpc_headers(manifest.get_custom_headers()).map_err(
+                        |e| RetryClientError::InvalidRpcHeaders(network.name.clone(), e),
+                    )?,
+                    address_filtering: network
+                        .get_logs_settings
+                        .clone()
+                        .map(|settings| settings.address_filtering),
+                    reorg_safe_distance: network.reorg_safe_distance,
+                    chain_state_notification: reth_tx.clone(),
+                },
             )
             .await?;
 
             Ok::<_, RetryClientError>(CreateNetworkProvider {
                 network_name: network.name.clone(),
                 disable_logs_bloom_checks: network.disable_logs_bloom_checks.unwrap_or_default(),
+                include_block_timestamp: network.include_block_timestamp.unwrap_or_default(),
                 client: provider,
             })
         });
@@ -0,0 +1,308 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, Response, ResponsePacket, SerializedRequest},
+    transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut},
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tower::Service;
+use tracing::{error, info};
+
+/// Set to a directory to record every rpc request and response of the run into it.
+pub const RPC_RECORD_ENV: &str = "RINDEXER_RPC_RECORD";
+
+/// Set to a directory recorded with [`RPC_RECORD_ENV`] to answer every rpc request from it
+/// instead of the network's endpoints.
+pub const RPC_REPLAY_ENV: &str = "RINDEXER_RPC_REPLAY";
+
+/// The cassette of every chain, shared by all the providers and endpoints of that chain.
+static RPC_CASSETTES: Lazy<Mutex<HashMap<u64, Arc<RpcCassette>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(thiserror::Error, Debug)]
+pub enum RpcCassetteError {
+    #[error("Could not open rpc cassette {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Could not parse rpc cassette {0}: {1}")]
+    Parse(String, serde_json::Error),
+}
+
+/// A request and the response the endpoint gave, one per line of `<chain_id>.jsonl`.
+#[derive(Serialize, Deserialize)]
+struct RecordedCall {
+    method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<Box<RawValue>>,
+    response: Response,
+}
+
+/// The rpc traffic of a chain, either being written while indexing against real endpoints or
+/// being served back in place of them.
+#[derive(Debug)]
+pub enum RpcCassette {
+    Record(Mutex<File>),
+    /// The recorded responses of every request in the order they were given, the last one keeps
+    /// being served once the earlier ones are used up.
+    Replay(Mutex<HashMap<String, VecDeque<Response>>>),
+}
+
+/// The cassette of the chain when the run is recording or replaying rpc, picked up from
+/// [`RPC_RECORD_ENV`] and [`RPC_REPLAY_ENV`].
+pub fn rpc_cassette(chain_id: u64) -> Result<Option<Arc<RpcCassette>>, RpcCassetteError> {
+    let (record_dir, replay_dir) =
+        (std::env::var_os(RPC_RECORD_ENV), std::env::var_os(RPC_REPLAY_ENV));
+    if record_dir.is_none() && replay_dir.is_none() {
+        return Ok(None);
+    }
+
+    let mut cassettes = RPC_CASSETTES.lock().unwrap();
+    if let Some(cassette) = cassettes.get(&chain_id) {
+        return Ok(Some(Arc::clone(cassette)));
+    }
+
+    // replaying wins, a run never has to reach the network to record what it already has
+    let cassette = match (replay_dir, record_dir) {
+        (Some(dir), _) => RpcCassette::load(&cassette_path(Path::new(&dir), chain_id))?,
+        (None, Some(dir)) => RpcCassette::create(&cassette_path(Path::new(&dir), chain_id))?,
+        (None, None) => unreachable!(),
+    };
+    let cassette = Arc::new(cassette);
+    cassettes.insert(chain_id, Arc::clone(&cassette));
+
+    Ok(Some(cassette))
+}
+
+fn cassette_path(dir: &Path, chain_id: u64) -> PathBuf {
+    dir.join(format!("{chain_id}.jsonl"))
+}
+
+fn call_key(method: &str, params: Option<&RawValue>) -> String {
+    format!("{} {}", method, params.map_or("", RawValue::get))
+}
+
+impl RpcCassette {
+    fn create(path: &Path) -> Result<Self, RpcCassetteError> {
+        let display_path = path.display().to_string();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| RpcCassetteError::Io(display_path.clone(), e))?;
+        }
+
+        let file = File::create(path).map_err(|e| RpcCassetteError::Io(display_path.clone(), e))?;
+        info!("Recording rpc requests to {}", display_path);
+
+        Ok(Self::Record(Mutex::new(file)))
+    }
+
+    fn load(path: &Path) -> Result<Self, RpcCassetteError> {
+        let display_path = path.display().to_string();
+        let contents =
+            fs::read_to_string(path).map_err(|e| RpcCassetteError::Io(display_path.clone(), e))?;
+
+        let mut calls: HashMap<String, VecDeque<Response>> = HashMap::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let call: RecordedCall = serde_json::from_str(line)
+                .map_err(|e| RpcCassetteError::Parse(display_path.clone(), e))?;
+            calls
+                .entry(call_key(&call.method, call.params.as_deref()))
+                .or_default()
+                .push_back(call.response);
+        }
+        info!("Replaying rpc requests from {}", display_path);
+
+        Ok(Self::Replay(Mutex::new(calls)))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Self::Replay(_))
+    }
+
+    /// Appends the responses to the requests they answer, errors returned by the endpoint are
+    /// kept too so a replay shrinks block ranges the same way.
+    fn write(&self, request: &RequestPacket, response: &ResponsePacket) {
+        let Self::Record(file) = self else {
+            return;
+        };
+
+        let requests = match request {
+            RequestPacket::Single(request) => std::slice::from_ref(request),
+            RequestPacket::Batch(requests) => requests.as_slice(),
+        };
+        let responses = match response {
+            ResponsePacket::Single(response) => std::slice::from_ref(response),
+            ResponsePacket::Batch(responses) => responses.as_slice(),
+        };
+
+        let mut lines = String::new();
+        for response in responses {
+            let Some(request) = requests.iter().find(|request| *request.id() == response.id) else {
+                continue;
+            };
+            let call = RecordedCall {
+                method: request.method().to_string(),
+                params: request.params().map(RawValue::to_owned),
+                response: response.clone(),
+            };
+            match serde_json::to_string(&call) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => error!("Failed to record rpc response for {}: {}", call.method, e),
+            }
+        }
+
+        if let Err(e) = file.lock().unwrap().write_all(lines.as_bytes()) {
+            error!("Failed to write rpc cassette: {}", e);
+        }
+    }
+
+    fn replay_request(&self, request: &SerializedRequest) -> Result<Response, TransportError> {
+        let Self::Replay(calls) = self else {
+            return Err(TransportErrorKind::custom_str("rpc cassette is not replaying"));
+        };
+
+        let mut calls = calls.lock().unwrap();
+        let responses = calls
+            .get_mut(&call_key(request.method(), request.params()))
+            .filter(|responses| !responses.is_empty())
+            .ok_or_else(|| {
+                TransportErrorKind::custom_str(&format!(
+                    "No recorded rpc response for {} {}",
+                    request.method(),
+                    request.params().map_or("", RawValue::get)
+                ))
+            })?;
+
+        let mut response =
+            if responses.len() > 1 { responses.pop_front().unwrap() } else { responses[0].clone() };
+        response.id = request.id().clone();
+
+        Ok(response)
+    }
+
+    fn replay(&self, request: &RequestPacket) -> Result<ResponsePacket, TransportError> {
+        match request {
+            RequestPacket::Single(request) => {
+                self.replay_request(request).map(ResponsePacket::Single)
+            }
+            RequestPacket::Batch(requests) => requests
+                .iter()
+                .map(|request| self.replay_request(request))
+                .collect::<Result<Vec<_>, _>>()
+                .map(ResponsePacket::Batch),
+        }
+    }
+}
+
+/// Sits in place of an endpoint's transport to record what it answers, or when replaying to
+/// answer from the cassette without any endpoint behind it.
+#[derive(Debug, Clone)]
+pub struct RpcCassetteTransport {
+    cassette: Arc<RpcCassette>,
+    inner: Option<BoxTransport>,
+}
+
+impl RpcCassetteTransport {
+    pub fn new(cassette: Arc<RpcCassette>, inner: Option<BoxTransport>) -> Self {
+        Self { cassette, inner }
+    }
+}
+
+impl Service<RequestPacket> for RpcCassetteTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Some(inner) if !self.cassette.is_replay() => inner.poll_ready(cx),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let cassette = Arc::clone(&self.cassette);
+        let inner = self.inner.as_mut().filter(|_| !cassette.is_replay());
+        let Some(inner) = inner else {
+            return Box::pin(async move { cassette.replay(&req) });
+        };
+
+        let response = inner.call(req.clone());
+        Box::pin(async move {
+            let response = response.await?;
+            cassette.write(&req, &response);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::json_rpc::{Id, Request};
+
+    use super::*;
+
+    fn request(id: u64, method: &'static str, block: &str) -> SerializedRequest {
+        Request::new(method, Id::Number(id), [block]).serialize().unwrap()
+    }
+
+    fn response(id: u64, result: &str) -> ResponsePacket {
+        serde_json::from_str(&format!(r#"{{"jsonrpc":"2.0","id":{id},"result":"{result}"}}"#))
+            .unwrap()
+    }
+
+    fn result(response: &ResponsePacket) -> String {
+        response.first_payload().unwrap().as_success().unwrap().get().to_string()
+    }
+
+    #[test]
+    fn test_recorded_calls_replay_in_order() {
+        let path = std::env::temp_dir()
+            .join(format!("rindexer-rpc-cassette-{}", std::process::id()))
+            .join("1.jsonl");
+
+        let recording = RpcCassette::create(&path).unwrap();
+        for (id, result) in [(1, "0x1"), (2, "0x2")] {
+            let request = RequestPacket::Single(request(id, "eth_blockNumber", "latest"));
+            recording.write(&request, &response(id, result));
+        }
+        recording.write(
+            &RequestPacket::Batch(vec![
+                request(3, "eth_getBlockByNumber", "0x1"),
+                request(4, "eth_getBlockByNumber", "0x2"),
+            ]),
+            &ResponsePacket::Batch(vec![
+                serde_json::from_str(r#"{"jsonrpc":"2.0","id":4,"result":"b2"}"#).unwrap(),
+                serde_json::from_str(r#"{"jsonrpc":"2.0","id":3,"result":"b1"}"#).unwrap(),
+            ]),
+        );
+
+        let replaying = RpcCassette::load(&path).unwrap();
+        let block_number = RequestPacket::Single(request(9, "eth_blockNumber", "latest"));
+        let first = replaying.replay(&block_number).unwrap();
+        assert_eq!(first.response_ids().collect::<Vec<_>>(), vec![&Id::Number(9)]);
+        assert_eq!(result(&first), r#""0x1""#);
+        assert_eq!(result(&replaying.replay(&block_number).unwrap()), r#""0x2""#);
+        // the last response keeps being served
+        assert_eq!(result(&replaying.replay(&block_number).unwrap()), r#""0x2""#);
+
+        let block = RequestPacket::Single(request(10, "eth_getBlockByNumber", "0x2"));
+        assert_eq!(result(&replaying.replay(&block).unwrap()), r#""b2""#);
+
+        let missing = RequestPacket::Single(request(11, "eth_getBlockByNumber", "0x3"));
+        assert!(replaying.replay(&missing).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    hex,
+    primitives::{keccak256, U64},
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut},
+};
+use tower::Service;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::{learned_block_ranges::LearnedBlockRanges, manifest::network::RpcEndpoint};
+
+/// How much a single request moves the error rate and latency averages of an endpoint.
+const HEALTH_SMOOTHING: f64 = 0.1;
+
+/// Requests an endpoint has to have served before its error rate or latency can eject it.
+const MIN_HEALTH_SAMPLES: u32 = 10;
+
+/// Endpoints failing more than this share of their requests are ejected.
+const MAX_ERROR_RATE: f64 = 0.5;
+
+/// Endpoints failing this many requests in a row are ejected without waiting for the samples.
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Endpoints this many times slower than the fastest endpoint of the network are ejected.
+const SLOW_ENDPOINT_FACTOR: f64 = 4.0;
+
+/// Endpoints are never ejected for latency below this, every endpoint is fast enough then.
+const MIN_SLOW_ENDPOINT_LATENCY_MS: f64 = 250.0;
+
+/// How long an ejected endpoint gets no requests before it is tried again.
+const EJECTION_DURATION: Duration = Duration::from_secs(30);
+
+/// Successful requests with a guessed block range before the range is doubled again.
+const GUESSED_BLOCK_RANGE_GROW_AFTER: u32 = 20;
+
+/// Without a stated or configured range a guessed one is dropped once it grows past this.
+const GUESSED_BLOCK_RANGE_CEILING: u64 = 10_000;
+
+/// A max block range learned from an error of an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockRangeLimit {
+    /// The provider stated the range it allows, it is kept for good and persisted.
+    Stated(U64),
+    /// Guessed from an error which did not state a range, it grows back as requests succeed.
+    Guessed(U64),
+}
+
+impl BlockRangeLimit {
+    pub fn range(&self) -> U64 {
+        match self {
+            Self::Stated(range) | Self::Guessed(range) => *range,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    samples: u32,
+    error_rate: f64,
+    latency_ms: Option<f64>,
+    consecutive_failures: u32,
+    ejected_until: Option<Instant>,
+}
+
+/// The health and learned block range of one rpc endpoint of a network.
+#[derive(Debug)]
+pub struct RpcEndpointState {
+    name: String,
+    weight: u32,
+    priority: u32,
+    configured_max_block_range: Option<U64>,
+    // the range the provider stated, 0 means nothing has been learned yet
+    learned_max_block_range: AtomicU64,
+    // the range guessed from errors, 0 means there is no guess
+    guessed_max_block_range: AtomicU64,
+    successes_since_guess: AtomicU32,
+    // where the learned range is persisted, with the network the endpoint belongs to
+    learned_block_ranges: OnceLock<(Arc<LearnedBlockRanges>, String)>,
+    has_fallback: bool,
+    health: Mutex<EndpointHealth>,
+}
+
+impl RpcEndpointState {
+    pub fn new(
+        endpoint: &RpcEndpoint,
+        network_max_block_range: Option<U64>,
+        has_fallback: bool,
+    ) -> Self {
+        Self {
+            name: endpoint_log_name(&endpoint.url),
+            weight: endpoint.weight.unwrap_or(1).max(1),
+            priority: endpoint.priority.unwrap_or_default(),
+            configured_max_block_range: endpoint.max_block_range.or(network_max_block_range),
+            learned_max_block_range: AtomicU64::new(0),
+            guessed_max_block_range: AtomicU64::new(0),
+            successes_since_guess: AtomicU32::new(0),
+            learned_block_ranges: OnceLock::new(),
+            has_fallback,
+            health: Mutex::new(EndpointHealth::default()),
+        }
+    }
+
+    /// The block range `eth_getLogs` requests to this endpoint are limited to, the guessed one
+    /// while there is a guess below the stated or configured one.
+    pub fn max_block_range(&self) -> Option<U64> {
+        let max_block_range = self.stated_max_block_range();
+        match self.guessed_max_block_range.load(Ordering::Relaxed) {
+            0 => max_block_range,
+            guessed => Some(
+                max_block_range.map_or(U64::from(guessed), |range| range.min(U64::from(guessed))),
+            ),
+        }
+    }
+
+    fn stated_max_block_range(&self) -> Option<U64> {
+        match self.learned_max_block_range.load(Ordering::Relaxed) {
+            0 => self.configured_max_block_range,
+            range => Some(U64::from(range)),
+        }
+    }
+
+    /// Limits the block range of this endpoint, only a range the provider stated is persisted.
+    pub fn limit_block_range(&self, limit: BlockRangeLimit) {
+        match limit {
+            BlockRangeLimit::Stated(range) => {
+                self.learned_max_block_range.store(range.to::<u64>(), Ordering::Relaxed);
+
+                if let Some((learned_block_ranges, network)) = self.learned_block_ranges.get() {
+                    if Some(range) != self.configured_max_block_range {
+                        learned_block_ranges.record(network, &self.name, range);
+                    }
+                }
+            }
+            BlockRangeLimit::Guessed(range) => {
+                self.guessed_max_block_range.store(range.to::<u64>(), Ordering::Relaxed);
+                self.successes_since_guess.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Counts a request which fetched the logs of a whole range, a guessed range doubles after a
+    /// run of them until it is back at the stated or configured one.
+    pub fn record_block_range_success(&self) {
+        let guessed = self.guessed_max_block_range.load(Ordering::Relaxed);
+        if guessed == 0
+            || self.successes_since_guess.fetch_add(1, Ordering::Relaxed) + 1
+                < GUESSED_BLOCK_RANGE_GROW_AFTER
+        {
+            return;
+        }
+
+        self.successes_since_guess.store(0, Ordering::Relaxed);
+        let grown = guessed.saturating_mul(2);
+        let ceiling = self
+            .stated_max_block_range()
+            .map(|range| range.to::<u64>())
+            .unwrap_or(GUESSED_BLOCK_RANGE_CEILING);
+        let grown = if grown >= ceiling { 0 } else { grown };
+        // a new guess from a failing request wins over growing the old one
+        let _ = self.guessed_max_block_range.compare_exchange(
+            guessed,
+            grown,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Starts this endpoint from the block range it was limited to before a restart and persists
+    /// whatever it learns from here on.
+    pub fn use_learned_block_ranges(
+        &self,
+        learned_block_ranges: Arc<LearnedBlockRanges>,
+        network: &str,
+    ) {
+        if let Some(max_block_range) = learned_block_ranges.get(network, &self.name) {
+            self.learned_max_block_range.store(max_block_range.to::<u64>(), Ordering::Relaxed);
+        }
+
+        if self.learned_block_ranges.set((learned_block_ranges, network.to_string())).is_err() {
+            debug!("Learned block ranges already registered for {} on {}", self.name, network);
+        }
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        let mut health = self.health.lock().unwrap();
+        match health.ejected_until {
+            Some(until) if until > now => false,
+            Some(_) => {
+                health.ejected_until = None;
+                info!("RPC endpoint {} back in rotation", self.name);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn ejected_until(&self) -> Option<Instant> {
+        self.health.lock().unwrap().ejected_until
+    }
+
+    /// The average latency once there are enough samples to trust it.
+    fn latency_ms(&self) -> Option<f64> {
+        let health = self.health.lock().unwrap();
+        if health.samples < MIN_HEALTH_SAMPLES {
+            return None;
+        }
+        health.latency_ms
+    }
+
+    fn record_success(&self, latency: Duration) {
+        let mut health = self.health.lock().unwrap();
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        health.samples = health.samples.saturating_add(1);
+        health.error_rate *= 1.0 - HEALTH_SMOOTHING;
+        health.consecutive_failures = 0;
+        health.latency_ms = Some(match health.latency_ms {
+            Some(average) => average + HEALTH_SMOOTHING * (latency_ms - average),
+            None => latency_ms,
+        });
+    }
+
+    fn record_failure(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.samples = health.samples.saturating_add(1);
+        health.error_rate = health.error_rate * (1.0 - HEALTH_SMOOTHING) + HEALTH_SMOOTHING;
+        health.consecutive_failures += 1;
+
+        if health.consecutive_failures >= MAX_CONSECUTIVE_FAILURES
+            || (health.samples >= MIN_HEALTH_SAMPLES && health.error_rate > MAX_ERROR_RATE)
+        {
+            self.eject(&mut health, "failing requests");
+        }
+    }
+
+    fn eject_if_slower_than(&self, latency_ms: f64) {
+        let mut health = self.health.lock().unwrap();
+        if health.ejected_until.is_some() || health.samples < MIN_HEALTH_SAMPLES {
+            return;
+        }
+        if health.latency_ms.is_some_and(|average| average > latency_ms) {
+            self.eject(&mut health, "too slow");
+        }
+    }
+
+    fn eject(&self, health: &mut EndpointHealth, reason: &str) {
+        // with a single endpoint there is nothing to route to instead
+        if !self.has_fallback {
+            return;
+        }
+
+        warn!(
+            "RPC endpoint {} is {}, routing requests around it for {}s",
+            self.name,
+            reason,
+            EJECTION_DURATION.as_secs()
+        );
+        // it starts from a clean slate once back so old samples can not eject it straight away
+        *health = EndpointHealth {
+            ejected_until: Some(Instant::now() + EJECTION_DURATION),
+            ..Default::default()
+        };
+    }
+}
+
+/// The host and a short hash of the full url, rpc urls often carry an api key in the path or
+/// query so it is never logged, yet endpoints on the same host keep apart.
+pub fn endpoint_log_name(url: &str) -> String {
+    let hash = keccak256(url.as_bytes());
+    let host = Url::parse(url).ok().and_then(|url| url.host_str().map(|host| host.to_string()));
+
+    match host {
+        Some(host) => format!("{host}#{}", hex::encode(&hash[..4])),
+        None => url.to_string(),
+    }
+}
+
+/// The order endpoints should be tried in for the next request. Healthy endpoints come first by
+/// priority, picked by weight within the same priority, the ejected ones are only a last resort.
+pub fn route(endpoints: &[Arc<RpcEndpointState>]) -> Vec<usize> {
+    let now = Instant::now();
+
+    if endpoints.len() > 1 {
+        let fastest = endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_healthy(now))
+            .filter_map(|endpoint| endpoint.latency_ms())
+            .min_by(|a, b| a.total_cmp(b));
+        if let Some(fastest) = fastest {
+            let threshold = (fastest * SLOW_ENDPOINT_FACTOR).max(MIN_SLOW_ENDPOINT_LATENCY_MS);
+            for endpoint in endpoints {
+                endpoint.eject_if_slower_than(threshold);
+            }
+        }
+    }
+
+    let (mut healthy, mut ejected): (Vec<usize>, Vec<usize>) =
+        (0..endpoints.len()).partition(|&i| endpoints[i].is_healthy(now));
+
+    healthy.sort_by_key(|&i| endpoints[i].priority);
+    let mut order = Vec::with_capacity(endpoints.len());
+    for tier in healthy.chunk_by(|&a, &b| endpoints[a].priority == endpoints[b].priority) {
+        order.extend(weighted_shuffle(tier, endpoints));
+    }
+
+    ejected.sort_by_key(|&i| endpoints[i].ejected_until());
+    order.extend(ejected);
+
+    order
+}
+
+fn weighted_shuffle(tier: &[usize], endpoints: &[Arc<RpcEndpointState>]) -> Vec<usize> {
+    let mut remaining = tier.to_vec();
+    let mut order = Vec::with_capacity(tier.len());
+
+    while !remaining.is_empty() {
+        let total: u64 = remaining.iter().map(|&i| endpoints[i].weight as u64).sum();
+        let mut pick = rand::random_range(0..total);
+        let position = remaining
+            .iter()
+            .position(|&i| {
+                let weight = endpoints[i].weight as u64;
+                if pick < weight {
+                    true
+                } else {
+                    pick -= weight;
+                    false
+                }
+            })
+            .unwrap_or_default();
+        order.push(remaining.remove(position));
+    }
+
+    order
+}
+
+/// Wraps the transport of an endpoint to record the outcome of every request against its health.
+#[derive(Debug, Clone)]
+pub struct HealthTrackedTransport {
+    state: Arc<RpcEndpointState>,
+    inner: BoxTransport,
+}
+
+impl HealthTrackedTransport {
+    pub fn new(state: Arc<RpcEndpointState>, inner: BoxTransport) -> Self {
+        Self { state, inner }
+    }
+}
+
+impl Service<RequestPacket> for HealthTrackedTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let state = Arc::clone(&self.state);
+        let request = self.inner.call(req);
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = request.await;
+            // an error response still means the endpoint answered, only failing to get one counts
+            match &result {
+                Ok(_) => state.record_success(start.elapsed()),
+                Err(_) => state.record_failure(),
+            }
+            result
+        })
+    }
+}
+
+/// Sends every request to the endpoint picked by [`route`], failing over to the next one when an
+/// endpoint does not answer.
+#[derive(Debug, Clone)]
+pub struct RpcRouter {
+    endpoints: Arc<[(Arc<RpcEndpointState>, HealthTrackedTransport)]>,
+}
+
+impl RpcRouter {
+    pub fn new(endpoints: Vec<(Arc<RpcEndpointState>, HealthTrackedTransport)>) -> Self {
+        Self { endpoints: endpoints.into() }
+    }
+}
+
+impl Service<RequestPacket> for RpcRouter {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let endpoints = Arc::clone(&self.endpoints);
+
+        Box::pin(async move {
+            let states = endpoints.iter().map(|(state, _)| Arc::clone(state)).collect::<Vec<_>>();
+            let mut last_error = None;
+
+            for index in route(&states) {
+                let mut transport = endpoints[index].1.clone();
+                match transport.call(req.clone()).await {
+                    Ok(response) => return Ok(response),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+
+            Err(last_error
+                .unwrap_or_else(|| TransportErrorKind::custom_str("No rpc endpoints configured")))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(weight: Option<u32>, priority: Option<u32>) -> Arc<RpcEndpointState> {
+        let endpoint = RpcEndpoint {
+            url: "https://example.rpc.org".to_string(),
+            weight,
+            priority,
+            max_block_range: None,
+        };
+        Arc::new(RpcEndpointState::new(&endpoint, None, true))
+    }
+
+    #[test]
+    fn test_route_prefers_lower_priority() {
+        let endpoints = vec![endpoint(None, Some(1)), endpoint(None, Some(0))];
+
+        assert_eq!(route(&endpoints), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_route_moves_failing_endpoint_last() {
+        let endpoints = vec![endpoint(None, None), endpoint(None, Some(1))];
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            endpoints[0].record_failure();
+        }
+
+        assert_eq!(route(&endpoints), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_route_ejects_slow_endpoint() {
+        let endpoints = vec![endpoint(None, None), endpoint(None, Some(1))];
+        for _ in 0..MIN_HEALTH_SAMPLES {
+            endpoints[0].record_success(Duration::from_secs(5));
+            endpoints[1].record_success(Duration::from_millis(100));
+        }
+
+        assert_eq!(route(&endpoints), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_single_endpoint_is_never_ejected() {
+        let endpoint = RpcEndpoint::new("https://example.rpc.org".to_string());
+        let state = Arc::new(RpcEndpointState::new(&endpoint, None, false));
+        for _ in 0..MAX_CONSECUTIVE_FAILURES {
+            state.record_failure();
+        }
+
+        assert!(state.ejected_until().is_none());
+    }
+
+    #[test]
+    fn test_learned_max_block_range() {
+        let endpoint = RpcEndpoint {
+            max_block_range: Some(U64::from(5000)),
+            ..RpcEndpoint::new("https://example.rpc.org".to_string())
+        };
+        let state = RpcEndpointState::new(&endpoint, Some(U64::from(10000)), true);
+        assert_eq!(state.max_block_range(), Some(U64::from(5000)));
+
+        state.limit_block_range(BlockRangeLimit::Stated(U64::from(2000)));
+        assert_eq!(state.max_block_range(), Some(U64::from(2000)));
+
+        state.limit_block_range(BlockRangeLimit::Guessed(U64::from(500)));
+        assert_eq!(state.max_block_range(), Some(U64::from(500)));
+    }
+
+    #[test]
+    fn test_guessed_max_block_range_grows_back() {
+        let endpoint = RpcEndpoint {
+            max_block_range: Some(U64::from(2000)),
+            ..RpcEndpoint::new("https://example.rpc.org".to_string())
+        };
+        let state = RpcEndpointState::new(&endpoint, None, true);
+        state.limit_block_range(BlockRangeLimit::Guessed(U64::from(500)));
+
+        let mut ranges = vec![];
+        for _ in 0..GUESSED_BLOCK_RANGE_GROW_AFTER * 2 {
+            state.record_block_range_success();
+            ranges.push(state.max_block_range());
+        }
+        assert_eq!(ranges[GUESSED_BLOCK_RANGE_GROW_AFTER as usize - 2], Some(U64::from(500)));
+        assert_eq!(ranges[GUESSED_BLOCK_RANGE_GROW_AFTER as usize - 1], Some(U64::from(1000)));
+        assert_eq!(ranges.last(), Some(&Some(U64::from(2000))));
+
+        // without a configured range the guess is dropped once it is large again
+        let endpoint = RpcEndpoint::new("https://example.rpc.org".to_string());
+        let state = RpcEndpointState::new(&endpoint, None, true);
+        state.limit_block_range(BlockRangeLimit::Guessed(U64::from(5000)));
+        for _ in 0..GUESSED_BLOCK_RANGE_GROW_AFTER {
+            state.record_block_range_success();
+        }
+        assert_eq!(state.max_block_range(), None);
+    }
+
+    #[test]
+    fn test_endpoint_log_name_keeps_endpoints_on_one_host_apart() {
+        let first = endpoint_log_name("https://eth.rpc.org/v2/first-api-key");
+        let second = endpoint_log_name("https://eth.rpc.org/v2/second-api-key");
+
+        assert!(first.starts_with("eth.rpc.org#"));
+        assert!(!first.contains("api-key"));
+        assert_ne!(first, second);
+        assert_eq!(first, endpoint_log_name("https://eth.rpc.org/v2/first-api-key"));
+    }
+}
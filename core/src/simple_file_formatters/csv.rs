@@ -1,11 +1,14 @@
 use std::{
+    collections::HashSet,
     fs::File,
     path::{Path, PathBuf},
+    str::FromStr,
     sync::Arc,
 };
 
+use alloy::primitives::{BlockHash, U64};
 use csv::Reader;
-use csv::Writer;
+use csv::{ReaderBuilder, Writer, WriterBuilder};
 use tokio::sync::Mutex;
 
 pub struct AsyncCsvAppender {
@@ -57,6 +60,27 @@ impl AsyncCsvAppender {
         .expect("Failed to run CSV bulk write operation")
     }
 
+    /// Removes the records of `network` from the given blocks, used for the logs the node flags
+    /// as removed. Returns the amount of records removed.
+    pub async fn remove_blocks(
+        &self,
+        network: &str,
+        block_hashes: HashSet<BlockHash>,
+    ) -> Result<usize, csv::Error> {
+        let lock = Arc::clone(&self.writer_lock);
+        let path = PathBuf::from(self.path.as_ref());
+        let network = network.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let _guard = lock.blocking_lock();
+            remove_records(&path, &network, "block_hash", |value| {
+                BlockHash::from_str(value).is_ok_and(|hash| block_hashes.contains(&hash))
+            })
+        })
+        .await
+        .expect("Failed to run CSV remove operation")
+    }
+
     pub async fn append_header(&self, header: Vec<String>) -> Result<(), csv::Error> {
         let lock = Arc::clone(&self.writer_lock);
         let path = Arc::clone(&self.path);
@@ -107,3 +131,112 @@ impl AsyncCsvReader {
         .expect("Failed to run CSV read operation")
     }
 }
+
+/// Removes every record for `network` with a block number above `block_number`, used to roll back
+/// csv output after a reorg. Returns the amount of records removed.
+pub async fn remove_records_after_block(
+    file_path: &str,
+    network: &str,
+    block_number: U64,
+) -> Result<usize, csv::Error> {
+    let path = PathBuf::from(file_path);
+    let network = network.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        remove_records(&path, &network, "block_number", |value| {
+            U64::from_str(value).is_ok_and(|value| value > block_number)
+        })
+    })
+    .await
+    .expect("Failed to run CSV rollback operation")
+}
+
+/// Rewrites the csv without the records of `network` whose `column` matches.
+fn remove_records(
+    path: &Path,
+    network: &str,
+    column: &str,
+    matches: impl Fn(&str) -> bool,
+) -> Result<usize, csv::Error> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    // rows are written without the trailing columns on some paths so allow uneven records
+    let mut reader = ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column_index = headers.iter().position(|h| h == column);
+    let network_index = headers.iter().position(|h| h == "network");
+
+    let (Some(column_index), Some(network_index)) = (column_index, network_index) else {
+        return Ok(0);
+    };
+
+    let mut kept = Vec::new();
+    let mut removed = 0;
+    for record in reader.records() {
+        let record = record?;
+        let is_removed = record.get(network_index) == Some(network)
+            && record.get(column_index).is_some_and(&matches);
+
+        if is_removed {
+            removed += 1;
+        } else {
+            kept.push(record);
+        }
+    }
+
+    if removed == 0 {
+        return Ok(0);
+    }
+
+    let temp_path = path.with_extension("csv.tmp");
+    let mut writer = WriterBuilder::new().flexible(true).from_path(&temp_path)?;
+    writer.write_record(&headers)?;
+    for record in kept {
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remove_blocks() {
+        let file_path =
+            std::env::temp_dir().join(format!("rindexer-csv-remove-{}.csv", std::process::id()));
+        let removed = B256::repeat_byte(1);
+        let kept = B256::repeat_byte(2);
+
+        let csv = AsyncCsvAppender::new(file_path.to_str().unwrap());
+        csv.append_header(vec!["block_hash".to_string(), "network".to_string()]).await.unwrap();
+        csv.append_bulk(vec![
+            vec![format!("{removed:?}"), "ethereum".to_string()],
+            vec![format!("{kept:?}"), "ethereum".to_string()],
+            vec![format!("{removed:?}"), "base".to_string()],
+        ])
+        .await
+        .unwrap();
+
+        let count = csv.remove_blocks("ethereum", HashSet::from([removed])).await.unwrap();
+        assert_eq!(count, 1);
+
+        let records = AsyncCsvReader::new(file_path.to_str().unwrap()).read_all().await.unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec![format!("{kept:?}"), "ethereum".to_string()],
+                vec![format!("{removed:?}"), "base".to_string()],
+            ]
+        );
+
+        std::fs::remove_file(&file_path).unwrap();
+    }
+}
@@ -12,7 +12,10 @@ use crate::{
         relationship::{ApplyAllRelationships, Relationship},
         setup::{setup_postgres, SetupPostgresError},
     },
-    event::callback_registry::{EventCallbackRegistry, TraceCallbackRegistry},
+    event::{
+        callback_registry::{EventCallbackRegistry, TraceCallbackRegistry},
+        failed_batches::{replay_failed_batches, FailedBatchesError},
+    },
     indexer::{
         no_code::{setup_no_code, SetupNoCodeError},
         start::{start_indexing, StartIndexingError},
@@ -30,6 +33,8 @@ use crate::{
 pub struct IndexingDetails {
     pub registry: EventCallbackRegistry,
     pub trace_registry: TraceCallbackRegistry,
+    /// Hand the failed batches of the project back to their handlers before indexing starts.
+    pub replay_failed_batches: bool,
 }
 
 pub struct StartDetails<'a> {
@@ -83,6 +88,9 @@ pub enum StartRindexerError {
 
     #[error("Reth CLI error: {0}")]
     RethCliError(#[from] Box<dyn std::error::Error>),
+
+    #[error("Could not replay failed batches: {0}")]
+    ReplayFailedBatches(#[from] FailedBatchesError),
 }
 
 async fn handle_shutdown(signal: &str) {
@@ -174,6 +182,10 @@ pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindex
                     )
                     .await?;
 
+                if indexing_details.replay_failed_batches {
+                    replay_failed_batches(project_path, &indexing_details.registry).await?;
+                }
+
                 let mut dependencies: Vec<ContractEventDependencies> =
                     ContractEventDependencies::parse(&manifest);
 
@@ -257,6 +269,7 @@ pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindex
 
 pub struct IndexerNoCodeDetails {
     pub enabled: bool,
+    pub replay_failed_batches: bool,
 }
 
 pub struct StartNoCodeDetails<'a> {
@@ -11,8 +11,8 @@ use tokio::{
 use tracing::error;
 
 use crate::{
-    event::{filter_event_data_by_conditions, EventMessage},
-    indexer::native_transfer::EVENT_NAME,
+    event::{filter_event_data_by_conditions, EventMessage, EventMessageType},
+    indexer::{contract_deployment::CONTRACT_CREATED_EVENT_NAME, native_transfer::EVENT_NAME},
     manifest::stream::{
         KafkaStreamConfig, KafkaStreamQueueConfig, RabbitMQStreamConfig, RabbitMQStreamQueueConfig,
         RedisStreamConfig, RedisStreamStreamConfig, SNSStreamTopicConfig, StreamEvent,
@@ -200,6 +200,7 @@ impl StreamsClients {
             event_data: Value::Array(chunk.to_vec()),
             event_signature_hash: event_message.event_signature_hash,
             network: event_message.network.clone(),
+            message_type: event_message.message_type,
         };
 
         serde_json::to_string(&chunk_message).unwrap()
@@ -216,6 +217,7 @@ impl StreamsClients {
             event_data: Value::Array(chunk.to_vec()),
             event_signature_hash: event_message.event_signature_hash,
             network: event_message.network.clone(),
+            message_type: event_message.message_type,
         };
 
         serde_json::to_value(&chunk_message).unwrap()
@@ -244,7 +246,10 @@ impl StreamsClients {
         let stream_event = events.iter().find(|e| e.event_name == event_message.event_name);
 
         // Allow no trace events to be defined, otherwise use the defined event config.
-        if event_message.event_name == EVENT_NAME && stream_event.is_none() {
+        if (event_message.event_name == EVENT_NAME
+            || event_message.event_name == CONTRACT_CREATED_EVENT_NAME)
+            && stream_event.is_none()
+        {
             return chunk.to_vec();
         }
 
@@ -461,6 +466,13 @@ impl StreamsClients {
             return Ok(0);
         }
 
+        // a retraction reuses the identity of the events it removes, so give it its own message
+        // id otherwise deduplicating consumers (e.g. fifo topics) would drop it
+        let id = match event_message.message_type {
+            EventMessageType::Event => id,
+            EventMessageType::Removed => format!("removed-{id}"),
+        };
+
         // will always have something even if the event has no parameters due to the tx_information
         if let Value::Array(data_array) = &event_message.event_data {
             let chunks = Arc::new(self.chunk_data(data_array));
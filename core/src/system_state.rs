@@ -6,7 +6,7 @@ use std::{
 use once_cell::sync::Lazy;
 use tracing::info;
 
-use crate::indexer::task_tracker::active_indexing_count;
+use crate::{indexer::task_tracker::active_indexing_count, logger::mark_shutdown_started};
 
 static IS_RUNNING: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(true));
 
@@ -33,3 +33,17 @@ pub async fn initiate_shutdown() {
 pub fn is_running() -> bool {
     IS_RUNNING.load(Ordering::SeqCst)
 }
+
+/// Stops indexing like a shutdown signal would but exits with a non-zero code once the active
+/// indexing tasks have finished, used when a handler fails with the `halt` policy.
+pub(crate) fn halt() {
+    if !IS_RUNNING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async {
+        mark_shutdown_started();
+        initiate_shutdown().await;
+        std::process::exit(1);
+    });
+}
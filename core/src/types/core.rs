@@ -45,30 +45,10 @@ impl LogParam {
         Self { name, value, components: vec![] }
     }
 
+    /// Extracts a value of the components by name. Supports deep paths like `foo.bar`.
+    #[allow(dead_code)]
     pub fn get_param_value(&self, name: &str) -> Option<DynSolValue> {
-        if self.components.is_empty() {
-            return None;
-        }
-
-        let mut current_component = self.components.clone();
-        let mut current_value = self.value.clone();
-
-        for part in name.split(".") {
-            match current_component.iter().enumerate().find(|(_, param)| param.name == part) {
-                Some((idx, value)) => {
-                    current_component = value.components.clone();
-                    current_value = current_value
-                        .as_fixed_seq()
-                        .expect("Must be a complex type")
-                        .get(idx)
-                        .expect("Complex type value must be present")
-                        .clone();
-                }
-                None => return None,
-            }
-        }
-
-        Some(current_value)
+        ParsedLog { params: vec![self.clone()] }.get_param_value(&format!("{}.{name}", self.name))
     }
 }
 
@@ -80,17 +60,126 @@ pub struct ParsedLog {
 }
 
 impl ParsedLog {
-    /// Extracts param by name. Supports deep paths like `foo.bar.baz`.
+    /// Extracts param by name. Supports deep paths like `foo.bar.baz`, `None` when the path goes
+    /// through an array as it has a value for every item, use [`Self::get_param_values`] for
+    /// those.
+    #[allow(dead_code)]
     pub fn get_param_value(&self, name: &str) -> Option<DynSolValue> {
-        match name.split_once('.') {
-            Some((root, rest)) if !rest.is_empty() => self
-                .params
+        self.get_param_values(name)
+            .filter(|values| values.len() == 1)
+            .and_then(|mut values| values.pop())
+    }
+
+    /// Extracts every value at a path. Supports deep paths like `foo.bar.baz`, and `[]` after a
+    /// name goes through each item of an array so `pools[].addr` is the `addr` of every item.
+    pub fn get_param_values(&self, path: &str) -> Option<Vec<DynSolValue>> {
+        let mut segments = path.split('.');
+        let (name, is_array) = array_path_segment(segments.next()?);
+        let param = self.params.iter().find(|param| param.name == name)?;
+
+        let mut components = param.components.clone();
+        let mut values = vec![param.value.clone()];
+        if is_array {
+            values = array_items(values)?;
+        }
+
+        for segment in segments {
+            let (name, is_array) = array_path_segment(segment);
+            let (idx, component) =
+                components.iter().enumerate().find(|(_, param)| param.name == name)?;
+            let component_components = component.components.clone();
+
+            values = values
                 .iter()
-                .find(|param| param.name == *root)
-                .and_then(|param| param.get_param_value(rest)),
-            _ => {
-                self.params.iter().find(|param| param.name == name).map(|param| param.value.clone())
+                .map(|value| value.as_fixed_seq().and_then(|values| values.get(idx)).cloned())
+                .collect::<Option<Vec<_>>>()?;
+            components = component_components;
+            if is_array {
+                values = array_items(values)?;
             }
         }
+
+        Some(values)
+    }
+}
+
+fn array_path_segment(segment: &str) -> (&str, bool) {
+    match segment.strip_suffix("[]") {
+        Some(name) => (name, true),
+        None => (segment, false),
+    }
+}
+
+fn array_items(values: Vec<DynSolValue>) -> Option<Vec<DynSolValue>> {
+    let items = values
+        .into_iter()
+        .map(|value| match value {
+            DynSolValue::Array(items) | DynSolValue::FixedArray(items) => Some(items),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(items.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    fn param(name: &str, ty: &str, components: Vec<Param>) -> Param {
+        Param { name: name.to_string(), ty: ty.to_string(), components, internal_type: None }
+    }
+
+    #[test]
+    fn test_get_param_values_through_arrays() {
+        let first = address!("0x0000000000000000000000000000000000000001");
+        let second = address!("0x0000000000000000000000000000000000000002");
+        let pool = |addr: Address| {
+            DynSolValue::Tuple(vec![DynSolValue::Uint(U256::from(500), 24), addr.into()])
+        };
+        let log = ParsedLog {
+            params: vec![
+                LogParam::new("owners".to_string(), DynSolValue::Array(vec![first.into()])),
+                LogParam {
+                    name: "pools".to_string(),
+                    value: DynSolValue::Array(vec![pool(first), pool(second)]),
+                    components: vec![
+                        param("fee", "uint24", vec![]),
+                        param("addr", "address", vec![]),
+                    ],
+                },
+            ],
+        };
+
+        assert_eq!(log.get_param_values("owners[]"), Some(vec![first.into()]));
+        assert_eq!(log.get_param_values("pools[].addr"), Some(vec![first.into(), second.into()]));
+        assert_eq!(log.get_param_values("pools.addr"), None);
+        assert_eq!(log.get_param_values("pools[].missing"), None);
+
+        assert_eq!(log.get_param_value("owners"), Some(DynSolValue::Array(vec![first.into()])));
+        assert_eq!(log.get_param_value("pools[].addr"), None);
+    }
+
+    #[test]
+    fn test_get_param_value_through_components() {
+        let owner = address!("0x0000000000000000000000000000000000000001");
+        let order = LogParam {
+            name: "order".to_string(),
+            value: DynSolValue::Tuple(vec![
+                DynSolValue::Tuple(vec![owner.into()]),
+                DynSolValue::Uint(U256::from(500), 24),
+            ]),
+            components: vec![
+                param("maker", "tuple", vec![param("owner", "address", vec![])]),
+                param("fee", "uint24", vec![]),
+            ],
+        };
+        let log = ParsedLog { params: vec![order.clone()] };
+
+        assert_eq!(order.get_param_value("maker.owner"), Some(owner.into()));
+        assert_eq!(order.get_param_value("missing"), None);
+        assert_eq!(log.get_param_value("order.fee"), Some(DynSolValue::Uint(U256::from(500), 24)));
     }
 }
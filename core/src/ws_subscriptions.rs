@@ -0,0 +1,214 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::U64,
+    providers::{Provider, RootProvider},
+    rpc::{client::RpcClient, types::Filter},
+    transports::{ws::WsConnect, Authorization},
+};
+use futures::StreamExt;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::{is_running, manifest::network::LiveSubscription, rpc_endpoints::endpoint_log_name};
+
+/// How long a subscribed live indexer waits for the socket before checking the chain anyway, a
+/// socket can stay open and still stop delivering.
+const SUBSCRIBED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+type SubscriptionProvider = Arc<RootProvider<AnyNetwork>>;
+
+/// Keeps an `eth_subscribe` websocket open for a network, so live indexing is woken up by the
+/// chain instead of polling it. While the socket is down live indexing polls as before.
+#[derive(Debug)]
+pub struct WsSubscriptions {
+    mode: LiveSubscription,
+    // the provider of the open socket, `None` while it is down
+    provider: watch::Sender<Option<SubscriptionProvider>>,
+    new_heads: watch::Sender<Option<u64>>,
+}
+
+impl WsSubscriptions {
+    pub fn start(url: String, auth: Option<Authorization>, mode: LiveSubscription) -> Arc<Self> {
+        let subscriptions = Arc::new(Self {
+            mode,
+            provider: watch::channel(None).0,
+            new_heads: watch::channel(None).0,
+        });
+        tokio::spawn(follow_new_heads(Arc::clone(&subscriptions), url, auth));
+
+        subscriptions
+    }
+
+    /// The number of the newest head the socket delivered.
+    pub fn latest_head(&self) -> Option<u64> {
+        *self.new_heads.borrow()
+    }
+
+    /// What one live indexer waits on between iterations, `filter` is only used for `logs`
+    /// subscriptions.
+    pub fn live_wake(self: &Arc<Self>, filter: Filter) -> LiveWake {
+        let logs = if self.mode == LiveSubscription::Logs {
+            let (logs_tx, logs_rx) = watch::channel(None);
+            tokio::spawn(follow_logs(self.provider.subscribe(), filter, logs_tx));
+            Some(logs_rx)
+        } else {
+            None
+        };
+
+        LiveWake {
+            connection: self.provider.subscribe(),
+            new_heads: self.new_heads.subscribe(),
+            logs,
+        }
+    }
+}
+
+fn connect(url: &str, auth: Option<Authorization>) -> WsConnect {
+    // alloy would reconnect on its own, the subscription would then silently miss the blocks in
+    // between, so a dropped socket ends the stream and is reconnected here
+    WsConnect::new(url).with_auth_opt(auth).with_max_retries(0)
+}
+
+async fn follow_new_heads(
+    subscriptions: Arc<WsSubscriptions>,
+    url: String,
+    auth: Option<Authorization>,
+) {
+    let name = endpoint_log_name(&url);
+    let mut backoff = Duration::from_secs(1);
+
+    while is_running() {
+        let provider = match RpcClient::builder().ws(connect(&url, auth.clone())).await {
+            Ok(client) => Arc::new(RootProvider::<AnyNetwork>::new(client)),
+            Err(e) => {
+                warn!("Could not open websocket to {}, polling for new blocks: {}", name, e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                continue;
+            }
+        };
+
+        match provider.subscribe_blocks().await {
+            Ok(subscription) => {
+                info!("Subscribed to new heads over websocket {}", name);
+                backoff = Duration::from_secs(1);
+                subscriptions.provider.send_replace(Some(Arc::clone(&provider)));
+
+                let mut stream = subscription.into_stream();
+                while let Some(header) = stream.next().await {
+                    if !is_running() {
+                        break;
+                    }
+                    subscriptions.new_heads.send_replace(Some(header.number));
+                }
+
+                subscriptions.provider.send_replace(None);
+                if is_running() {
+                    warn!("Websocket {} dropped, polling for new blocks until it reconnects", name);
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Could not subscribe to new heads on {}, polling for new blocks: {}",
+                    name, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Subscribes the filter on every socket the new heads task opens, publishing the highest block
+/// a matching log was seen in. Stops once the live indexer drops its `LiveWake`.
+async fn follow_logs(
+    connection: watch::Receiver<Option<SubscriptionProvider>>,
+    filter: Filter,
+    logs_tx: watch::Sender<Option<u64>>,
+) {
+    tokio::select! {
+        _ = subscribe_logs(connection, &filter, &logs_tx) => {}
+        _ = logs_tx.closed() => {}
+    }
+}
+
+async fn subscribe_logs(
+    mut connection: watch::Receiver<Option<SubscriptionProvider>>,
+    filter: &Filter,
+    logs_tx: &watch::Sender<Option<u64>>,
+) {
+    while is_running() {
+        let provider = connection.borrow_and_update().clone();
+        let Some(provider) = provider else {
+            if connection.changed().await.is_err() {
+                break;
+            }
+            continue;
+        };
+
+        match provider.subscribe_logs(filter).await {
+            Ok(subscription) => {
+                let mut stream = subscription.into_stream();
+                while let Some(log) = stream.next().await {
+                    if let Some(block_number) = log.block_number {
+                        logs_tx.send_modify(|seen| {
+                            *seen = Some(seen.map_or(block_number, |seen| seen.max(block_number)))
+                        });
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Could not subscribe to logs, waiting for the socket to reconnect: {}", e);
+            }
+        }
+
+        // the socket dropped, wait for the next one
+        if connection.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The subscriptions one live indexer sleeps on between iterations.
+#[derive(Debug)]
+pub struct LiveWake {
+    connection: watch::Receiver<Option<SubscriptionProvider>>,
+    new_heads: watch::Receiver<Option<u64>>,
+    logs: Option<watch::Receiver<Option<u64>>>,
+}
+
+impl LiveWake {
+    /// Waits until there is likely something to index from `next_block`. Returns `false`
+    /// straight away when the socket is down, the caller polls instead.
+    pub async fn wait(&mut self, next_block: U64) -> bool {
+        let LiveWake { connection, new_heads, logs } = self;
+        if connection.borrow_and_update().is_none() {
+            return false;
+        }
+
+        let next_block = next_block.to::<u64>();
+        let logs = match logs {
+            // a log still to index needs the head to move past the safe distance, so that waits
+            // on new heads
+            Some(logs) if !logs.borrow().is_some_and(|block| block >= next_block) => Some(logs),
+            _ => None,
+        };
+
+        tokio::select! {
+            _ = connection.changed() => {}
+            _ = tokio::time::sleep(SUBSCRIBED_POLL_INTERVAL) => {}
+            _ = async {
+                match logs {
+                    Some(logs) => logs.changed().await,
+                    None => new_heads.changed().await,
+                }
+            } => {}
+        }
+
+        true
+    }
+}
@@ -107,6 +107,10 @@ export default defineConfig({
               text: "Native Transfers",
               link: "/docs/start-building/yaml-config/native-transfers",
             },
+            {
+              text: "Contract Deployments",
+              link: "/docs/start-building/yaml-config/contract-deployments",
+            },
             {
               text: "Contracts",
               link: "/docs/start-building/yaml-config/contracts",
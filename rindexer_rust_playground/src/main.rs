@@ -16,6 +16,7 @@ async fn main() {
 
     let mut enable_graphql = false;
     let mut enable_indexer = false;
+    let mut replay_failed_batches = false;
 
     let mut port: Option<u16> = None;
 
@@ -29,6 +30,7 @@ async fn main() {
         match arg.as_str() {
             "--graphql" => enable_graphql = true,
             "--indexer" => enable_indexer = true,
+            "--replay-failed-batches" => replay_failed_batches = true,
             _ if arg.starts_with("--port=") || arg.starts_with("--p") => {
                 if let Some(value) = arg.split('=').nth(1) {
                     let overridden_port = value.parse::<u16>();
@@ -57,6 +59,7 @@ async fn main() {
                     Some(IndexingDetails {
                         registry: register_all_handlers(&manifest_path).await,
                         trace_registry: TraceCallbackRegistry { events: vec![] },
+                        replay_failed_batches,
                     })
                 } else {
                     None
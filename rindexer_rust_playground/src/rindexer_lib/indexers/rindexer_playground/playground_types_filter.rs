@@ -3,6 +3,7 @@ use super::super::super::typings::rindexer_playground::events::playground_types_
     PlaygroundTypesFilterEventType, SwapEvent, no_extensions,
 };
 use alloy::primitives::{I256, U256};
+use rindexer::map_block_timestamp_to_ethereum_wrapper;
 use rindexer::{
     EthereumSqlTypeWrapper, PgType, RindexerColorize,
     event::callback_registry::EventCallbackRegistry, rindexer_error, rindexer_info,
@@ -19,7 +20,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
 
             let mut postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = vec![];
             let mut csv_bulk_data: Vec<Vec<String>> = vec![];
-            for result in results.iter() {
+            for result in results.iter().filter(|result| !result.tx_information.removed) {
                 csv_bulk_data.push(vec![
                     result.tx_information.address.to_string(),
                     result.event_data.sender.to_string(),
@@ -63,6 +64,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                     EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),
                     EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),
                     EthereumSqlTypeWrapper::U256(result.tx_information.log_index),
+                    map_block_timestamp_to_ethereum_wrapper(&result.tx_information),
                 ];
                 postgres_bulk_data.push(data);
             }
@@ -103,6 +105,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                 "network".to_string(),
                 "tx_index".to_string(),
                 "log_index".to_string(),
+                "block_timestamp".to_string(),
             ];
 
             if postgres_bulk_data.len() > 100 {
@@ -5,9 +5,12 @@
 use alloy::{primitives::U64, transports::http::reqwest::header::HeaderMap};
 use rindexer::{
     lazy_static,
-    manifest::network::{AddressFiltering, BlockPollFrequency},
+    manifest::network::{AddressFiltering, BlockPollFrequency, ReorgSafeDistance, RpcEndpoint},
     notifications::ChainStateNotification,
-    provider::{JsonRpcCachedProvider, RetryClientError, RindexerProvider, create_client},
+    provider::{
+        CreateClientOptions, JsonRpcCachedProvider, RetryClientError, RindexerProvider,
+        create_client, create_client_with_endpoints,
+    },
     public_read_env_value,
     reth::node::start_reth_node_with_exex,
 };
@@ -20,25 +23,30 @@ async fn create_shadow_client(
     rpc_url: &str,
     chain_id: u64,
     compute_units_per_second: Option<u64>,
-    block_poll_frequency: Option<BlockPollFrequency>,
     max_block_range: Option<U64>,
+    block_poll_frequency: Option<BlockPollFrequency>,
+    mut custom_headers: HeaderMap,
     address_filtering: Option<AddressFiltering>,
+    reorg_safe_distance: Option<ReorgSafeDistance>,
     chain_state_notification: Option<Sender<ChainStateNotification>>,
 ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
-    let mut header = HeaderMap::new();
-    header.insert(
+    custom_headers.insert(
         "X-SHADOW-API-KEY",
         public_read_env_value("RINDEXER_PHANTOM_API_KEY").unwrap().parse().unwrap(),
     );
-    create_client(
-        rpc_url,
+    create_client_with_endpoints(
+        &[RpcEndpoint::new(rpc_url.to_string())],
         chain_id,
-        compute_units_per_second,
-        max_block_range,
-        block_poll_frequency,
-        header,
-        address_filtering,
-        chain_state_notification,
+        CreateClientOptions {
+            compute_units_per_second,
+            max_block_range,
+            block_poll_frequency,
+            custom_headers,
+            address_filtering,
+            reorg_safe_distance,
+            chain_state_notification,
+            ..Default::default()
+        },
     )
     .await
 }
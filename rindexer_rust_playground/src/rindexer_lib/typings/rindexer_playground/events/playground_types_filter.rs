@@ -287,6 +287,7 @@ where
             .index_event_in_order
             .as_ref()
             .map_or(false, |vec| vec.contains(&event_name.to_string()));
+        let on_error = contract_details.on_error_for_event(event_name);
 
         // Expect providers to have been initialized, but it's an async init so this should
         // be fast but for correctness we must await each future.
@@ -317,10 +318,21 @@ where
                         .iter()
                         .find(|n| n.name == c.network)
                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                    include_block_timestamp: contract_details
+                        .include_block_timestamp
+                        .unwrap_or_else(|| {
+                            rindexer_yaml
+                                .networks
+                                .iter()
+                                .find(|n| n.name == c.network)
+                                .map_or(false, |n| n.include_block_timestamp.unwrap_or_default())
+                        }),
+                    include_tx_context: contract_details.include_tx_context.unwrap_or_default(),
                 })
                 .collect(),
             abi: contract_details.abi,
             reorg_safe_distance: contract_details.reorg_safe_distance.unwrap_or_default(),
+            logs_fetch_mode: contract_details.logs_fetch_mode.unwrap_or_default(),
         };
 
         let callback: Arc<
@@ -340,6 +352,7 @@ where
             indexer_name: "RindexerPlayground".to_string(),
             event_name: event_name.to_string(),
             index_event_in_order,
+            on_error,
             topic_id: topic_id.parse::<B256>().unwrap(),
             contract,
             callback,